@@ -0,0 +1,45 @@
+//! Checks track files before they ship.
+//!
+//! Usage: `cargo run --bin check_track -- <track.json>...`
+//!
+//! Prints every problem found and exits non-zero when any file has an error.
+use std::{env, fs, process::ExitCode};
+use wasm::{Severity, check_track_file};
+
+fn main() -> ExitCode {
+    let files: Vec<String> = env::args().skip(1).collect();
+    if files.is_empty() {
+        eprintln!("usage: check_track <track.json>...");
+        return ExitCode::from(2);
+    }
+
+    let mut failed = false;
+    for file in &files {
+        let issues = match fs::read_to_string(file) {
+            Ok(json) => check_track_file(&json),
+            Err(err) => {
+                println!("{}: error: cannot read file: {}", file, err);
+                failed = true;
+                continue;
+            }
+        };
+        for issue in &issues {
+            println!("{}: {}", file, issue);
+        }
+        let errors = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            failed = true;
+        } else {
+            println!("{}: ok ({} warnings)", file, issues.len());
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
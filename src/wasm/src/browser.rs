@@ -7,12 +7,17 @@ use wasm_bindgen::{
 use wasm_bindgen_futures::JsFuture;
 
 use web_sys::{
-    CanvasRenderingContext2d, Document, HtmlCanvasElement, Response, Window,
+    Blob, BlobPropertyBag, CanvasRenderingContext2d, Document, HtmlAnchorElement,
+    HtmlCanvasElement, HtmlInputElement, Response, Storage, Url, UrlSearchParams, Window,
 };
 
 macro_rules! log {
     ( $( $t:tt )* ) => {
+        // Native builds (tests, tools) have no browser console
+        #[cfg(target_arch = "wasm32")]
         web_sys::console::log_1(&format!( $( $t )* ).into());
+        #[cfg(not(target_arch = "wasm32"))]
+        eprintln!( $( $t )* );
     }
 }
 
@@ -81,6 +86,78 @@ pub async fn fetch_array_buffer(resource: &str) -> Result<ArrayBuffer> {
         .map_err(|err| anyhow!("Error converting raw JSValue to ArrayBuffer {:#?}", err))
 }
 
+pub async fn fetch_text(resource: &str) -> Result<String> {
+    let text = fetch_response(resource)
+        .await?
+        .text()
+        .map_err(|err| anyhow!("Error loading text {:#?}", err))?;
+
+    JsFuture::from(text)
+        .await
+        .map_err(|err| anyhow!("Error converting text into a future {:#?}", err))?
+        .as_string()
+        .ok_or_else(|| anyhow!("Error converting {} response to String", resource))
+}
+
+/// Value of a `?name=value` parameter in the page URL
+pub fn query_param(name: &str) -> Option<String> {
+    let search = window().ok()?.location().search().ok()?;
+    UrlSearchParams::new_with_str(&search).ok()?.get(name)
+}
+
+/// Offer `text` to the user as a file download named `filename`
+pub fn download_text(filename: &str, text: &str, mime_type: &str) -> Result<()> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(text));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)
+        .map_err(|err| anyhow!("Error creating Blob {:#?}", err))?;
+    let url = Url::create_object_url_with_blob(&blob)
+        .map_err(|err| anyhow!("Error creating object URL {:#?}", err))?;
+    let anchor: HtmlAnchorElement = document()?
+        .create_element("a")
+        .map_err(|err| anyhow!("Error creating anchor {:#?}", err))?
+        .dyn_into()
+        .map_err(|element| anyhow!("Error converting {:#?} to HtmlAnchorElement", element))?;
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+    Url::revoke_object_url(&url).map_err(|err| anyhow!("Error revoking object URL {:#?}", err))
+}
+
+/// Ask the user for a file and read it as text
+///
+/// Fails with "No file chosen" if the user cancels the file dialog.
+pub async fn pick_text_file(accept: &str) -> Result<String> {
+    let input: HtmlInputElement = document()?
+        .create_element("input")
+        .map_err(|err| anyhow!("Error creating input {:#?}", err))?
+        .dyn_into()
+        .map_err(|element| anyhow!("Error converting {:#?} to HtmlInputElement", element))?;
+    input.set_type("file");
+    input.set_accept(accept);
+    let mut listening = Ok(());
+    let closed = js_sys::Promise::new(&mut |resolve, _reject| {
+        input.set_onchange(Some(resolve.unchecked_ref()));
+        // Closing the dialog without a file fires `cancel` instead of `change`
+        listening = input.add_event_listener_with_callback("cancel", &resolve);
+    });
+    listening.map_err(|err| anyhow!("Error listening for cancel {:#?}", err))?;
+    input.click();
+    JsFuture::from(closed)
+        .await
+        .map_err(|err| anyhow!("Error choosing file {:#?}", err))?;
+
+    let file = input
+        .files()
+        .and_then(|files| files.get(0))
+        .ok_or_else(|| anyhow!("No file chosen"))?;
+    JsFuture::from(file.text())
+        .await
+        .map_err(|err| anyhow!("Error reading file {:#?}", err))?
+        .as_string()
+        .ok_or_else(|| anyhow!("Error converting {} to String", file.name()))
+}
 
 pub type LoopClosure = Closure<dyn FnMut(f64)>;
 pub fn create_raf_closure(f: impl FnMut(f64) + 'static) -> LoopClosure {
@@ -97,6 +174,24 @@ pub fn closure_wrap<T: WasmClosure + ?Sized>(data: Box<T>) -> Closure<T> {
     Closure::wrap(data)
 }
 
+pub fn local_storage() -> Result<Storage> {
+    window()?
+        .local_storage()
+        .map_err(|err| anyhow!("Error getting localStorage {:#?}", err))?
+        .ok_or_else(|| anyhow!("No localStorage found"))
+}
+
+/// Today's date in the browser's time zone as `YYYY-MM-DD`
+pub fn today() -> String {
+    let date = js_sys::Date::new_0();
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.get_full_year(),
+        date.get_month() + 1,
+        date.get_date()
+    )
+}
+
 pub fn now() -> Result<f64> {
     Ok(window()?
         .performance()
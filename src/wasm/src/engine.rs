@@ -8,14 +8,19 @@ use futures::channel::{
     mpsc::{UnboundedReceiver, unbounded},
     //oneshot::channel,
 };
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, HashMap},
+    rc::Rc,
+};
 use wasm_bindgen::JsCast;
 use web_sys::CanvasRenderingContext2d;
 use web_sys::{AudioBuffer, AudioContext};
 
 pub const FONT_COLOR: &str = "green";
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -32,7 +37,7 @@ impl Point {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Velocity {
     pub x: f32,
     pub y: f32,
@@ -49,93 +54,66 @@ impl Line {
     }
 }
 
-pub struct Renderer {
-    context: CanvasRenderingContext2d,
-}
+/// Drawing backend used by every game element.
+///
+/// Implementors only provide the primitive shapes; the racing cars and fruit
+/// trees are composed from them by the provided methods. Coordinates are in
+/// game space, where `y` grows upwards from the bottom of the canvas.
+pub trait Renderer {
+    fn clear(&self, point: &Point, width: f32, height: f32);
+    fn text(&self, point: &Point, text: &str, color: &str, font: &str, align: &str);
+    fn line(&self, p: &Point, q: &Point);
+    /// Draw a circle centred on `center`
+    fn arc(&self, center: &Point, radius: f64, fill: Option<&str>, stroke: &str, line_width: f64);
+    /// Draw an axis-aligned ellipse centred on `center`
+    fn ellipse(
+        &self,
+        center: &Point,
+        radius_x: f64,
+        radius_y: f64,
+        fill: Option<&str>,
+        stroke: &str,
+        line_width: f64,
+    );
+    /// Draw a diamond whose corners are `size` away from `center`
+    fn diamond(&self, center: &Point, size: f64, fill: &str, stroke: &str, line_width: f64);
+    /// Set the opacity of everything drawn afterwards (1.0 is opaque)
+    fn set_alpha(&self, alpha: f64);
+    /// Draw a rectangle centred on `center`
+    fn rect(
+        &self,
+        center: &Point,
+        width: f64,
+        height: f64,
+        fill: &str,
+        stroke: &str,
+        line_width: f64,
+    );
+    /// Fill the polygon with corners `points`, without an outline
+    fn polygon(&self, points: &[Point], fill: &str);
 
-impl Renderer {
     /// Draw a small filled wheel (o) at the specified position
-    pub fn draw_small_wheel(&self, point: &Point, color: &str) {
-        self.context.set_fill_style_str(color);
-        self.context.begin_path();
-        self.context
-            .arc(
-                point.x as f64,
-                CANVAS_HEIGHT as f64 - point.y as f64,
-                3.0, // radius
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap_or(());
-        self.context.fill();
-
-        // Add stroke for better visibility
-        self.context.set_stroke_style_str("#1a3f2a");
-        self.context.set_line_width(0.5);
-        self.context.stroke();
+    fn draw_small_wheel(&self, point: &Point, color: &str) {
+        self.arc(point, 3.0, Some(color), "#1a3f2a", 0.5);
     }
 
     /// Draw a large hollow wheel (O) at the specified position
-    pub fn draw_large_wheel(&self, point: &Point, color: &str) {
-        self.context.set_stroke_style_str(color);
-        self.context.set_line_width(2.0);
-        self.context.begin_path();
-        self.context
-            .arc(
-                point.x as f64,
-                CANVAS_HEIGHT as f64 - point.y as f64,
-                4.0, // radius
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap_or(());
-        self.context.stroke();
+    fn draw_large_wheel(&self, point: &Point, color: &str) {
+        self.arc(point, 4.0, None, color, 2.0);
     }
 
     /// Draw a center body (●) at the specified position
-    pub fn draw_center_body(&self, point: &Point, color: &str) {
-        self.context.set_fill_style_str(color);
-        self.context.begin_path();
-        self.context
-            .arc(
-                point.x as f64,
-                CANVAS_HEIGHT as f64 - point.y as f64,
-                2.5, // radius
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap_or(());
-        self.context.fill();
-
-        // Add stroke for better definition
-        self.context.set_stroke_style_str("#aa2222");
-        self.context.set_line_width(0.5);
-        self.context.stroke();
+    fn draw_center_body(&self, point: &Point, color: &str) {
+        self.arc(point, 2.5, Some(color), "#aa2222", 0.5);
     }
 
     /// Draw a diamond body (◆) at the specified position
-    pub fn draw_diamond_body(&self, point: &Point, color: &str) {
-        let canvas_y = CANVAS_HEIGHT as f64 - point.y as f64;
-
-        self.context.set_fill_style_str(color);
-        self.context.begin_path();
-
-        // Diamond shape: top, right, bottom, left
-        self.context.move_to(point.x as f64, canvas_y - 8.0); // top
-        self.context.line_to(point.x as f64 + 8.0, canvas_y); // right
-        self.context.line_to(point.x as f64, canvas_y + 8.0); // bottom
-        self.context.line_to(point.x as f64 - 8.0, canvas_y); // left
-        self.context.close_path();
-        self.context.fill();
-
-        // Add stroke
-        self.context.set_stroke_style_str("#2a5f41");
-        self.context.set_line_width(1.0);
-        self.context.stroke();
+    fn draw_diamond_body(&self, point: &Point, color: &str) {
+        self.diamond(point, 8.0, color, "#2a5f41", 1.0);
     }
 
     /// Draw racing car in normal state (o●o / ◆ / O●O)
-    pub fn draw_normal_racing_car(&self, position: &Point) {
+    fn draw_normal_racing_car(&self, position: &Point) {
         let wheel_color = "#2a5f41"; // Green wheels
         let body_color = "#cc3333"; // Red body
         let diamond_color = "#4a9f6a"; // Green diamond
@@ -200,7 +178,7 @@ impl Renderer {
     }
 
     /// Draw rival racing car in normal state with blue color (o●o / ◆ / O●O)
-    pub fn draw_blue_normal_racing_car(&self, position: &Point) {
+    fn draw_blue_normal_racing_car(&self, position: &Point) {
         let wheel_color = "#1a4f5a"; // Dark blue wheels
         let body_color = "#3366cc"; // Blue body
         let diamond_color = "#4a9f6a"; // Green diamond
@@ -265,7 +243,7 @@ impl Renderer {
     }
 
     /// Draw racing car in knocked/damaged state (O● O / ◆ / o ●o)
-    pub fn draw_knocked_racing_car(&self, position: &Point) {
+    fn draw_knocked_racing_car(&self, position: &Point) {
         let wheel_color = "#2a5f41"; // Green wheels
         let body_color = "#cc3333"; // Red body
         let diamond_color = "#4a9f6a"; // Green diamond
@@ -330,7 +308,7 @@ impl Renderer {
     }
 
     /// Draw racing car facing left (with perspective)
-    pub fn draw_left_facing_racing_car(&self, position: &Point) {
+    fn draw_left_facing_racing_car(&self, position: &Point) {
         let wheel_color = "#2a5f41";
         let body_color = "#cc3333";
         let diamond_color = "#4a9f6a";
@@ -407,7 +385,7 @@ impl Renderer {
     }
 
     /// Draw blue rival racing car facing left (with perspective)
-    pub fn draw_blue_left_facing_racing_car(&self, position: &Point) {
+    fn draw_blue_left_facing_racing_car(&self, position: &Point) {
         let wheel_color = "#1a4f5a"; // Dark blue wheels
         let body_color = "#3366cc"; // Blue body
         let diamond_color = "#4a9f6a"; // Green diamond
@@ -484,7 +462,7 @@ impl Renderer {
     }
 
     /// Draw racing car facing right (with perspective)
-    pub fn draw_right_facing_racing_car(&self, position: &Point) {
+    fn draw_right_facing_racing_car(&self, position: &Point) {
         let wheel_color = "#2a5f41";
         let body_color = "#cc3333";
         let diamond_color = "#4a9f6a";
@@ -561,7 +539,7 @@ impl Renderer {
     }
 
     /// Draw blue rival racing car facing right (with perspective)
-    pub fn draw_blue_right_facing_racing_car(&self, position: &Point) {
+    fn draw_blue_right_facing_racing_car(&self, position: &Point) {
         let wheel_color = "#1a4f5a"; // Dark blue wheels
         let body_color = "#3366cc"; // Blue body
         let diamond_color = "#4a9f6a";
@@ -637,113 +615,8 @@ impl Renderer {
         );
     }
 
-    /// Helper method to draw elliptical wheels for perspective views
-    fn draw_ellipse_wheel(
-        &self,
-        point: &Point,
-        radius_x: f64,
-        radius_y: f64,
-        color: &str,
-        filled: bool,
-    ) {
-        let canvas_y = CANVAS_HEIGHT as f64 - point.y as f64;
-
-        if filled {
-            self.context.set_fill_style_str(color);
-        }
-        self.context.set_stroke_style_str(color);
-        self.context.set_line_width(if filled { 0.5 } else { 2.0 });
-
-        self.context.begin_path();
-        self.context
-            .ellipse(
-                point.x as f64,
-                canvas_y,
-                radius_x,
-                radius_y,
-                0.0,                        // rotation
-                0.0,                        // start angle
-                std::f64::consts::PI * 2.0, // end angle
-            )
-            .unwrap_or(());
-
-        if filled {
-            self.context.fill();
-        }
-        self.context.stroke();
-    }
-
-    /// Draw a tree trunk (▯) at the specified position
-    pub fn draw_tree_trunk(&self, point: &Point, color: &str) {
-        let canvas_y = CANVAS_HEIGHT as f64 - point.y as f64;
-
-        self.context.set_fill_style_str(color);
-        self.context.set_stroke_style_str("#8B4513"); // Brown stroke
-        self.context.set_line_width(1.0);
-
-        self.context.begin_path();
-        // Rectangle trunk: width=8, height=12
-        self.context.rect(
-            point.x as f64 - 4.0, // x - width/2
-            canvas_y - 6.0,       // y - height/2
-            8.0,                  // width
-            12.0,                 // height
-        );
-        self.context.fill();
-        self.context.stroke();
-    }
-
-    /// Draw tree leaves ($) as a circle at the specified position
-    pub fn draw_tree_leaves(&self, point: &Point, color: &str, radius: f32) {
-        self.context.set_fill_style_str(color);
-        self.context.set_stroke_style_str("#2d5016"); // Dark green stroke
-        self.context.set_line_width(0.5);
-
-        self.context.begin_path();
-        self.context
-            .arc(
-                point.x as f64,
-                CANVAS_HEIGHT as f64 - point.y as f64,
-                radius as f64,
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap_or(());
-        self.context.fill();
-        self.context.stroke();
-    }
-
-    /// Draw a fruit on tree (various colored circles for different fruits)
-    pub fn draw_fruit(&self, point: &Point, fruit_type: &str) {
-        let (color, stroke_color, radius) = match fruit_type {
-            "apple" => ("#ff4444", "#cc2222", 3.0),  // Red apple
-            "orange" => ("#ff8800", "#dd6600", 3.5), // Orange
-            "cherry" => ("#dd0000", "#aa0000", 2.5), // Red cherry
-            "lemon" => ("#ffff44", "#dddd22", 3.0),  // Yellow lemon
-            "plum" => ("#8844ff", "#6622dd", 3.0),   // Purple plum
-            _ => ("#44ff44", "#22dd22", 3.0),        // Default green
-        };
-
-        self.context.set_fill_style_str(color);
-        self.context.set_stroke_style_str(stroke_color);
-        self.context.set_line_width(0.8);
-
-        self.context.begin_path();
-        self.context
-            .arc(
-                point.x as f64,
-                CANVAS_HEIGHT as f64 - point.y as f64,
-                radius,
-                0.0,
-                std::f64::consts::PI * 2.0,
-            )
-            .unwrap_or(());
-        self.context.fill();
-        self.context.stroke();
-    }
-
     /// Draw a complete fruit tree at the specified position
-    pub fn draw_fruit_tree(&self, position: &Point, fruit_type: &str) {
+    fn draw_fruit_tree(&self, position: &Point, fruit_type: &str) {
         let tree_distance = 12.0;
 
         // Layer 1: " $ " - Single small leaf cluster
@@ -857,14 +730,75 @@ impl Renderer {
             "#8b4513", // Saddle brown
         );
     }
+
+    /// Helper method to draw elliptical wheels for perspective views
+    fn draw_ellipse_wheel(
+        &self,
+        point: &Point,
+        radius_x: f64,
+        radius_y: f64,
+        color: &str,
+        filled: bool,
+    ) {
+        self.ellipse(
+            point,
+            radius_x,
+            radius_y,
+            if filled { Some(color) } else { None },
+            color,
+            if filled { 0.5 } else { 2.0 },
+        );
+    }
+
+    /// Draw a tree trunk (▯) at the specified position
+    fn draw_tree_trunk(&self, point: &Point, color: &str) {
+        // Rectangle trunk: width=8, height=12
+        self.rect(point, 8.0, 12.0, color, "#8B4513", 1.0);
+    }
+
+    /// Draw tree leaves ($) as a circle at the specified position
+    fn draw_tree_leaves(&self, point: &Point, color: &str, radius: f32) {
+        self.arc(point, radius as f64, Some(color), "#2d5016", 0.5);
+    }
+
+    /// Draw a fruit on tree (various colored circles for different fruits)
+    fn draw_fruit(&self, point: &Point, fruit_type: &str) {
+        let (color, stroke_color, radius) = match fruit_type {
+            "apple" => ("#ff4444", "#cc2222", 3.0),  // Red apple
+            "orange" => ("#ff8800", "#dd6600", 3.5), // Orange
+            "cherry" => ("#dd0000", "#aa0000", 2.5), // Red cherry
+            "lemon" => ("#ffff44", "#dddd22", 3.0),  // Yellow lemon
+            "plum" => ("#8844ff", "#6622dd", 3.0),   // Purple plum
+            _ => ("#44ff44", "#22dd22", 3.0),        // Default green
+        };
+        self.arc(point, radius, Some(color), stroke_color, 0.8);
+    }
 }
 
-impl Renderer {
-    pub fn clear(&self, point: &Point, width: f32, height: f32) {
+/// `Renderer` drawing on the game's `<canvas>` element
+pub struct CanvasRenderer {
+    context: CanvasRenderingContext2d,
+}
+
+impl CanvasRenderer {
+    /// Fill (if any) and stroke the current path
+    fn paint(&self, fill: Option<&str>, stroke: &str, line_width: f64) {
+        if let Some(fill) = fill {
+            self.context.set_fill_style_str(fill);
+            self.context.fill();
+        }
+        self.context.set_stroke_style_str(stroke);
+        self.context.set_line_width(line_width);
+        self.context.stroke();
+    }
+}
+
+impl Renderer for CanvasRenderer {
+    fn clear(&self, point: &Point, width: f32, height: f32) {
         self.context
             .clear_rect(point.x.into(), point.y.into(), width as f64, height as f64);
     }
-    pub fn text(&self, point: &Point, text: &str, color: &str, font: &str, align: &str) {
+    fn text(&self, point: &Point, text: &str, color: &str, font: &str, align: &str) {
         self.context.set_fill_style_str(color);
         self.context.set_text_align(align);
         self.context.set_text_baseline("middle");
@@ -873,7 +807,7 @@ impl Renderer {
             .context
             .fill_text(text, point.x as f64, CANVAS_HEIGHT as f64 - point.y as f64);
     }
-    pub fn line(&self, p: &Point, q: &Point) {
+    fn line(&self, p: &Point, q: &Point) {
         self.context.begin_path();
         self.context.set_stroke_style_str(FONT_COLOR);
         self.context
@@ -883,32 +817,518 @@ impl Renderer {
         self.context.close_path();
         self.context.stroke();
     }
+    fn arc(&self, center: &Point, radius: f64, fill: Option<&str>, stroke: &str, line_width: f64) {
+        self.context.begin_path();
+        self.context
+            .arc(
+                center.x as f64,
+                CANVAS_HEIGHT as f64 - center.y as f64,
+                radius,
+                0.0,
+                std::f64::consts::PI * 2.0,
+            )
+            .unwrap_or(());
+        self.paint(fill, stroke, line_width);
+    }
+    fn ellipse(
+        &self,
+        center: &Point,
+        radius_x: f64,
+        radius_y: f64,
+        fill: Option<&str>,
+        stroke: &str,
+        line_width: f64,
+    ) {
+        self.context.begin_path();
+        self.context
+            .ellipse(
+                center.x as f64,
+                CANVAS_HEIGHT as f64 - center.y as f64,
+                radius_x,
+                radius_y,
+                0.0,                        // rotation
+                0.0,                        // start angle
+                std::f64::consts::PI * 2.0, // end angle
+            )
+            .unwrap_or(());
+        self.paint(fill, stroke, line_width);
+    }
+    fn diamond(&self, center: &Point, size: f64, fill: &str, stroke: &str, line_width: f64) {
+        let canvas_y = CANVAS_HEIGHT as f64 - center.y as f64;
+
+        self.context.begin_path();
+        // Diamond shape: top, right, bottom, left
+        self.context.move_to(center.x as f64, canvas_y - size); // top
+        self.context.line_to(center.x as f64 + size, canvas_y); // right
+        self.context.line_to(center.x as f64, canvas_y + size); // bottom
+        self.context.line_to(center.x as f64 - size, canvas_y); // left
+        self.context.close_path();
+        self.paint(Some(fill), stroke, line_width);
+    }
+    fn set_alpha(&self, alpha: f64) {
+        self.context.set_global_alpha(alpha);
+    }
+    fn rect(
+        &self,
+        center: &Point,
+        width: f64,
+        height: f64,
+        fill: &str,
+        stroke: &str,
+        line_width: f64,
+    ) {
+        self.context.begin_path();
+        self.context.rect(
+            center.x as f64 - width / 2.0,
+            CANVAS_HEIGHT as f64 - center.y as f64 - height / 2.0,
+            width,
+            height,
+        );
+        self.paint(Some(fill), stroke, line_width);
+    }
+    fn polygon(&self, points: &[Point], fill: &str) {
+        let Some((first, rest)) = points.split_first() else {
+            return;
+        };
+        self.context.begin_path();
+        self.context
+            .move_to(first.x.into(), CANVAS_HEIGHT as f64 - first.y as f64);
+        for point in rest {
+            self.context
+                .line_to(point.x.into(), CANVAS_HEIGHT as f64 - point.y as f64);
+        }
+        self.context.close_path();
+        self.context.set_fill_style_str(fill);
+        self.context.fill();
+    }
+}
+
+/// A single call recorded by `RecordingRenderer`
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Clear {
+        point: Point,
+        width: f32,
+        height: f32,
+    },
+    Text {
+        point: Point,
+        text: String,
+        color: String,
+        font: String,
+        align: String,
+    },
+    Line {
+        p: Point,
+        q: Point,
+    },
+    Arc {
+        center: Point,
+        radius: f64,
+        fill: Option<String>,
+        stroke: String,
+        line_width: f64,
+    },
+    Ellipse {
+        center: Point,
+        radius_x: f64,
+        radius_y: f64,
+        fill: Option<String>,
+        stroke: String,
+        line_width: f64,
+    },
+    Diamond {
+        center: Point,
+        size: f64,
+        fill: String,
+        stroke: String,
+        line_width: f64,
+    },
+    Alpha {
+        alpha: f64,
+    },
+    Rect {
+        center: Point,
+        width: f64,
+        height: f64,
+        fill: String,
+        stroke: String,
+        line_width: f64,
+    },
+    Polygon {
+        points: Vec<Point>,
+        fill: String,
+    },
+    Tree {
+        position: Point,
+        fruit_type: String,
+    },
+}
+
+/// `Renderer` that keeps a list of draw commands instead of drawing.
+///
+/// Meant for snapshot tests: draw a frame, then compare `dump()` with a
+/// golden file. Fruit trees are recorded as a single `Tree` command.
+#[derive(Default)]
+pub struct RecordingRenderer {
+    commands: RefCell<Vec<DrawCommand>>,
+}
+
+impl RecordingRenderer {
+    pub fn new() -> Self {
+        RecordingRenderer {
+            commands: RefCell::new(vec![]),
+        }
+    }
+    /// Commands recorded so far, in drawing order
+    pub fn commands(&self) -> Vec<DrawCommand> {
+        self.commands.borrow().clone()
+    }
+    /// Recorded commands, one per line
+    pub fn dump(&self) -> String {
+        self.commands
+            .borrow()
+            .iter()
+            .map(|command| format!("{:?}\n", command))
+            .collect()
+    }
+    /// Compares the recorded commands with the golden file `snapshots/<name>`;
+    /// run with `UPDATE_SNAPSHOTS=1` to rewrite the file instead
+    #[cfg(test)]
+    pub fn assert_snapshot(&self, name: &str) {
+        let _path = format!("{}/snapshots/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let _dump = self.dump();
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&_path, &_dump).unwrap();
+            return;
+        }
+        let _expected = std::fs::read_to_string(&_path)
+            .unwrap_or_else(|_err| panic!("missing snapshot {}: {}", _path, _err));
+        assert_eq!(_dump, _expected, "snapshot {} differs", name);
+    }
+    fn record(&self, command: DrawCommand) {
+        self.commands.borrow_mut().push(command);
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn clear(&self, point: &Point, width: f32, height: f32) {
+        self.record(DrawCommand::Clear {
+            point: *point,
+            width,
+            height,
+        });
+    }
+    fn text(&self, point: &Point, text: &str, color: &str, font: &str, align: &str) {
+        self.record(DrawCommand::Text {
+            point: *point,
+            text: text.into(),
+            color: color.into(),
+            font: font.into(),
+            align: align.into(),
+        });
+    }
+    fn line(&self, p: &Point, q: &Point) {
+        self.record(DrawCommand::Line { p: *p, q: *q });
+    }
+    fn arc(&self, center: &Point, radius: f64, fill: Option<&str>, stroke: &str, line_width: f64) {
+        self.record(DrawCommand::Arc {
+            center: *center,
+            radius,
+            fill: fill.map(String::from),
+            stroke: stroke.into(),
+            line_width,
+        });
+    }
+    fn ellipse(
+        &self,
+        center: &Point,
+        radius_x: f64,
+        radius_y: f64,
+        fill: Option<&str>,
+        stroke: &str,
+        line_width: f64,
+    ) {
+        self.record(DrawCommand::Ellipse {
+            center: *center,
+            radius_x,
+            radius_y,
+            fill: fill.map(String::from),
+            stroke: stroke.into(),
+            line_width,
+        });
+    }
+    fn diamond(&self, center: &Point, size: f64, fill: &str, stroke: &str, line_width: f64) {
+        self.record(DrawCommand::Diamond {
+            center: *center,
+            size,
+            fill: fill.into(),
+            stroke: stroke.into(),
+            line_width,
+        });
+    }
+    fn set_alpha(&self, alpha: f64) {
+        self.record(DrawCommand::Alpha { alpha });
+    }
+    fn rect(
+        &self,
+        center: &Point,
+        width: f64,
+        height: f64,
+        fill: &str,
+        stroke: &str,
+        line_width: f64,
+    ) {
+        self.record(DrawCommand::Rect {
+            center: *center,
+            width,
+            height,
+            fill: fill.into(),
+            stroke: stroke.into(),
+            line_width,
+        });
+    }
+    fn polygon(&self, points: &[Point], fill: &str) {
+        self.record(DrawCommand::Polygon {
+            points: points.to_vec(),
+            fill: fill.into(),
+        });
+    }
+    fn draw_fruit_tree(&self, position: &Point, fruit_type: &str) {
+        self.record(DrawCommand::Tree {
+            position: *position,
+            fruit_type: fruit_type.into(),
+        });
+    }
 }
 
 #[async_trait(?Send)]
 pub trait Game {
     async fn initialize(&self) -> Result<Box<dyn Game>>;
-    fn update(&mut self, keystate: &KeyState);
-    fn draw(&self, renderer: &Renderer);
+    fn update(&mut self, keystate: &KeyState, mouse: &MouseState);
+    fn draw(&self, renderer: &dyn Renderer);
+}
+
+/// Source of the current time in milliseconds.
+///
+/// The game loop paces fixed steps with it, so a simulated clock makes the
+/// loop deterministic in tests and replays.
+pub trait Clock {
+    fn now(&self) -> Result<f64>;
+    /// Calendar date as `YYYY-MM-DD`, used to stamp high scores
+    fn today(&self) -> String;
+}
+
+/// `Clock` backed by `performance.now()`
+pub struct BrowserClock;
+impl Clock for BrowserClock {
+    fn now(&self) -> Result<f64> {
+        browser::now()
+    }
+    fn today(&self) -> String {
+        browser::today()
+    }
+}
+
+/// `Clock` that only moves when told to
+#[derive(Default)]
+pub struct SimulatedClock {
+    time: Cell<f64>,
+}
+impl SimulatedClock {
+    pub fn new(time: f64) -> Self {
+        SimulatedClock {
+            time: Cell::new(time),
+        }
+    }
+    /// Move the clock forward by `delta` milliseconds
+    pub fn advance(&self, delta: f64) {
+        self.time.set(self.time.get() + delta);
+    }
+}
+impl Clock for SimulatedClock {
+    fn now(&self) -> Result<f64> {
+        Ok(self.time.get())
+    }
+    /// The simulated time is read as milliseconds since the Unix epoch
+    fn today(&self) -> String {
+        // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let z = (self.time.get() / 86_400_000.0).floor() as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Small key-value store that survives page reloads
+pub trait Storage {
+    fn get(&self, key: &str) -> Result<Option<String>>;
+    fn set(&self, key: &str, value: &str) -> Result<()>;
+}
+
+/// `Storage` backed by `window.localStorage`
+pub struct LocalStorage;
+impl Storage for LocalStorage {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        browser::local_storage()?
+            .get_item(key)
+            .map_err(|err| anyhow!("Error reading {} from localStorage {:#?}", key, err))
+    }
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        browser::local_storage()?
+            .set_item(key, value)
+            .map_err(|err| anyhow!("Error writing {} to localStorage {:#?}", key, err))
+    }
+}
+
+/// `Storage` kept in memory, for tests and headless runs
+#[derive(Default)]
+pub struct MemoryStorage {
+    items: RefCell<HashMap<String, String>>,
+}
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage {
+            items: RefCell::new(HashMap::new()),
+        }
+    }
+}
+impl Storage for MemoryStorage {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.items.borrow().get(key).cloned())
+    }
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.items.borrow_mut().insert(key.into(), value.into());
+        Ok(())
+    }
+}
+/// A shared `Storage` lets boards loaded one after another see each other's
+/// saves, as a page reload would
+impl<S: Storage + ?Sized> Storage for Rc<S> {
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        self.as_ref().get(key)
+    }
+    fn set(&self, key: &str, value: &str) -> Result<()> {
+        self.as_ref().set(key, value)
+    }
+}
+/// A shared clock keeps ticking for every owner, so a test can advance the
+/// clock a `GameLoop` paces itself with
+impl<C: Clock + ?Sized> Clock for Rc<C> {
+    fn now(&self) -> Result<f64> {
+        self.as_ref().now()
+    }
+    fn today(&self) -> String {
+        self.as_ref().today()
+    }
+}
+
+/// Destination of the files a player saves from the game.
+///
+/// The game only offers saving when it was given one, so a native build or
+/// a test never reaches for the browser.
+pub trait Downloads {
+    /// Offer `text` to the player as a file named `filename`
+    fn save_text(&self, filename: &str, text: &str, mime_type: &str) -> Result<()>;
+}
+
+/// `Downloads` that hands the file to the browser's download manager
+pub struct BrowserDownloads;
+impl Downloads for BrowserDownloads {
+    fn save_text(&self, filename: &str, text: &str, mime_type: &str) -> Result<()> {
+        browser::download_text(filename, text, mime_type)
+    }
 }
 
-const FRAME_SIZE: f64 = 1.0 / 60.0 * 1000.0;
+/// `Downloads` that keeps every saved file in memory
+#[derive(Default)]
+pub struct MemoryDownloads {
+    files: RefCell<Vec<(String, String)>>,
+}
+impl MemoryDownloads {
+    /// Name and text of every file saved so far, in saving order
+    pub fn files(&self) -> Vec<(String, String)> {
+        self.files.borrow().clone()
+    }
+}
+impl Downloads for MemoryDownloads {
+    fn save_text(&self, filename: &str, text: &str, _mime_type: &str) -> Result<()> {
+        self.files.borrow_mut().push((filename.into(), text.into()));
+        Ok(())
+    }
+}
+/// A shared `Downloads` lets a test look at what the game saved
+impl<D: Downloads + ?Sized> Downloads for Rc<D> {
+    fn save_text(&self, filename: &str, text: &str, mime_type: &str) -> Result<()> {
+        self.as_ref().save_text(filename, text, mime_type)
+    }
+}
+
+/// Length of one fixed update step in milliseconds
+pub const FRAME_SIZE: f64 = 1.0 / 60.0 * 1000.0;
+/// Most fixed steps run by one tick; a longer stall (a background tab, a
+/// breakpoint) is dropped instead of being caught up all at once
+pub const MAX_STEPS_PER_TICK: u32 = 10;
 pub struct GameLoop {
+    clock: Box<dyn Clock>,
     last_frame: f64,
     accumulated_delta: f64,
 }
 type SharedLoopClosure = Rc<RefCell<Option<LoopClosure>>>;
 
 impl GameLoop {
+    /// Create a game loop paced by `clock`
+    pub fn new(clock: Box<dyn Clock>) -> Result<Self> {
+        Ok(GameLoop {
+            last_frame: clock.now()?,
+            accumulated_delta: 0.0,
+            clock,
+        })
+    }
+
+    /// Run every fixed step due since the previous call, at most
+    /// `MAX_STEPS_PER_TICK` of them; the time left over is carried to the
+    /// next call
+    ///
+    /// # Returns
+    /// The number of `Game::update` calls made
+    pub fn tick(
+        &mut self,
+        game: &mut dyn Game,
+        keystate: &KeyState,
+        mouse: &MouseState,
+    ) -> Result<u32> {
+        let now = self.clock.now()?;
+        self.accumulated_delta += now - self.last_frame;
+        self.last_frame = now;
+
+        let mut steps = 0;
+        while self.accumulated_delta > FRAME_SIZE {
+            if steps == MAX_STEPS_PER_TICK {
+                // Drop the backlog rather than fall further behind
+                self.accumulated_delta %= FRAME_SIZE;
+                break;
+            }
+            game.update(keystate, mouse);
+            self.accumulated_delta -= FRAME_SIZE;
+            steps += 1;
+        }
+        Ok(steps)
+    }
+
     pub async fn start(game: impl Game + 'static) -> Result<()> {
         let mut keyevent_receiver = prepare_input()?;
+        let mut mouseevent_receiver = prepare_mouse_input()?;
         let mut game = game.initialize().await?;
-        let mut game_loop = GameLoop {
-            last_frame: browser::now()?,
-            accumulated_delta: 0.0,
-        };
+        let mut game_loop = GameLoop::new(Box::new(BrowserClock))?;
 
-        let renderer = Renderer {
+        let renderer = CanvasRenderer {
             context: browser::context()?,
         };
 
@@ -916,17 +1336,15 @@ impl GameLoop {
         let g = f.clone();
 
         let mut keystate = KeyState::new();
-        *g.borrow_mut() = Some(browser::create_raf_closure(move |perf: f64| {
+        let mut mouse = MouseState::new();
+        *g.borrow_mut() = Some(browser::create_raf_closure(move |_perf: f64| {
             process_input(&mut keystate, &mut keyevent_receiver);
+            process_mouse_input(&mut mouse, &mut mouseevent_receiver);
 
-            game_loop.accumulated_delta += perf - game_loop.last_frame;
-            while game_loop.accumulated_delta > FRAME_SIZE {
-                game.update(&keystate);
-                game_loop.accumulated_delta -= FRAME_SIZE;
+            if let Err(err) = game_loop.tick(game.as_mut(), &keystate, &mouse) {
+                log!("Error running game loop {:#?}", err);
             }
-            let _last_frame = browser::now().unwrap();
             game.draw(&renderer);
-            game_loop.last_frame = _last_frame;
 
             let _ = browser::request_animation_frame(f.borrow().as_ref().unwrap());
         }));
@@ -940,47 +1358,156 @@ impl GameLoop {
     }
 }
 
+/// Key codes held down at a given moment.
+///
+/// Only the `KeyboardEvent.code` strings are kept, so a `KeyState` can be
+/// built without a browser and serialized into a replay.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct KeyState {
-    pressed_keys: HashMap<String, web_sys::KeyboardEvent>,
+    pressed_keys: BTreeSet<String>,
 }
 
 impl KeyState {
-    fn new() -> Self {
+    pub fn new() -> Self {
         KeyState {
-            pressed_keys: HashMap::new(),
+            pressed_keys: BTreeSet::new(),
         }
     }
     pub fn is_pressed(&self, code: &str) -> bool {
-        self.pressed_keys.contains_key(code)
+        self.pressed_keys.contains(code)
     }
 
-    fn set_pressed(&mut self, code: &str, event: web_sys::KeyboardEvent) {
-        self.pressed_keys.insert(code.into(), event);
+    pub fn set_pressed(&mut self, code: &str) {
+        self.pressed_keys.insert(code.into());
     }
 
-    fn set_released(&mut self, code: &str) {
+    pub fn set_released(&mut self, code: &str) {
         self.pressed_keys.remove(code);
     }
 }
 
+/// Mouse pointer over the canvas at a given moment.
+///
+/// The position is in game space, like everything handed to a `Renderer`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MouseState {
+    position: Option<Point>,
+    pressed: bool,
+}
+
+impl MouseState {
+    pub fn new() -> Self {
+        MouseState {
+            position: None,
+            pressed: false,
+        }
+    }
+    /// Pointer position, `None` until the mouse has moved over the canvas
+    pub fn position(&self) -> Option<Point> {
+        self.position
+    }
+    /// Whether the main button is held down
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+    pub fn set_position(&mut self, position: Point) {
+        self.position = Some(position);
+    }
+    pub fn set_pressed(&mut self, pressed: bool) {
+        self.pressed = pressed;
+    }
+}
+
 enum KeyPress {
     KeyUp(web_sys::KeyboardEvent),
     KeyDown(web_sys::KeyboardEvent),
 }
 
 fn process_input(state: &mut KeyState, keyevent_receiver: &mut UnboundedReceiver<KeyPress>) {
-    loop {
-        match keyevent_receiver.try_next() {
-            Ok(None) => break,
-            Err(_err) => break,
-            Ok(Some(evt)) => match evt {
-                KeyPress::KeyUp(evt) => state.set_released(&evt.code()),
-                KeyPress::KeyDown(evt) => state.set_pressed(&evt.code(), evt),
-            },
+    while let Ok(evt) = keyevent_receiver.try_recv() {
+        match evt {
+            KeyPress::KeyUp(evt) => state.set_released(&evt.code()),
+            KeyPress::KeyDown(evt) => state.set_pressed(&evt.code()),
         };
     }
 }
 
+enum MousePress {
+    Down(Point),
+    Up(Point),
+    Move(Point),
+}
+
+fn process_mouse_input(
+    state: &mut MouseState,
+    mouseevent_receiver: &mut UnboundedReceiver<MousePress>,
+) {
+    while let Ok(evt) = mouseevent_receiver.try_recv() {
+        match evt {
+            MousePress::Down(point) => {
+                state.set_position(point);
+                state.set_pressed(true);
+            }
+            MousePress::Up(point) => {
+                state.set_position(point);
+                state.set_pressed(false);
+            }
+            MousePress::Move(point) => state.set_position(point),
+        }
+    }
+}
+
+/// Convert a mouse event position on the canvas element into game space
+fn mouse_point(canvas: &web_sys::HtmlCanvasElement, evt: &web_sys::MouseEvent) -> Point {
+    // The canvas may be scaled by CSS
+    let scale_x = canvas.width() as f32 / canvas.client_width().max(1) as f32;
+    let scale_y = canvas.height() as f32 / canvas.client_height().max(1) as f32;
+    Point::new(
+        evt.offset_x() as f32 * scale_x,
+        CANVAS_HEIGHT - evt.offset_y() as f32 * scale_y,
+    )
+}
+
+// For Mouse Input
+fn prepare_mouse_input() -> Result<UnboundedReceiver<MousePress>> {
+    let (mousedown_sender, mouseevent_receiver) = unbounded();
+    let mousedown_sender = Rc::new(RefCell::new(mousedown_sender));
+    let mouseup_sender = Rc::clone(&mousedown_sender);
+    let mousemove_sender = Rc::clone(&mousedown_sender);
+    let canvas = browser::canvas()?;
+
+    let down_canvas = canvas.clone();
+    let onmousedown = browser::closure_wrap(Box::new(move |evt: web_sys::MouseEvent| {
+        let _ = mousedown_sender
+            .borrow_mut()
+            .start_send(MousePress::Down(mouse_point(&down_canvas, &evt)));
+    }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+
+    let up_canvas = canvas.clone();
+    let onmouseup = browser::closure_wrap(Box::new(move |evt: web_sys::MouseEvent| {
+        let _ = mouseup_sender
+            .borrow_mut()
+            .start_send(MousePress::Up(mouse_point(&up_canvas, &evt)));
+    }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+
+    let move_canvas = canvas.clone();
+    let onmousemove = browser::closure_wrap(Box::new(move |evt: web_sys::MouseEvent| {
+        let _ = mousemove_sender
+            .borrow_mut()
+            .start_send(MousePress::Move(mouse_point(&move_canvas, &evt)));
+    }) as Box<dyn FnMut(web_sys::MouseEvent)>);
+
+    canvas.set_onmousedown(Some(onmousedown.as_ref().unchecked_ref()));
+    canvas.set_onmouseup(Some(onmouseup.as_ref().unchecked_ref()));
+    canvas.set_onmousemove(Some(onmousemove.as_ref().unchecked_ref()));
+    onmousedown.forget();
+    onmouseup.forget();
+    onmousemove.forget();
+
+    Ok(mouseevent_receiver)
+}
+
 // For Keypress Input
 fn prepare_input() -> Result<UnboundedReceiver<KeyPress>> {
     let (keydown_sender, keyevent_receiver) = unbounded();
@@ -1037,7 +1564,151 @@ impl Audio {
         sound::play_sound(&self.context, &sound.buffer, sound::Looping::No)
     }
 
+    /// Plays `sound` once, faster and higher for a `rate` above 1.0
+    pub fn play_sound_at_rate(&self, sound: &Sound, rate: f32) -> Result<()> {
+        sound::play_sound_at_rate(&self.context, &sound.buffer, rate)
+    }
+
     pub fn play_looping_sound(&self, sound: &Sound) -> Result<()> {
         sound::play_sound(&self.context, &sound.buffer, sound::Looping::Yes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Date of the simulated clock at `time` milliseconds since the epoch
+    fn date_at(time: f64) -> String {
+        SimulatedClock::new(time).today()
+    }
+
+    #[test]
+    fn simulated_clock_today_at_epoch() {
+        assert_eq!(date_at(0.0), "1970-01-01");
+        assert_eq!(date_at(-1.0), "1969-12-31");
+    }
+
+    #[test]
+    fn simulated_clock_today_at_month_and_year_ends() {
+        assert_eq!(date_at(1_704_067_200_000.0 - 1.0), "2023-12-31");
+        assert_eq!(date_at(1_704_067_200_000.0), "2024-01-01");
+        assert_eq!(date_at(1_677_628_800_000.0 - 1.0), "2023-02-28");
+        assert_eq!(date_at(1_677_628_800_000.0), "2023-03-01");
+    }
+
+    #[test]
+    fn simulated_clock_today_in_leap_years() {
+        assert_eq!(date_at(1_709_164_800_000.0 - 1.0), "2024-02-28");
+        assert_eq!(date_at(1_709_164_800_000.0), "2024-02-29");
+        assert_eq!(date_at(1_709_251_200_000.0 - 1.0), "2024-02-29");
+        assert_eq!(date_at(1_709_251_200_000.0), "2024-03-01");
+        // Centuries are leap years only every 400 years
+        assert_eq!(date_at(951_868_800_000.0 - 1.0), "2000-02-29");
+        assert_eq!(date_at(4_107_542_400_000.0 - 1.0), "2100-02-28");
+        assert_eq!(date_at(4_107_542_400_000.0), "2100-03-01");
+    }
+
+    /// Counts its updates and draws nothing
+    #[derive(Default)]
+    struct CountingGame {
+        updates: u32,
+    }
+    #[async_trait(?Send)]
+    impl Game for CountingGame {
+        async fn initialize(&self) -> Result<Box<dyn Game>> {
+            Ok(Box::new(CountingGame::default()))
+        }
+        fn update(&mut self, _keystate: &KeyState, _mouse: &MouseState) {
+            self.updates += 1;
+        }
+        fn draw(&self, _renderer: &dyn Renderer) {}
+    }
+
+    fn simulated_loop() -> (Rc<SimulatedClock>, GameLoop) {
+        let clock = Rc::new(SimulatedClock::new(1000.0));
+        let game_loop = GameLoop::new(Box::new(Rc::clone(&clock))).unwrap();
+        (clock, game_loop)
+    }
+
+    #[test]
+    fn game_loop_runs_one_step_per_elapsed_frame() {
+        let (clock, mut game_loop) = simulated_loop();
+        let mut game = CountingGame::default();
+        let keystate = KeyState::new();
+        let mouse = MouseState::new();
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 0);
+        clock.advance(FRAME_SIZE * 3.5);
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 3);
+        assert_eq!(game.updates, 3);
+    }
+
+    #[test]
+    fn game_loop_carries_leftover_time_to_the_next_tick() {
+        let (clock, mut game_loop) = simulated_loop();
+        let mut game = CountingGame::default();
+        let keystate = KeyState::new();
+        let mouse = MouseState::new();
+        clock.advance(FRAME_SIZE * 0.6);
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 0);
+        clock.advance(FRAME_SIZE * 0.6);
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 1);
+        clock.advance(FRAME_SIZE * 0.6);
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 0);
+        clock.advance(FRAME_SIZE * 0.6);
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 1);
+        assert_eq!(game.updates, 2);
+    }
+
+    #[test]
+    fn game_loop_drops_a_long_stall() {
+        let (clock, mut game_loop) = simulated_loop();
+        let mut game = CountingGame::default();
+        let keystate = KeyState::new();
+        let mouse = MouseState::new();
+        clock.advance(FRAME_SIZE * 500.5);
+        assert_eq!(
+            game_loop.tick(&mut game, &keystate, &mouse).unwrap(),
+            MAX_STEPS_PER_TICK
+        );
+        // The stall is not caught up on the following ticks
+        clock.advance(FRAME_SIZE);
+        assert_eq!(game_loop.tick(&mut game, &keystate, &mouse).unwrap(), 1);
+        assert_eq!(game.updates, MAX_STEPS_PER_TICK + 1);
+    }
+
+    #[test]
+    fn recording_renderer_matches_car_snapshot() {
+        let renderer = RecordingRenderer::new();
+        renderer.draw_normal_racing_car(&Point::new(400.0, 100.0));
+        renderer.assert_snapshot("normal_racing_car.txt");
+    }
+
+    #[test]
+    fn recording_renderer_keeps_stroke_and_line_width() {
+        let renderer = RecordingRenderer::new();
+        renderer.rect(&Point::new(10.0, 20.0), 30.0, 40.0, "red", "blue", 2.5);
+        renderer.ellipse(&Point::new(1.0, 2.0), 3.0, 4.0, None, "black", 0.5);
+        assert_eq!(
+            renderer.commands(),
+            vec![
+                DrawCommand::Rect {
+                    center: Point::new(10.0, 20.0),
+                    width: 30.0,
+                    height: 40.0,
+                    fill: "red".into(),
+                    stroke: "blue".into(),
+                    line_width: 2.5,
+                },
+                DrawCommand::Ellipse {
+                    center: Point::new(1.0, 2.0),
+                    radius_x: 3.0,
+                    radius_y: 4.0,
+                    fill: None,
+                    stroke: "black".into(),
+                    line_width: 0.5,
+                },
+            ]
+        );
+    }
+}
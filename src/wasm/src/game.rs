@@ -1,35 +1,54 @@
 mod cart;
+mod checkpoint;
+mod difficulty;
+mod editor;
+mod generator;
+mod ghost;
+mod hazard;
+mod high_score;
+mod item;
+mod lap_timer;
 mod music;
 mod ornament;
+mod pad;
+mod physics;
+mod race;
+mod racing_line;
+mod replay;
 mod rival_cart;
+mod surface;
+mod track;
+pub(crate) mod track_check;
 mod wall;
 use crate::{
-    browser::now,
-    engine::{Audio, Game, KeyState, Line, Point, Renderer, Sound, Velocity},
-    game::wall::wall::WALLS_DATA,
+    browser,
+    engine::{
+        Audio, BrowserClock, BrowserDownloads, Downloads, Game, KeyState, Line, LocalStorage,
+        MouseState, Point, Renderer, Sound, Velocity,
+    },
 };
-use anyhow::Result;
+use anyhow::{Context as _, Result, anyhow};
 use async_trait::async_trait;
-use cart::cart::*;
+use difficulty::difficulty::*;
+use editor::editor::*;
+use generator::generator::*;
+use high_score::high_score::*;
+use lap_timer::lap_timer::*;
 use music::music::*;
-use ornament::ornament::*;
-use rival_cart::rival_cart::*;
-use wall::wall::*;
+use race::race::*;
+use replay::replay::*;
+use std::{cell::RefCell, rc::Rc};
+use track::track::*;
+use track_check::track_check::*;
 /* <-- CONSTANT VALUE */
 
 pub const CANVAS_WIDTH: f32 = 800.0;
 pub const CANVAS_HEIGHT: f32 = 1000.0;
-const CART_START_X: f32 = CANVAS_WIDTH / 2.0;
 pub const CART_START_Y: f32 = 100.0;
 
 const FONT_COLOR: &str = "green";
-const STAGE_LEFT: f32 = 100.0;
-pub const STAGE_GOAL: f32 = 4500.0;
-const VELOCITY_X: f32 = 0.8;
-const VELOCITY_STEP: f32 = 0.03;
-const VELOCITY_BRAKE_STEP: f32 = 0.06;
-const VELOCITY_LIMIT: f32 = 5.0;
-const VELOCITY_ZERO: f32 = 0.0;
+/// Units of course that scroll past for each unit of distance driven
+pub const COURSE_PER_DISTANCE: f32 = 2.0;
 /// SCREEN
 const TITLE: &str = "Cart";
 const TITLE_X: f32 = CANVAS_WIDTH / 2.0;
@@ -37,6 +56,12 @@ const TITLE_Y: f32 = 820.0;
 const TITLE_MESSAGE: &str = "Push Space Key.";
 const TITLE_MESSAGE_X: f32 = CANVAS_WIDTH / 2.0;
 const TITLE_MESSAGE_Y: f32 = 660.0;
+/// Heading over the driving keys, which work differently on the title screen
+const MESSAGE_CONTROLS: &str = "In the race";
+const MESSAGE_CONTROLS_Y: f32 = 600.0;
+const MESSAGE_DIFFICULTY_Y: f32 = 160.0;
+const MESSAGE_CATCH_UP_Y: f32 = 110.0;
+const MESSAGE_CATCH_UP: &str = "C: Catch-up";
 
 const MESSAGE_HIGHSCORE_X: f32 = 30.0;
 const MESSAGE_HIGHSCORE_Y: f32 = 940.0;
@@ -44,24 +69,94 @@ const MESSAGE_TIME_X: f32 = 30.0;
 const MESSAGE_TIME_Y: f32 = 900.0;
 const MESSAGE_VELOCITY_X: f32 = 30.0;
 const MESSAGE_VELOCITY_Y: f32 = 860.0;
+const MESSAGE_LAP_X: f32 = 30.0;
+const MESSAGE_LAP_Y: f32 = 820.0;
+const MESSAGE_DELTA_X: f32 = 750.0;
+const MESSAGE_DELTA_Y: f32 = 860.0;
+const MESSAGE_LAP_TIMES_DISTANCE: f32 = 40.0;
+const DELTA_GAIN_COLOR: &str = "green";
+const DELTA_LOSS_COLOR: &str = "red";
+const MESSAGE_DAMAGE_X: f32 = 750.0;
+const MESSAGE_DAMAGE_Y: f32 = 820.0;
+/// Share of `DAMAGE_LIMIT` from which the damage meter turns red
+const DAMAGE_WARNING: f32 = 0.7;
+const DAMAGE_WARNING_COLOR: &str = "red";
+/// Fixed steps the respawn penalty stays on screen
+const MESSAGE_RESPAWN_FRAMES: u32 = 120;
+const MESSAGE_CHECKPOINT_X: f32 = 30.0;
+const MESSAGE_CHECKPOINT_Y: f32 = 780.0;
+const MESSAGE_WARNING_Y: f32 = 600.0;
+/// Fixed steps a lap warning stays on screen
+const MESSAGE_WARNING_FRAMES: u32 = 120;
+/// Centre of the item slot
+const MESSAGE_ITEM_X: f32 = 690.0;
+const MESSAGE_ITEM_Y: f32 = 780.0;
+const ITEM_SLOT_WIDTH: f64 = 120.0;
+const ITEM_SLOT_HEIGHT: f64 = 36.0;
+const ITEM_SLOT_COLOR: &str = "#003300";
+/// Shown in the item slot while it is empty
+const MESSAGE_NO_ITEM: &str = "X: Item";
+const MESSAGE_SHIELD: &str = "SHIELD";
+const MESSAGE_RACE_POSITION_X: f32 = 750.0;
+const MESSAGE_RACE_POSITION_Y: f32 = 730.0;
+const MESSAGE_RESULTS: &str = "RESULTS";
+/// The results and the best times share the clear screen side by side
+const MESSAGE_RESULTS_X: f32 = CANVAS_WIDTH / 4.0 + 10.0;
+const MESSAGE_RESULTS_Y: f32 = 360.0;
+#[allow(dead_code)] // debug HUD, see GameStage::draw
 const MESSAGE_POSITION_X_X: f32 = 30.0;
+#[allow(dead_code)]
 const MESSAGE_POSITION_X_Y: f32 = 820.0;
+#[allow(dead_code)]
 const MESSAGE_POSITION_Y_X: f32 = 30.0;
+#[allow(dead_code)]
 const MESSAGE_POSITION_Y_Y: f32 = 780.0;
 const MESSAGE_TIME: i32 = 100;
 const MESSAGE_RUNNING: &str = "Ready Go!";
 const MESSAGE_GAMEOVER: &str = "Game Over!";
 const MESSAGE_GAMECLEAR: &str = "Congrantuation!!";
+const MESSAGE_REPLAY: &str = "REPLAY";
+const MESSAGE_TEST_DRIVE: &str = "TEST DRIVE  Esc Edit";
 const MESSAGE_DISTANCE: f32 = 120.0;
-const ORNAMENT_X: f32 = STAGE_LEFT + 20.0;
-const ORNAMENT_Y: f32 = 950.0;
-const ORNAMENT_WIDTH: f32 = 10.0;
-const ORNAMENT_HEIGHT: f32 = 9900.0;
 const BRAKESOUND_FILE: &str = "/cart/assets/beep-7.wav";
 const BACKGROUND_MUSIC_FILE: &str = "/cart/assets/background_song.mp3";
+const REPLAY_FILE: &str = "replay.json";
+const REPLAY_FILE_TYPE: &str = "application/json";
+const SAVE_REPLAY_KEY: &str = "KeyD";
+const MESSAGE_SAVE_REPLAY: &str = "D: Save replay";
+const MESSAGE_REPLAY_SAVED: &str = "Replay saved";
+const MESSAGE_SAVE_REPLAY_Y: f32 = 60.0;
+const MESSAGE_RANKING: &str = "Best Times";
+const MESSAGE_RANKING_X: f32 = CANVAS_WIDTH * 3.0 / 4.0 - 10.0;
+const MESSAGE_RANKING_Y: f32 = 360.0;
+const MESSAGE_RANKING_DISTANCE: f32 = 26.0;
+const TRACK_DIRECTORY: &str = "/cart/assets/tracks/";
+const TRACK_INDEX_FILE: &str = "index.json";
+const MESSAGE_TRACK_SELECT: &str = "Select Track";
+const MESSAGE_TRACK_SELECT_Y: f32 = 880.0;
+const MESSAGE_TRACK_NAME_Y: f32 = 780.0;
+const MESSAGE_TRACK_BEST_Y: f32 = 730.0;
+const MESSAGE_TRACK_COUNT_Y: f32 = 690.0;
+const TRACK_PREVIEW_Y: f32 = 380.0;
+const TRACK_PREVIEW_WIDTH: f32 = 240.0;
+const TRACK_PREVIEW_HEIGHT: f32 = 560.0;
+const TRACK_PREVIEW_MESSAGE_Y: f32 = 60.0;
+const TRACK_SELECT_HELP: &str = "E: Edit track";
+const TRACK_SELECT_HELP_Y: f32 = 25.0;
+const TRACK_FILE_TYPE: &str = "application/json";
 
 /* CONSTANT VALUE --> */
 
+/// Length of course that scrolls past while driving `distance`
+pub fn distance_to_course(distance: f32) -> f32 {
+    distance * COURSE_PER_DISTANCE
+}
+
+/// Distance driven while `course` length of course scrolls past
+pub fn course_to_distance(course: f32) -> f32 {
+    course / COURSE_PER_DISTANCE
+}
+
 pub struct GameStage {
     machine: Option<GameStageStateMachine>,
 }
@@ -73,6 +168,8 @@ impl GameStage {
 }
 enum GameStageStateMachine {
     Ready(GameStageState<Ready>),
+    TrackSelect(GameStageState<TrackSelect>),
+    TrackEditor(GameStageState<TrackEditor>),
     Playing(GameStageState<Playing>),
     GameOver(GameStageState<GameOver>),
     GameClear(GameStageState<GameClear>),
@@ -82,19 +179,24 @@ impl GameStageStateMachine {
     fn new(material: Material) -> Self {
         GameStageStateMachine::Ready(GameStageState::new(material))
     }
-    /// Update game state based on key input
-    fn update(self, _keystate: &KeyState) -> Self {
+    /// Update game state based on key and mouse input
+    fn update(self, _keystate: &KeyState, _mouse: &MouseState) -> Self {
         match self {
             GameStageStateMachine::Ready(state) => state.update(_keystate).into(),
+            GameStageStateMachine::TrackSelect(state) => state.update(_keystate).into(),
+            GameStageStateMachine::TrackEditor(state) => state.update(_keystate, _mouse).into(),
             GameStageStateMachine::Playing(state) => state.update(_keystate).into(),
             GameStageStateMachine::GameOver(state) => state.update(_keystate).into(),
             GameStageStateMachine::GameClear(state) => state.update(_keystate).into(),
         }
     }
     /// Draw game elements based on current state
-    fn draw(&self, renderer: &Renderer) {
+    fn draw(&self, renderer: &dyn Renderer) {
         match self {
             GameStageStateMachine::Ready(state) => state.material.draw(renderer),
+            // The track preview replaces the race
+            GameStageStateMachine::TrackSelect(_) => {}
+            GameStageStateMachine::TrackEditor(state) => state._state.editor.draw(renderer),
             GameStageStateMachine::Playing(state) => state.material.draw(renderer),
             GameStageStateMachine::GameOver(state) => state.material.draw(renderer),
             GameStageStateMachine::GameClear(state) => state.material.draw(renderer),
@@ -106,6 +208,16 @@ impl From<GameStageState<Ready>> for GameStageStateMachine {
         GameStageStateMachine::Ready(state)
     }
 }
+impl From<GameStageState<TrackSelect>> for GameStageStateMachine {
+    fn from(state: GameStageState<TrackSelect>) -> Self {
+        GameStageStateMachine::TrackSelect(state)
+    }
+}
+impl From<GameStageState<TrackEditor>> for GameStageStateMachine {
+    fn from(state: GameStageState<TrackEditor>) -> Self {
+        GameStageStateMachine::TrackEditor(state)
+    }
+}
 impl From<GameStageState<Playing>> for GameStageStateMachine {
     fn from(state: GameStageState<Playing>) -> Self {
         GameStageStateMachine::Playing(state)
@@ -127,15 +239,29 @@ struct GameStageState<T> {
     material: Material,
 }
 
-struct Ready;
+struct Ready {
+    /// Keys held in the previous step, so a held key acts only once
+    last_keystate: KeyState,
+}
 impl GameStageState<Ready> {
     /// Create new game state in Ready state
     fn new(material: Material) -> GameStageState<Ready> {
         GameStageState {
-            _state: Ready,
+            _state: Ready {
+                last_keystate: KeyState::new(),
+            },
             material,
         }
     }
+    /// Transition to TrackSelect state
+    fn select_track(self, _keystate: &KeyState) -> GameStageState<TrackSelect> {
+        GameStageState {
+            _state: TrackSelect {
+                last_keystate: _keystate.clone(),
+            },
+            material: self.material,
+        }
+    }
     /// Start game and transition to Playing state
     fn start_running(self) -> GameStageState<Playing> {
         GameStageState {
@@ -143,215 +269,273 @@ impl GameStageState<Ready> {
             material: self.material,
         }
     }
-    /// Handle key input in Ready state (start game with space key)
-    fn update(self, _keystate: &KeyState) -> ReadyEndState {
-        if _keystate.is_pressed("Space") {
-            return ReadyEndState::Complete(self.start_running());
+    /// Handle key input in Ready state (choose the difficulty with arrow keys and catch-up with C key, choose a track with space key)
+    fn update(mut self, _keystate: &KeyState) -> ReadyEndState {
+        let _pushed =
+            |code: &str| _keystate.is_pressed(code) && !self._state.last_keystate.is_pressed(code);
+        if _pushed("Space") {
+            // A shared replay already knows its track
+            if self.material.playback.is_some() {
+                return ReadyEndState::Replay(self.start_running());
+            }
+            return ReadyEndState::Complete(self.select_track(_keystate));
+        }
+        // A shared replay also knows its difficulty
+        let mut _difficulty = self.material.race.difficulty;
+        if _pushed("ArrowRight") {
+            _difficulty.level = _difficulty.level.next();
+        } else if _pushed("ArrowLeft") {
+            _difficulty.level = _difficulty.level.previous();
+        }
+        if _pushed("KeyC") {
+            _difficulty.catch_up = !_difficulty.catch_up;
         }
+        if _difficulty != self.material.race.difficulty && self.material.playback.is_none() {
+            self.material.race.difficulty = _difficulty;
+            self.material = Material::reset(self.material);
+        }
+        self._state.last_keystate = _keystate.clone();
         ReadyEndState::Continue(self)
     }
 }
 enum ReadyEndState {
-    Complete(GameStageState<Playing>),
+    Complete(GameStageState<TrackSelect>),
+    Replay(GameStageState<Playing>),
     Continue(GameStageState<Ready>),
 }
 impl From<ReadyEndState> for GameStageStateMachine {
     fn from(state: ReadyEndState) -> Self {
         match state {
-            ReadyEndState::Complete(running) => running.into(),
+            ReadyEndState::Complete(track_select) => track_select.into(),
+            ReadyEndState::Replay(running) => running.into(),
             ReadyEndState::Continue(ready) => ready.into(),
         }
     }
 }
 
-struct Playing;
-impl GameStageState<Playing> {
-    /// Main update process during gameplay
-    fn update(mut self, _keystate: &KeyState) -> RunningEndState {
-        // Check if cart completed one lap
-        let _position: Point = self.material.cart.get_position();
-        let mut _velocity: Velocity = self.material.cart.get_velocity();
-        self.material.distance += _velocity.y;
-
-        if self.material.distance > STAGE_GOAL {
-            self.material.lap_count += 1;
-            self.material.distance = 0.0; // Reset distance for next lap
-
-            // Reset walls to original positions
-            self.material.walls.clear();
-            for w in WALLS_DATA {
-                self.material.walls.push(Wall::new(
-                    Point { x: w.0, y: w.1 },
-                    Point { x: w.2, y: w.3 },
-                    Velocity { x: 0.0, y: 0.0 },
-                ));
-            }
-
-            // Reset ornaments to original positions
-            self.material.ornaments = vec![Ornament::new(
-                Point {
-                    x: ORNAMENT_X,
-                    y: ORNAMENT_Y,
-                },
-                Point {
-                    x: ORNAMENT_X + ORNAMENT_WIDTH,
-                    y: ORNAMENT_Y + ORNAMENT_HEIGHT,
-                },
-                Velocity { x: 0.0, y: 0.0 },
-            )];
-
-            // Reset rival carts positions
-            let mut _rival_carts: Vec<RivalCart> = Vec::new();
-            let mut _no: usize;
-            for r in &self.material.rival_carts {
-                let mut _y = r.get_position().y;
-                _no = r.get_no();
-                if _y < 0.0 {
-                    _y = r.get_distance() + CART_START_Y;
-                }
-                let mut _distance = r.get_distance();
-                let mut _p = Point {
-                    x: r.get_position().x,
-                    y: _y,
-                };
-                let _speed = r.get_velocity().y;
-                _rival_carts.push(RivalCart::new(_p, _speed, _distance, _no));
-            }
-
-            // Check if cart completed 3 laps
-            if self.material.lap_count >= 3 {
-                let mut _highscore: i32 = now().unwrap() as i32 - self.material.start_time;
-                if self.material.highscore != 0 {
-                    _highscore = _highscore.min(self.material.highscore);
-                }
-                self.material.highscore = _highscore;
-                self.material.score = now().unwrap() as i32 - self.material.start_time;
-                return RunningEndState::GameClear(GameStageState {
-                    _state: GameClear,
-                    material: self.material,
-                });
-            }
-        }
-
-        // Rival Cart late or early lap adjust
-        let mut _rival_carts: Vec<RivalCart> = Vec::new();
-        for r in &mut self.material.rival_carts {
-            let _y = r.get_position().y;
-            if r.get_position().y - self.material.cart.get_position().y > STAGE_GOAL {
-                let _p = Point::new(r.get_position().x, r.get_position().y - STAGE_GOAL);
-                r.set_position(_p);
-            } else if self.material.cart.get_position().y - r.get_position().y
-                > STAGE_GOAL - CANVAS_HEIGHT
-            {
-                let _p = Point::new(
-                    r.get_position().x,
-                    r.get_position().y + STAGE_GOAL + CANVAS_HEIGHT,
-                );
-                r.set_position(_p);
-            }
+struct TrackSelect {
+    /// Keys held in the previous step, so a held key acts only once
+    last_keystate: KeyState,
+}
+impl GameStageState<TrackSelect> {
+    /// Handle key input in TrackSelect state (choose with arrow keys, start with space key, edit with E key)
+    fn update(mut self, _keystate: &KeyState) -> TrackSelectEndState {
+        let _pushed =
+            |code: &str| _keystate.is_pressed(code) && !self._state.last_keystate.is_pressed(code);
+        if _pushed("Space") {
+            return TrackSelectEndState::Complete(GameStageState {
+                _state: Playing,
+                material: self.material,
+            });
         }
-
-        if _keystate.is_pressed("ArrowUp") && _velocity.y < VELOCITY_LIMIT {
-            _velocity.y += VELOCITY_STEP;
+        if _pushed("KeyE") {
+            let _track = (*self.material.tracks.selected()).clone();
+            return TrackSelectEndState::Edit(GameStageState {
+                _state: TrackEditor::new(Box::new(Editor::new(_track, _keystate))),
+                material: self.material,
+            });
         }
-        if _keystate.is_pressed("ArrowDown") {
-            _velocity.x = 0.0;
-            // Return cart to normal
-            self.material.cart.set_direction(CarDirection::Normal);
+        if _pushed("ArrowRight") {
+            self.material.tracks.select_next();
+            self.material = Material::reset(self.material);
+        } else if _pushed("ArrowLeft") {
+            self.material.tracks.select_previous();
+            self.material = Material::reset(self.material);
         }
-        if _keystate.is_pressed("ArrowLeft") {
-            _velocity.x = -VELOCITY_X;
+        self._state.last_keystate = _keystate.clone();
+        TrackSelectEndState::Continue(self)
+    }
+}
+enum TrackSelectEndState {
+    Complete(GameStageState<Playing>),
+    Edit(GameStageState<TrackEditor>),
+    Continue(GameStageState<TrackSelect>),
+}
+impl From<TrackSelectEndState> for GameStageStateMachine {
+    fn from(state: TrackSelectEndState) -> Self {
+        match state {
+            TrackSelectEndState::Complete(running) => running.into(),
+            TrackSelectEndState::Edit(track_editor) => track_editor.into(),
+            TrackSelectEndState::Continue(track_select) => track_select.into(),
         }
-        if _keystate.is_pressed("ArrowRight") {
-            _velocity.x = VELOCITY_X;
+    }
+}
+
+struct TrackEditor {
+    editor: Box<Editor>,
+    /// Contents of the file chosen for import, once the file dialog completes
+    import: Rc<RefCell<Option<Result<String>>>>,
+}
+impl TrackEditor {
+    fn new(editor: Box<Editor>) -> Self {
+        TrackEditor {
+            editor,
+            import: Rc::new(RefCell::new(None)),
         }
-        if _keystate.is_pressed("Space") {
-            _velocity.y -= VELOCITY_BRAKE_STEP;
-            self.material.music.clone().play_brake_sound();
+    }
+}
+impl GameStageState<TrackEditor> {
+    /// Go back to the editor a test drive started from
+    fn resume(mut material: Material, _keystate: &KeyState) -> GameStageState<TrackEditor> {
+        let mut _editor = match material.editor.take() {
+            Some(editor) => editor,
+            None => Box::new(Editor::new((*material.race.track).clone(), _keystate)),
+        };
+        _editor.resume(_keystate);
+        GameStageState {
+            _state: TrackEditor::new(_editor),
+            material,
         }
-        // velocity limit
-        if _velocity.y < VELOCITY_ZERO {
-            _velocity.y = 0.0
+    }
+    /// Handle input in TrackEditor state (edit with the mouse, test drive with T key, leave with Escape key)
+    fn update(mut self, _keystate: &KeyState, _mouse: &MouseState) -> TrackEditorEndState {
+        let _imported = self._state.import.borrow_mut().take();
+        if let Some(_result) = _imported {
+            self.import_track(_result, _keystate);
         }
-        self.material.cart.run(_velocity);
-
-        // Ornament
-        self.material.ornaments.iter_mut().for_each(|ornament| {
-            ornament.run(Velocity {
-                x: 0.0,
-                y: _velocity.y,
-            });
-        });
-
-        // walls update
-        self.material.walls.iter_mut().for_each(|wall| {
-            wall.run(Velocity {
-                x: 0.0,
-                y: _velocity.y,
+        let _editor = &mut self._state.editor;
+        if _editor.is_pushed(_keystate, "Escape") {
+            return TrackEditorEndState::Complete(GameStageState {
+                _state: TrackSelect {
+                    last_keystate: _keystate.clone(),
+                },
+                material: self.material,
             });
-        });
-
-        // Check Cart for Walls
-        let _knocked = false;
-        for i in 0..self.material.walls.len() {
-            let _wall = &self.material.walls[i];
-            let _line = _wall.get_line();
-            if _wall.p().y.min(_wall.q().y) > self.material.distance {
-                continue;
+        }
+        if _editor.is_pushed(_keystate, "KeyT") {
+            match check_edited_track(&_editor.track) {
+                Ok(()) => return TrackEditorEndState::TestDrive(self.test_drive()),
+                Err(err) => _editor.message = format!("{:#}", err).replace('\n', " "),
             }
-            if self.material.cart.intersect(_line) {
-                self.material.cart.knocked();
-                return RunningEndState::GameOver(GameStageState {
-                    _state: GameOver,
-                    material: self.material,
-                });
+        } else if _editor.is_pushed(_keystate, "KeyS") {
+            _editor.message = match export_track(&_editor.track) {
+                Ok(_file) => format!("Exported {}", _file),
+                Err(err) => format!("{:#}", err).replace('\n', " "),
             };
+        } else if _editor.is_pushed(_keystate, "KeyO") {
+            let _import = self._state.import.clone();
+            browser::spawn_local(async move {
+                let _result = browser::pick_text_file(TRACK_FILE_TYPE).await;
+                *_import.borrow_mut() = Some(_result);
+            });
         }
-
-        // Check Cart for Rival Cart
-        for _r in &self.material.rival_carts {
-            if _r.check_collision_with_cart(self.material.cart.get_position()) {
-                self.material.cart.knocked();
-                return RunningEndState::GameOver(GameStageState {
-                    _state: GameOver,
-                    material: self.material,
-                });
+        _editor.update(_keystate, _mouse);
+        TrackEditorEndState::Continue(self)
+    }
+    /// Replace the edited track with an imported track file
+    fn import_track(&mut self, result: Result<String>, _keystate: &KeyState) {
+        match result.and_then(|json| Track::from_json(&json)) {
+            Ok(_track) => {
+                let _name = _track.name.clone();
+                *self._state.editor = Editor::new(_track, _keystate);
+                self._state.editor.message = format!("Imported {}", _name);
             }
+            Err(err) => {
+                self._state.editor.message = format!("{:#}", err).replace('\n', " ");
+            }
+        }
+    }
+    /// Race on the edited track, coming back to the editor afterwards
+    fn test_drive(self) -> GameStageState<Playing> {
+        let mut _material = self.material;
+        _material.tracks.insert(self._state.editor.track.clone());
+        let mut _material = Material::reset(_material);
+        _material.editor = Some(self._state.editor);
+        GameStageState {
+            _state: Playing,
+            material: _material,
+        }
+    }
+}
+enum TrackEditorEndState {
+    Complete(GameStageState<TrackSelect>),
+    TestDrive(GameStageState<Playing>),
+    Continue(GameStageState<TrackEditor>),
+}
+impl From<TrackEditorEndState> for GameStageStateMachine {
+    fn from(state: TrackEditorEndState) -> Self {
+        match state {
+            TrackEditorEndState::Complete(track_select) => track_select.into(),
+            TrackEditorEndState::TestDrive(running) => running.into(),
+            TrackEditorEndState::Continue(track_editor) => track_editor.into(),
         }
+    }
+}
 
-        // Update cart
-        self.material.cart.update();
-
-        // Update all rival carts check_collision and adjust
-        let _walls = self.material.walls.clone();
-        self.material.rival_carts.iter_mut().for_each(|rival_cart| {
-            rival_cart.update(&_walls, self.material.cart.get_velocity());
-        });
-        self.material.ornaments.iter_mut().for_each(|ornament| {
-            ornament.update();
-        });
-        self.material.walls.iter_mut().for_each(|wall| {
-            wall.update();
-        });
-
-        // Check collision with rival carts
-        let cart_position = self.material.cart.get_position();
-        for rival_cart in &self.material.rival_carts {
-            if rival_cart.check_collision_with_cart(cart_position) {
-                self.material.cart.knocked();
-                return RunningEndState::GameOver(GameStageState {
-                    _state: GameOver,
+struct Playing;
+impl GameStageState<Playing> {
+    /// Main update process during gameplay
+    fn update(mut self, _keystate: &KeyState) -> RunningEndState {
+        // During playback the recorded keys replace the live ones
+        let _keystate = match &mut self.material.playback {
+            Some(player) => player.next_keystate(),
+            None => _keystate.clone(),
+        };
+        self.material.recording.record(&_keystate);
+
+        if self.material.editor.is_some() && _keystate.is_pressed("Escape") {
+            return RunningEndState::Edit(GameStageState::<TrackEditor>::resume(
+                self.material,
+                &_keystate,
+            ));
+        }
+        if _keystate.is_pressed("Space")
+            && let Some(music) = &self.material.music
+        {
+            music.clone().play_brake_sound();
+        }
+        let _status = self.material.race.update(&_keystate);
+        if let Some(_kind) = self.material.race.pad_hit
+            && let Some(music) = &self.material.music
+        {
+            music.clone().play_pad_sound(_kind);
+        }
+        match _status {
+            RaceStatus::Running => RunningEndState::Continue(self),
+            RaceStatus::Crashed if self.respawn() => RunningEndState::Continue(self),
+            RaceStatus::Crashed => RunningEndState::GameOver(GameStageState {
+                _state: GameOver,
+                material: self.material,
+            }),
+            RaceStatus::Finished => {
+                let _score: i32 = self.material.race.elapsed();
+                self.material.score = _score;
+                // A replay repeats a race that is already on the board, and a
+                // test drive is not a race on the saved track
+                if self.material.playback.is_none()
+                    && self.material.editor.is_none()
+                    && let Err(err) = self
+                        .material
+                        .scoreboard
+                        .record(&self.material.race.track.name, _score)
+                {
+                    log!("Error saving high scores {:#?}", err);
+                }
+                RunningEndState::GameClear(GameStageState {
+                    _state: GameClear,
                     material: self.material,
-                });
+                })
             }
         }
-        RunningEndState::Continue(self)
+    }
+}
+impl GameStageState<Playing> {
+    /// Puts a wrecked cart back at its last checkpoint, if the track has any
+    fn respawn(&mut self) -> bool {
+        if !self.material.race.respawn() {
+            return false;
+        }
+        if let Some(music) = &self.material.music {
+            music.clone().play_brake_sound();
+        }
+        true
     }
 }
 impl From<RunningEndState> for GameStageStateMachine {
     fn from(state: RunningEndState) -> Self {
         match state {
             RunningEndState::Continue(running) => running.into(),
+            RunningEndState::Edit(track_editor) => track_editor.into(),
             RunningEndState::GameOver(gameover) => gameover.into(),
             RunningEndState::GameClear(gameclear) => gameclear.into(),
         }
@@ -360,24 +544,45 @@ impl From<RunningEndState> for GameStageStateMachine {
 
 struct GameOver;
 impl GameStageState<GameOver> {
-    /// Handle GameOver state (restart with space key)
-    fn update(self, _keystate: &KeyState) -> GameOverEndState {
-        if _keystate.is_pressed("Space") {
-            GameOverEndState::Complete(self.new_game())
+    /// Handle GameOver state (restart with space key, replay with R key,
+    /// save the replay with D key)
+    fn update(mut self, _keystate: &KeyState) -> GameOverEndState {
+        if _keystate.is_pressed("Space") && self.material.editor.is_some() {
+            GameOverEndState::Edit(GameStageState::<TrackEditor>::resume(
+                self.material,
+                _keystate,
+            ))
+        } else if _keystate.is_pressed("Space") {
+            GameOverEndState::Complete(self.new_game(_keystate))
+        } else if _keystate.is_pressed("KeyR") {
+            GameOverEndState::Replay(self.replay())
+        } else if _keystate.is_pressed(SAVE_REPLAY_KEY) {
+            self.material.save_replay();
+            GameOverEndState::Continue(self)
         } else {
             GameOverEndState::Continue(self)
         }
     }
+    /// Play the last race again from its recorded input
+    fn replay(self) -> GameStageState<Playing> {
+        GameStageState {
+            _state: Playing,
+            material: Material::replay(self.material),
+        }
+    }
     /// Start new game (reset materials to Ready state)
-    fn new_game(self) -> GameStageState<Ready> {
+    fn new_game(self, _keystate: &KeyState) -> GameStageState<Ready> {
         GameStageState {
-            _state: Ready,
+            _state: Ready {
+                last_keystate: _keystate.clone(),
+            },
             material: Material::reset(self.material),
         }
     }
 }
 enum RunningEndState {
     Continue(GameStageState<Playing>),
+    Edit(GameStageState<TrackEditor>),
     GameOver(GameStageState<GameOver>),
     GameClear(GameStageState<GameClear>),
 }
@@ -385,29 +590,53 @@ enum RunningEndState {
 enum GameOverEndState {
     Continue(GameStageState<GameOver>),
     Complete(GameStageState<Ready>),
+    Replay(GameStageState<Playing>),
+    Edit(GameStageState<TrackEditor>),
 }
 impl From<GameOverEndState> for GameStageStateMachine {
     fn from(state: GameOverEndState) -> Self {
         match state {
             GameOverEndState::Continue(game_over) => game_over.into(),
             GameOverEndState::Complete(ready) => ready.into(),
+            GameOverEndState::Replay(playing) => playing.into(),
+            GameOverEndState::Edit(track_editor) => track_editor.into(),
         }
     }
 }
 struct GameClear;
 impl GameStageState<GameClear> {
-    /// Handle GameClear state (restart with space key)
-    fn update(self, _keystate: &KeyState) -> GameClearEndState {
-        if _keystate.is_pressed("Space") {
-            GameClearEndState::Complete(self.new_game())
+    /// Handle GameClear state (restart with space key, replay with R key,
+    /// save the replay with D key)
+    fn update(mut self, _keystate: &KeyState) -> GameClearEndState {
+        if _keystate.is_pressed("Space") && self.material.editor.is_some() {
+            GameClearEndState::Edit(GameStageState::<TrackEditor>::resume(
+                self.material,
+                _keystate,
+            ))
+        } else if _keystate.is_pressed("Space") {
+            GameClearEndState::Complete(self.new_game(_keystate))
+        } else if _keystate.is_pressed("KeyR") {
+            GameClearEndState::Replay(self.replay())
+        } else if _keystate.is_pressed(SAVE_REPLAY_KEY) {
+            self.material.save_replay();
+            GameClearEndState::Continue(self)
         } else {
             GameClearEndState::Continue(self)
         }
     }
+    /// Play the last race again from its recorded input
+    fn replay(self) -> GameStageState<Playing> {
+        GameStageState {
+            _state: Playing,
+            material: Material::replay(self.material),
+        }
+    }
     /// Start new game (reset materials to Ready state)
-    fn new_game(self) -> GameStageState<Ready> {
+    fn new_game(self, _keystate: &KeyState) -> GameStageState<Ready> {
         GameStageState {
-            _state: Ready,
+            _state: Ready {
+                last_keystate: _keystate.clone(),
+            },
             material: Material::reset(self.material),
         }
     }
@@ -415,12 +644,16 @@ impl GameStageState<GameClear> {
 enum GameClearEndState {
     Continue(GameStageState<GameClear>),
     Complete(GameStageState<Ready>),
+    Replay(GameStageState<Playing>),
+    Edit(GameStageState<TrackEditor>),
 }
 impl From<GameClearEndState> for GameStageStateMachine {
     fn from(state: GameClearEndState) -> Self {
         match state {
             GameClearEndState::Continue(game_clear) => game_clear.into(),
             GameClearEndState::Complete(ready) => ready.into(),
+            GameClearEndState::Replay(playing) => playing.into(),
+            GameClearEndState::Edit(track_editor) => track_editor.into(),
         }
     }
 }
@@ -548,7 +781,7 @@ pub trait Piece {
             ),
         )
     }
-    fn draw(&self, renderer: &Renderer);
+    fn draw(&self, renderer: &dyn Renderer);
     fn p(&self) -> Point {
         self.get_state_machine().context().p
     }
@@ -558,107 +791,100 @@ pub trait Piece {
 }
 
 pub struct Material {
-    music: Music,
-    start_time: i32,
-    distance: f32,
-    highscore: i32,
+    music: Option<Music>,
+    tracks: TrackRegistry,
+    scoreboard: ScoreBoard,
     score: i32,
-    cart: Cart,
-    ornaments: Vec<Ornament>,
-    walls: Vec<Wall>,
-    lap_count: i32,
-    rival_carts: Vec<RivalCart>,
+    race: Race,
+    recording: Replay,
+    /// Whether `recording` was offered as a download
+    recording_saved: bool,
+    playback: Option<ReplayPlayer>,
+    /// Where replays are saved; `None` when saving is not available
+    downloads: Option<Box<dyn Downloads>>,
+    /// The editor a test drive was started from
+    editor: Option<Box<Editor>>,
 }
 impl Material {
-    fn new(_highscore: i32, audio: Audio, sound: Sound) -> Self {
-        let mut _walls = vec![];
-        let _start_time: i32 = now().unwrap() as i32;
-        for w in WALLS_DATA {
-            _walls.push(Wall::new(
-                Point { x: w.0, y: w.1 },
-                Point { x: w.2, y: w.3 },
-                Velocity { x: 0.0, y: 0.0 },
-            ));
-        }
-        let _walls_copy = _walls.clone();
-
-        // Create 3 rival cars with different speeds and positions
-        let mut _rival_carts = vec![];
-        _rival_carts.push(RivalCart::new(
-            Point {
-                x: CART_START_X - 80.0,
-                y: CART_START_Y + 100.0,
-            },
-            2.0, // speed
-            0.0,
-            2,
-        ));
-        _rival_carts.push(RivalCart::new(
-            Point {
-                x: CART_START_X - 180.0,
-                y: CART_START_Y + 20.0,
-            },
-            1.5,
-            0.0,
-            2,
-        ));
-        _rival_carts.push(RivalCart::new(
-            Point {
-                x: CART_START_X + 180.0,
-                y: CART_START_Y,
-            },
-            1.0,
-            0.0,
-            1,
-        ));
+    /// Create game materials for the selected track; `music` is `None` when running without audio
+    /// and `downloads` is `None` when files cannot be saved
+    fn new(
+        tracks: TrackRegistry,
+        scoreboard: ScoreBoard,
+        music: Option<Music>,
+        downloads: Option<Box<dyn Downloads>>,
+        difficulty: Difficulty,
+    ) -> Self {
+        let _track = tracks.selected();
         Material {
-            music: Music::new(audio, sound),
-            distance: 0.0,
-            start_time: _start_time,
-            highscore: _highscore,
+            music,
+            tracks,
+            scoreboard,
             score: 0,
-            cart: Cart::new(
-                Point {
-                    x: CART_START_X,
-                    y: CART_START_Y,
-                },
-                Velocity { x: 0.0, y: 0.0 },
-            ),
-            ornaments: vec![Ornament::new(
-                Point {
-                    x: ORNAMENT_X,
-                    y: ORNAMENT_Y,
-                },
-                Point {
-                    x: ORNAMENT_X + ORNAMENT_WIDTH,
-                    y: ORNAMENT_Y + ORNAMENT_HEIGHT,
-                },
-                Velocity { x: 0.0, y: 0.0 },
-            )],
-            walls: _walls,
-            lap_count: 0,
-            rival_carts: _rival_carts,
+            recording: Replay::new(&_track.name, difficulty),
+            race: Race::new(_track, difficulty),
+            recording_saved: false,
+            playback: None,
+            downloads,
+            editor: None,
         }
     }
-    // Reset game materials (keep highscore)
+    // Reset game materials for the selected track (keep high scores and difficulty, and the ghost while the track is unchanged)
     fn reset(material: Self) -> Self {
-        Material::new(
-            material.highscore,
-            material.music.audio.clone(),
-            material.music.sound.clone(),
-        )
+        let _last_race = material.race;
+        let mut _material = Material::new(
+            material.tracks,
+            material.scoreboard,
+            material.music,
+            material.downloads,
+            _last_race.difficulty,
+        );
+        _material.editor = material.editor;
+        if Rc::ptr_eq(&_last_race.track, &_material.race.track) {
+            _material.race.ghost = _last_race.ghost;
+            _material.race.lap_timer.best_lap = _last_race.lap_timer.best_lap;
+        }
+        _material
+    }
+    // Reset game materials and play back the input of the last race
+    fn replay(material: Self) -> Self {
+        let _recording = material.recording.clone();
+        let mut _material = Material::reset(material);
+        _material.playback = Some(ReplayPlayer::new(_recording));
+        _material
+    }
+    // Whether the race just run was live input on a track file that can be
+    // saved as a replay file; a replay of an edited track could not be played back
+    fn can_save_replay(&self) -> bool {
+        self.playback.is_none()
+            && self.editor.is_none()
+            && !self.tracks.is_selected_edited()
+            && self.downloads.is_some()
+    }
+    // Offer the input of a live race as a replay file download, once per race;
+    // put the file next to the game and open index.html?replay=<file> to play it
+    fn save_replay(&mut self) {
+        if self.recording_saved || !self.can_save_replay() {
+            return;
+        }
+        let Some(_downloads) = &self.downloads else {
+            return;
+        };
+        match self
+            .recording
+            .to_json()
+            .and_then(|json| _downloads.save_text(REPLAY_FILE, &json, REPLAY_FILE_TYPE))
+        {
+            Ok(()) => {
+                self.recording_saved = true;
+            }
+            Err(err) => {
+                log!("Error saving replay {:#?}", err);
+            }
+        }
     }
-    fn draw(&self, renderer: &Renderer) {
-        self.cart.draw(renderer);
-        self.ornaments.iter().for_each(|ornament| {
-            ornament.draw(renderer);
-        });
-        self.walls.iter().for_each(|wall| {
-            wall.draw(renderer);
-        });
-        self.rival_carts.iter().for_each(|rival_cart| {
-            rival_cart.draw(renderer);
-        });
+    fn draw(&self, renderer: &dyn Renderer) {
+        self.race.draw(renderer);
     }
 }
 
@@ -674,16 +900,33 @@ impl Game for GameStage {
             let background_music = audio.load_sound(BACKGROUND_MUSIC_FILE).await?;
             audio.play_looping_sound(&background_music)?;
 
-            let mut _walls = vec![];
-            for w in WALLS_DATA {
-                _walls.push(Wall::new(
-                    Point { x: w.0, y: w.1 },
-                    Point { x: w.2, y: w.3 },
-                    Velocity { x: 0.0, y: 0.0 },
-                ));
+            let _scoreboard = ScoreBoard::load(Box::new(LocalStorage), Box::new(BrowserClock));
+            let mut _courses = load_tracks().await?;
+            // index.html?seed=<number> shares a generated course; otherwise it changes daily
+            let _seed = browser::query_param("seed")
+                .and_then(|seed| seed.parse().ok())
+                .unwrap_or_else(|| seed_of_the_day(&browser::today()));
+            _courses.push(generate(_seed));
+            let mut _tracks = TrackRegistry::new(_courses)?;
+            // index.html?replay=<file> plays a shared replay instead of live input
+            let mut _playback = None;
+            let mut _difficulty = Difficulty::default();
+            if let Some(_replay_file) = browser::query_param("replay") {
+                let _replay = Replay::from_json(&browser::fetch_text(&_replay_file).await?)?;
+                if !_replay.track.is_empty() && !_tracks.select(&_replay.track) {
+                    log!("Replay track {} not found", _replay.track);
+                }
+                _difficulty = _replay.difficulty;
+                _playback = Some(ReplayPlayer::new(_replay));
             }
-            let _walls_copy = _walls.clone();
-            let _material = Material::new(0, audio, sound);
+            let mut _material = Material::new(
+                _tracks,
+                _scoreboard,
+                Some(Music::new(audio, sound)),
+                Some(Box::new(BrowserDownloads)),
+                _difficulty,
+            );
+            _material.playback = _playback;
 
             let machine = GameStageStateMachine::new(_material);
             Ok(Box::new(GameStage {
@@ -693,26 +936,38 @@ impl Game for GameStage {
     }
 
     // Update entire game
-    fn update(&mut self, _keystate: &KeyState) {
+    fn update(&mut self, _keystate: &KeyState, _mouse: &MouseState) {
         if let Some(machine) = self.machine.take() {
-            self.machine.replace(machine.update(_keystate));
+            self.machine.replace(machine.update(_keystate, _mouse));
         }
     }
     // Draw the entire game
-    fn draw(&self, renderer: &Renderer) {
+    fn draw(&self, renderer: &dyn Renderer) {
         renderer.clear(&Point { x: 0.0, y: 0.0 }, CANVAS_WIDTH, CANVAS_HEIGHT);
         match &self.machine {
             Some(GameStageStateMachine::Ready(_state)) => {
-                draw_openning(renderer);
+                draw_openning(renderer, &_state.material.race.difficulty);
+            }
+            Some(GameStageStateMachine::TrackSelect(_state)) => {
+                draw_track_select(renderer, &_state.material);
             }
             Some(GameStageStateMachine::Playing(_state)) => {
-                let _time = now().unwrap() as i32 - _state.material.start_time;
+                let _time = _state.material.race.elapsed();
                 renderer.text(
                     &Point {
                         x: MESSAGE_HIGHSCORE_X,
                         y: MESSAGE_HIGHSCORE_Y,
                     },
-                    format!("BEST TIME: {}", get_passed_time(&_state.material.highscore)).as_str(),
+                    format!(
+                        "BEST TIME: {}",
+                        get_passed_time(
+                            &_state
+                                .material
+                                .scoreboard
+                                .best_time(&_state.material.race.track.name)
+                        )
+                    )
+                    .as_str(),
                     FONT_COLOR,
                     "32px myfont",
                     "left",
@@ -732,7 +987,11 @@ impl Game for GameStage {
                         x: MESSAGE_VELOCITY_X,
                         y: MESSAGE_VELOCITY_Y,
                     },
-                    format!("Velocity: {:.1}", _state.material.cart.get_velocity().y).as_str(),
+                    format!(
+                        "Velocity: {:.1}",
+                        _state.material.race.cart.get_velocity().y
+                    )
+                    .as_str(),
                     FONT_COLOR,
                     "28px selif",
                     "left",
@@ -743,7 +1002,7 @@ impl Game for GameStage {
                         x: MESSAGE_POSITION_X_X,
                         y: MESSAGE_POSITION_X_Y,
                     },
-                    format!("Position X: {:.0}", _state.material.cart.get_position().x).as_str(),
+                    format!("Position X: {:.0}", _state.material.race.cart.get_position().x).as_str(),
                     FONT_COLOR,
                     "28px selif",
                     "left",
@@ -753,7 +1012,7 @@ impl Game for GameStage {
                         x: MESSAGE_POSITION_Y_X,
                         y: MESSAGE_POSITION_Y_Y,
                     },
-                    format!("Position Y: {:.0}", _state.material.distance).as_str(),
+                    format!("Position Y: {:.0}", _state.material.race.distance).as_str(),
                     FONT_COLOR,
                     "28px selif",
                     "left",
@@ -763,7 +1022,7 @@ impl Game for GameStage {
                         x: MESSAGE_POSITION_X_X,
                         y: MESSAGE_POSITION_Y_Y - 40.0,
                     },
-                    format!("Distance: {:.0}", _state.material.distance).as_str(),
+                    format!("Distance: {:.0}", _state.material.race.distance).as_str(),
                     FONT_COLOR,
                     "28px selif",
                     "left",
@@ -775,7 +1034,7 @@ impl Game for GameStage {
                     },
                     format!(
                         "RIVAL_DISTANCE: {:.0}",
-                        _state.material.rival_carts[0].get_distance(),
+                        _state.material.race.rival_carts[0].get_distance(),
                     )
                     .as_str(),
                     FONT_COLOR,
@@ -789,7 +1048,7 @@ impl Game for GameStage {
                     },
                     format!(
                         "RIVAL_POSITION_Y: {:.0}",
-                        _state.material.rival_carts[0].get_position().y,
+                        _state.material.race.rival_carts[0].get_position().y,
                     )
                     .as_str(),
                     FONT_COLOR,
@@ -802,11 +1061,154 @@ impl Game for GameStage {
                         x: MESSAGE_HIGHSCORE_X + 720.0,
                         y: MESSAGE_HIGHSCORE_Y,
                     },
-                    format!("{} / 3", _state.material.lap_count + 1).as_str(),
+                    format!("{} / {}", _state.material.race.lap_count + 1, RACE_LAPS).as_str(),
                     FONT_COLOR,
                     "32px myfont",
                     "right",
                 );
+                renderer.text(
+                    &Point {
+                        x: MESSAGE_RACE_POSITION_X,
+                        y: MESSAGE_RACE_POSITION_Y,
+                    },
+                    format!(
+                        "POS {}/{}",
+                        _state.material.race.position(),
+                        _state.material.race.rival_carts.len() + 1
+                    )
+                    .as_str(),
+                    FONT_COLOR,
+                    "32px myfont",
+                    "right",
+                );
+                let _lap_timer = &_state.material.race.lap_timer;
+                renderer.text(
+                    &Point {
+                        x: MESSAGE_LAP_X,
+                        y: MESSAGE_LAP_Y,
+                    },
+                    format!(
+                        "Lap: {}",
+                        get_passed_time(&frames_to_millis(
+                            _lap_timer.current_lap(_state.material.race.frames)
+                        ))
+                    )
+                    .as_str(),
+                    FONT_COLOR,
+                    "28px selif",
+                    "left",
+                );
+                if let Some(_delta) = _lap_timer.delta {
+                    renderer.text(
+                        &Point {
+                            x: MESSAGE_DELTA_X,
+                            y: MESSAGE_DELTA_Y,
+                        },
+                        format_delta(_delta).as_str(),
+                        if _delta > 0 {
+                            DELTA_LOSS_COLOR
+                        } else {
+                            DELTA_GAIN_COLOR
+                        },
+                        "28px selif",
+                        "right",
+                    );
+                }
+                let _damage = _state.material.race.damage / DAMAGE_LIMIT;
+                renderer.text(
+                    &Point {
+                        x: MESSAGE_DAMAGE_X,
+                        y: MESSAGE_DAMAGE_Y,
+                    },
+                    format!("Damage: {:.0}%", _damage.min(1.0) * 100.0).as_str(),
+                    if _damage >= DAMAGE_WARNING {
+                        DAMAGE_WARNING_COLOR
+                    } else {
+                        FONT_COLOR
+                    },
+                    "28px selif",
+                    "right",
+                );
+                let _race = &_state.material.race;
+                renderer.rect(
+                    &Point {
+                        x: MESSAGE_ITEM_X,
+                        y: MESSAGE_ITEM_Y + 8.0,
+                    },
+                    ITEM_SLOT_WIDTH,
+                    ITEM_SLOT_HEIGHT,
+                    ITEM_SLOT_COLOR,
+                    FONT_COLOR,
+                    2.0,
+                );
+                renderer.text(
+                    &Point {
+                        x: MESSAGE_ITEM_X,
+                        y: MESSAGE_ITEM_Y,
+                    },
+                    _race.item.map_or(MESSAGE_NO_ITEM, |_item| _item.name()),
+                    FONT_COLOR,
+                    "24px selif",
+                    "center",
+                );
+                if _race.shield {
+                    renderer.text(
+                        &Point {
+                            x: MESSAGE_ITEM_X,
+                            y: MESSAGE_ITEM_Y - 40.0,
+                        },
+                        MESSAGE_SHIELD,
+                        FONT_COLOR,
+                        "24px selif",
+                        "center",
+                    );
+                }
+                if !_race.checkpoints.is_empty() {
+                    renderer.text(
+                        &Point {
+                            x: MESSAGE_CHECKPOINT_X,
+                            y: MESSAGE_CHECKPOINT_Y,
+                        },
+                        format!(
+                            "Checkpoint: {} / {}",
+                            _race.checkpoints.passed(),
+                            _race.checkpoints.len()
+                        )
+                        .as_str(),
+                        FONT_COLOR,
+                        "28px selif",
+                        "left",
+                    );
+                }
+                if let Some((_warning, _frame)) = _race.warning
+                    && _race.frames - _frame < MESSAGE_WARNING_FRAMES
+                {
+                    renderer.text(
+                        &Point {
+                            x: TITLE_MESSAGE_X,
+                            y: MESSAGE_WARNING_Y,
+                        },
+                        _warning.message(),
+                        DAMAGE_WARNING_COLOR,
+                        "32px myfont",
+                        "center",
+                    );
+                }
+                if _race
+                    .last_respawn
+                    .is_some_and(|_frame| _race.frames - _frame < MESSAGE_RESPAWN_FRAMES)
+                {
+                    renderer.text(
+                        &Point {
+                            x: TITLE_MESSAGE_X,
+                            y: TITLE_MESSAGE_Y,
+                        },
+                        format!("RESPAWN +{:.1}s", _race.track.respawn_penalty).as_str(),
+                        DAMAGE_WARNING_COLOR,
+                        "32px myfont",
+                        "center",
+                    );
+                }
                 if _time < MESSAGE_TIME {
                     renderer.text(
                         &Point {
@@ -819,19 +1221,44 @@ impl Game for GameStage {
                         "center",
                     );
                 }
+                let _label = if _state.material.playback.is_some() {
+                    Some(MESSAGE_REPLAY)
+                } else if _state.material.editor.is_some() {
+                    Some(MESSAGE_TEST_DRIVE)
+                } else {
+                    None
+                };
+                if let Some(_label) = _label {
+                    renderer.text(
+                        &Point {
+                            x: MESSAGE_HIGHSCORE_X + 720.0,
+                            y: MESSAGE_TIME_Y,
+                        },
+                        _label,
+                        FONT_COLOR,
+                        "28px selif",
+                        "right",
+                    );
+                }
             }
             Some(GameStageStateMachine::GameOver(_state)) => {
-                let _score = now().unwrap() as i32 - _state.material.start_time;
-                let mut _message = MESSAGE_GAMEOVER.to_string();
                 draw_gameover(renderer);
+                draw_save_replay(renderer, &_state.material);
             }
             Some(GameStageStateMachine::GameClear(_state)) => {
+                let _race = &_state.material.race;
+                let _position = _race.position();
                 renderer.text(
                     &Point {
                         x: TITLE_MESSAGE_X,
                         y: TITLE_MESSAGE_Y,
                     },
-                    MESSAGE_GAMECLEAR,
+                    if _position == 1 {
+                        MESSAGE_GAMECLEAR.to_string()
+                    } else {
+                        format!("Finished {} / {}", _position, _race.rival_carts.len() + 1)
+                    }
+                    .as_str(),
                     FONT_COLOR,
                     "48px myfont",
                     "center",
@@ -847,16 +1274,34 @@ impl Game for GameStage {
                     "32px my_font",
                     "center",
                 );
-                renderer.text(
-                    &Point {
-                        x: TITLE_MESSAGE_X,
-                        y: TITLE_MESSAGE_Y - MESSAGE_DISTANCE - 50.0,
-                    },
-                    "3 Laps Completed!",
-                    FONT_COLOR,
-                    "32px my_font",
-                    "center",
+                let mut _y = TITLE_MESSAGE_Y - MESSAGE_DISTANCE - 50.0;
+                for (_no, _lap) in _state.material.race.lap_timer.laps.iter().enumerate() {
+                    renderer.text(
+                        &Point {
+                            x: TITLE_MESSAGE_X,
+                            y: _y,
+                        },
+                        format!(
+                            "Lap {}: {} s",
+                            _no + 1,
+                            get_passed_time(&frames_to_millis(_lap.frames))
+                        )
+                        .as_str(),
+                        FONT_COLOR,
+                        "32px my_font",
+                        "center",
+                    );
+                    _y -= MESSAGE_LAP_TIMES_DISTANCE;
+                }
+                draw_results(renderer, _race);
+                draw_ranking(
+                    renderer,
+                    _state
+                        .material
+                        .scoreboard
+                        .ranking(&_state.material.race.track.name),
                 );
+                draw_save_replay(renderer, &_state.material);
             }
             _ => {}
         }
@@ -866,13 +1311,157 @@ impl Game for GameStage {
     }
 }
 
+/// Load every track listed in the track index, in order
+async fn load_tracks() -> Result<Vec<Track>> {
+    let _index = format!("{}{}", TRACK_DIRECTORY, TRACK_INDEX_FILE);
+    let _files: Vec<String> = serde_json::from_str(&browser::fetch_text(&_index).await?)
+        .with_context(|| format!("Error parsing track index {}", _index))?;
+    let mut _tracks = vec![];
+    for _file in _files {
+        let _path = format!("{}{}", TRACK_DIRECTORY, _file);
+        let _track = Track::from_json(&browser::fetch_text(&_path).await?)
+            .with_context(|| format!("Error loading track {}", _path))?;
+        _tracks.push(_track);
+    }
+    Ok(_tracks)
+}
+
+/// Check an edited track before it is raced or exported, failing on the
+/// first problem that would spoil a race; warnings are let through
+fn check_edited_track(track: &Track) -> Result<()> {
+    track.validate()?;
+    match check_track(track)
+        .into_iter()
+        .find(|_issue| _issue.severity == Severity::Error)
+    {
+        Some(_issue) => Err(anyhow!("{}", _issue)),
+        None => Ok(()),
+    }
+}
+
+/// Offer the track as a file download, named after the track
+fn export_track(track: &Track) -> Result<String> {
+    check_edited_track(track)?;
+    let _file = format!("{}.json", track.name);
+    browser::download_text(&_file, &track.to_json()?, TRACK_FILE_TYPE)?;
+    Ok(_file)
+}
+
 fn get_passed_time(secondtime: &i32) -> String {
     let _seconds = secondtime / 1000;
     let _mini_seconds = secondtime % 1000;
     format!("{:<02}.{:<02}", _seconds, _mini_seconds)
 }
 
-fn draw_gameover(renderer: &Renderer) {
+/// Draw the finishing order with each car's gap to the winner
+fn draw_results(renderer: &dyn Renderer, race: &Race) {
+    renderer.text(
+        &Point {
+            x: MESSAGE_RESULTS_X,
+            y: MESSAGE_RESULTS_Y,
+        },
+        MESSAGE_RESULTS,
+        FONT_COLOR,
+        "32px myfont",
+        "center",
+    );
+    let _standings = race.standings();
+    let _winner = _standings.first().and_then(|_standing| _standing.finish);
+    let mut _y = MESSAGE_RESULTS_Y - MESSAGE_LAP_TIMES_DISTANCE;
+    for (_place, _standing) in _standings.iter().enumerate() {
+        // The winner shows the race time, the others how far behind they are
+        let _gap = match (_standing.finish, _winner) {
+            (Some(_), Some(_first)) if _place == 0 => {
+                format!("{} s", get_passed_time(&frames_to_millis(_first)))
+            }
+            (Some(_frames), Some(_first)) => {
+                format!(
+                    "+{} s",
+                    get_passed_time(&frames_to_millis(_frames - _first))
+                )
+            }
+            _ => format!(
+                "+{:.1} laps",
+                (RACE_LAPS as f32 * race.track.lap_length - _standing.progress).max(0.0)
+                    / race.track.lap_length
+            ),
+        };
+        renderer.text(
+            &Point {
+                x: MESSAGE_RESULTS_X,
+                y: _y,
+            },
+            format!("{}. {}  {}", _place + 1, _standing.racer.name(), _gap).as_str(),
+            FONT_COLOR,
+            "28px selif",
+            "center",
+        );
+        _y -= MESSAGE_LAP_TIMES_DISTANCE;
+    }
+}
+
+/// Draw the fastest races on the track, with their dates
+fn draw_ranking(renderer: &dyn Renderer, ranking: &[ScoreEntry]) {
+    if ranking.is_empty() {
+        return;
+    }
+    renderer.text(
+        &Point {
+            x: MESSAGE_RANKING_X,
+            y: MESSAGE_RANKING_Y,
+        },
+        MESSAGE_RANKING,
+        FONT_COLOR,
+        "28px selif",
+        "center",
+    );
+    let mut _y = MESSAGE_RANKING_Y;
+    for (_rank, _entry) in ranking.iter().enumerate() {
+        _y -= MESSAGE_RANKING_DISTANCE;
+        renderer.text(
+            &Point {
+                x: MESSAGE_RANKING_X,
+                y: _y,
+            },
+            &format!(
+                "{:>2}. {}  {}",
+                _rank + 1,
+                get_passed_time(&_entry.time),
+                _entry.date
+            ),
+            FONT_COLOR,
+            "22px selif",
+            "center",
+        );
+    }
+}
+
+/// Draw how to save the replay of a live race, or that it was saved
+fn draw_save_replay(renderer: &dyn Renderer, material: &Material) {
+    if !material.can_save_replay() {
+        return;
+    }
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_SAVE_REPLAY_Y,
+        },
+        &if material.recording_saved {
+            format!(
+                "{} ({} frames)",
+                MESSAGE_REPLAY_SAVED,
+                material.recording.len()
+            )
+        } else {
+            MESSAGE_SAVE_REPLAY.to_string()
+        },
+        FONT_COLOR,
+        "28px selif",
+        "center",
+    );
+}
+
+fn draw_gameover(renderer: &dyn Renderer) {
     renderer.text(
         &Point {
             x: TITLE_MESSAGE_X,
@@ -894,7 +1483,112 @@ fn draw_gameover(renderer: &Renderer) {
         "center",
     );
 }
-fn draw_openning(renderer: &Renderer) {
+fn draw_track_select(renderer: &dyn Renderer, material: &Material) {
+    let _track = &material.race.track;
+    renderer.text(
+        &Point {
+            x: TITLE_X,
+            y: MESSAGE_TRACK_SELECT_Y,
+        },
+        MESSAGE_TRACK_SELECT,
+        FONT_COLOR,
+        "64px myfont",
+        "center",
+    );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_TRACK_NAME_Y,
+        },
+        format!("◀  {}  ▶", _track.name).as_str(),
+        FONT_COLOR,
+        "48px myfont",
+        "center",
+    );
+    let _best = material.scoreboard.best_time(&_track.name);
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_TRACK_BEST_Y,
+        },
+        if _best == 0 {
+            "BEST TIME: --".to_string()
+        } else {
+            format!("BEST TIME: {}", get_passed_time(&_best))
+        }
+        .as_str(),
+        FONT_COLOR,
+        "32px myfont",
+        "center",
+    );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_TRACK_COUNT_Y,
+        },
+        format!(
+            "{} / {}",
+            material.tracks.selected_index() + 1,
+            material.tracks.len()
+        )
+        .as_str(),
+        FONT_COLOR,
+        "28px selif",
+        "center",
+    );
+    draw_track_preview(
+        renderer,
+        _track,
+        &Point {
+            x: CANVAS_WIDTH / 2.0,
+            y: TRACK_PREVIEW_Y,
+        },
+    );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: TRACK_PREVIEW_MESSAGE_Y,
+        },
+        TITLE_MESSAGE,
+        FONT_COLOR,
+        "36px myfont",
+        "center",
+    );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: TRACK_SELECT_HELP_Y,
+        },
+        TRACK_SELECT_HELP,
+        FONT_COLOR,
+        "20px selif",
+        "center",
+    );
+}
+/// Draw the walls of a whole track squeezed into the preview box centred on `center`
+fn draw_track_preview(renderer: &dyn Renderer, track: &Track, center: &Point) {
+    let _length = track
+        .wall_lines()
+        .map(|(p, q)| p.y.max(q.y))
+        .fold(track.finish_line.y, f32::max);
+    let _scale_x = TRACK_PREVIEW_WIDTH / CANVAS_WIDTH;
+    let _scale_y = TRACK_PREVIEW_HEIGHT / _length.max(1.0);
+    let _to_preview = |p: &Point| Point {
+        x: center.x + (p.x - CANVAS_WIDTH / 2.0) * _scale_x,
+        y: center.y + (p.y - _length / 2.0) * _scale_y,
+    };
+    for (_p, _q) in track.wall_lines() {
+        renderer.line(&_to_preview(&_p), &_to_preview(&_q));
+    }
+    renderer.arc(
+        &_to_preview(&track.start),
+        4.0,
+        Some(FONT_COLOR),
+        FONT_COLOR,
+        1.0,
+    );
+}
+fn draw_openning(renderer: &dyn Renderer, difficulty: &Difficulty) {
     renderer.text(
         &Point {
             x: TITLE_X,
@@ -915,6 +1609,16 @@ fn draw_openning(renderer: &Renderer) {
         "48px myfont",
         "center",
     );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_CONTROLS_Y,
+        },
+        MESSAGE_CONTROLS,
+        FONT_COLOR,
+        "24px selif",
+        "center",
+    );
     renderer.text(
         &Point {
             x: TITLE_MESSAGE_X,
@@ -940,7 +1644,7 @@ fn draw_openning(renderer: &Renderer) {
             x: TITLE_MESSAGE_X - MESSAGE_DISTANCE,
             y: TITLE_MESSAGE_Y - MESSAGE_DISTANCE - 80.0,
         },
-        "To Left ◀",
+        "Steer Left ◀",
         FONT_COLOR,
         "36px selif",
         "center",
@@ -950,7 +1654,7 @@ fn draw_openning(renderer: &Renderer) {
             x: TITLE_MESSAGE_X + MESSAGE_DISTANCE + 5.0,
             y: TITLE_MESSAGE_Y - MESSAGE_DISTANCE - 80.0,
         },
-        "▶ To Right",
+        "▶ Steer Right",
         FONT_COLOR,
         "36px selif",
         "center",
@@ -995,4 +1699,335 @@ fn draw_openning(renderer: &Renderer) {
         "24 myfont",
         "center",
     );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_DIFFICULTY_Y,
+        },
+        format!("Rivals  ◀  {}  ▶", difficulty.level.name()).as_str(),
+        FONT_COLOR,
+        "36px myfont",
+        "center",
+    );
+    renderer.text(
+        &Point {
+            x: TITLE_MESSAGE_X,
+            y: MESSAGE_CATCH_UP_Y,
+        },
+        format!(
+            "{} {}",
+            MESSAGE_CATCH_UP,
+            if difficulty.catch_up { "ON" } else { "OFF" }
+        )
+        .as_str(),
+        FONT_COLOR,
+        "28px selif",
+        "center",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{MemoryDownloads, MemoryStorage, RecordingRenderer, SimulatedClock};
+    use std::rc::Rc;
+
+    fn scoreboard() -> ScoreBoard {
+        ScoreBoard::load(
+            Box::new(MemoryStorage::new()),
+            Box::new(SimulatedClock::new(1_709_164_800_000.0)),
+        )
+    }
+
+    fn tracks() -> TrackRegistry {
+        let _default = include_str!("../../../cart/assets/tracks/default.json");
+        let _mirror = include_str!("../../../cart/assets/tracks/mirror.json");
+        TrackRegistry::new(vec![
+            Track::from_json(_default).unwrap(),
+            Track::from_json(_mirror).unwrap(),
+        ])
+        .unwrap()
+    }
+
+    /// The tracks of `tracks` without checkpoints, so a wreck ends the race
+    fn tracks_without_checkpoints() -> TrackRegistry {
+        let _default = include_str!("../../../cart/assets/tracks/default.json");
+        let _mirror = include_str!("../../../cart/assets/tracks/mirror.json");
+        TrackRegistry::new(
+            [_default, _mirror]
+                .iter()
+                .map(|_json| {
+                    let mut _track = Track::from_json(_json).unwrap();
+                    _track.checkpoints.clear();
+                    _track
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn stage_with(machine: GameStageStateMachine) -> GameStage {
+        GameStage {
+            machine: Some(machine),
+        }
+    }
+
+    fn keys(codes: &[&str]) -> KeyState {
+        let mut _keystate = KeyState::new();
+        codes.iter().for_each(|code| _keystate.set_pressed(code));
+        _keystate
+    }
+
+    fn ready_stage() -> GameStage {
+        stage_with(GameStageStateMachine::new(Material::new(
+            tracks(),
+            scoreboard(),
+            None,
+            None,
+            Difficulty::default(),
+        )))
+    }
+
+    /// Leaves the title screen and starts a race on the selected track
+    fn start_race(stage: &mut GameStage) {
+        stage.update(&keys(&["Space"]), &MouseState::new());
+        stage.update(&KeyState::new(), &MouseState::new());
+        stage.update(&keys(&["Space"]), &MouseState::new());
+    }
+
+    fn assert_stage_snapshot(stage: &GameStage, name: &str) {
+        let renderer = RecordingRenderer::new();
+        stage.draw(&renderer);
+        renderer.assert_snapshot(name);
+    }
+
+    #[test]
+    fn ready_stage_matches_snapshot() {
+        let _stage = ready_stage();
+        assert_stage_snapshot(&_stage, "stage_ready.txt");
+    }
+
+    #[test]
+    fn arrows_on_the_title_screen_change_the_difficulty() {
+        let mut _stage = ready_stage();
+        let _difficulty = |stage: &GameStage| match &stage.machine {
+            Some(GameStageStateMachine::Ready(_state)) => _state.material.race.difficulty,
+            _ => panic!("expected Ready"),
+        };
+        _stage.update(&keys(&["ArrowRight"]), &MouseState::new());
+        assert_eq!(_difficulty(&_stage).level, Level::Hard);
+        _stage.update(&KeyState::new(), &MouseState::new());
+        _stage.update(&keys(&["ArrowLeft"]), &MouseState::new());
+        _stage.update(&KeyState::new(), &MouseState::new());
+        _stage.update(&keys(&["ArrowLeft"]), &MouseState::new());
+        assert_eq!(_difficulty(&_stage).level, Level::Easy);
+        assert!(!_difficulty(&_stage).catch_up);
+        _stage.update(&keys(&["KeyC"]), &MouseState::new());
+        assert!(_difficulty(&_stage).catch_up);
+    }
+
+    #[test]
+    fn track_select_stage_matches_snapshot() {
+        let mut _stage = ready_stage();
+        _stage.update(&keys(&["Space"]), &MouseState::new());
+        assert!(matches!(
+            _stage.machine,
+            Some(GameStageStateMachine::TrackSelect(_))
+        ));
+        assert_stage_snapshot(&_stage, "stage_track_select.txt");
+    }
+
+    #[test]
+    fn held_arrow_selects_one_track_and_wraps_around() {
+        let mut _stage = ready_stage();
+        _stage.update(&keys(&["Space"]), &MouseState::new());
+        let _selected = |stage: &GameStage| match &stage.machine {
+            Some(GameStageStateMachine::TrackSelect(_state)) => {
+                _state.material.race.track.name.clone()
+            }
+            _ => panic!("expected Track Select"),
+        };
+        for _ in 0..5 {
+            _stage.update(&keys(&["ArrowRight"]), &MouseState::new());
+        }
+        assert_eq!(_selected(&_stage), "mirror");
+        _stage.update(&KeyState::new(), &MouseState::new());
+        _stage.update(&keys(&["ArrowRight"]), &MouseState::new());
+        assert_eq!(_selected(&_stage), "default");
+        _stage.update(&KeyState::new(), &MouseState::new());
+        _stage.update(&keys(&["ArrowLeft"]), &MouseState::new());
+        assert_eq!(_selected(&_stage), "mirror");
+    }
+
+    #[test]
+    fn playing_stage_matches_snapshot() {
+        let mut _stage = ready_stage();
+        start_race(&mut _stage);
+        for _ in 0..90 {
+            _stage.update(&keys(&["ArrowUp"]), &MouseState::new());
+        }
+        assert!(matches!(
+            _stage.machine,
+            Some(GameStageStateMachine::Playing(_))
+        ));
+        assert_stage_snapshot(&_stage, "stage_playing.txt");
+    }
+
+    #[test]
+    fn game_over_stage_matches_snapshot() {
+        let _stage = crashed_stage(None);
+        assert!(matches!(
+            _stage.machine,
+            Some(GameStageStateMachine::GameOver(_))
+        ));
+        assert_stage_snapshot(&_stage, "stage_game_over.txt");
+    }
+
+    #[test]
+    fn wrecked_cart_respawns_on_a_track_with_checkpoints() {
+        let mut _stage = ready_stage();
+        start_race(&mut _stage);
+        for _ in 0..2000 {
+            _stage.update(&keys(&["ArrowUp", "ArrowLeft"]), &MouseState::new());
+        }
+        match &_stage.machine {
+            Some(GameStageStateMachine::Playing(_state)) => {
+                assert!(_state.material.race.last_respawn.is_some());
+            }
+            _ => panic!("expected Playing"),
+        }
+    }
+
+    #[test]
+    fn game_clear_stage_matches_snapshot() {
+        let mut _material =
+            Material::new(tracks(), scoreboard(), None, None, Difficulty::default());
+        _material.scoreboard.record("default", 61_250).unwrap();
+        _material.scoreboard.record("default", 63_500).unwrap();
+        _material.score = 63_500;
+        for _frame in [1250, 2500, 3811] {
+            _material.race.lap_timer.complete_lap(_frame);
+        }
+        _material.race.lap_count = RACE_LAPS;
+        _material.race.frames = 3811;
+        _material.race.finishes = vec![(Racer::Rival(1), 3700), (Racer::Player, 3811)];
+        let _stage = stage_with(GameStageStateMachine::GameClear(GameStageState {
+            _state: GameClear,
+            material: _material,
+        }));
+        assert_stage_snapshot(&_stage, "stage_game_clear.txt");
+    }
+
+    /// Runs a race into the left wall and returns the stage at Game Over
+    fn crashed_stage(downloads: Option<Box<dyn Downloads>>) -> GameStage {
+        let mut _stage = stage_with(GameStageStateMachine::new(Material::new(
+            tracks_without_checkpoints(),
+            scoreboard(),
+            None,
+            downloads,
+            Difficulty::default(),
+        )));
+        start_race(&mut _stage);
+        for _ in 0..2000 {
+            _stage.update(&keys(&["ArrowUp", "ArrowLeft"]), &MouseState::new());
+        }
+        _stage
+    }
+
+    #[test]
+    fn replay_of_a_live_race_is_saved_once() {
+        let _downloads = Rc::new(MemoryDownloads::default());
+        let mut _stage = crashed_stage(Some(Box::new(Rc::clone(&_downloads))));
+        _stage.update(&keys(&[SAVE_REPLAY_KEY]), &MouseState::new());
+        _stage.update(&keys(&[SAVE_REPLAY_KEY]), &MouseState::new());
+        let _files = _downloads.files();
+        assert_eq!(_files.len(), 1);
+        assert_eq!(_files[0].0, REPLAY_FILE);
+        let _replay = Replay::from_json(&_files[0].1).unwrap();
+        match &_stage.machine {
+            Some(GameStageStateMachine::GameOver(_state)) => {
+                assert_eq!(_replay.len(), _state.material.recording.len());
+                assert!(_state.material.recording_saved);
+            }
+            _ => panic!("expected Game Over"),
+        }
+    }
+
+    #[test]
+    fn replay_is_not_saved_without_downloads_or_during_playback() {
+        let mut _stage = crashed_stage(None);
+        _stage.update(&keys(&[SAVE_REPLAY_KEY]), &MouseState::new());
+        match &_stage.machine {
+            Some(GameStageStateMachine::GameOver(_state)) => {
+                assert!(!_state.material.can_save_replay());
+                assert!(!_state.material.recording_saved);
+            }
+            _ => panic!("expected Game Over"),
+        }
+
+        let _downloads = Rc::new(MemoryDownloads::default());
+        let mut _stage = crashed_stage(Some(Box::new(Rc::clone(&_downloads))));
+        _stage.update(&keys(&["KeyR"]), &MouseState::new());
+        for _ in 0..2000 {
+            _stage.update(&KeyState::new(), &MouseState::new());
+        }
+        _stage.update(&keys(&[SAVE_REPLAY_KEY]), &MouseState::new());
+        assert!(matches!(
+            _stage.machine,
+            Some(GameStageStateMachine::GameOver(_))
+        ));
+        assert!(_downloads.files().is_empty());
+    }
+
+    #[test]
+    fn replay_of_an_edited_track_is_not_saved() {
+        let _downloads = Rc::new(MemoryDownloads::default());
+        let mut _stage = stage_with(GameStageStateMachine::new(Material::new(
+            tracks_without_checkpoints(),
+            scoreboard(),
+            None,
+            Some(Box::new(Rc::clone(&_downloads))),
+            Difficulty::default(),
+        )));
+        let mut _step = |codes: &[&str]| _stage.update(&keys(codes), &MouseState::new());
+        _step(&["Space"]);
+        _step(&["KeyE"]);
+        _step(&[]);
+        _step(&["KeyT"]);
+        // Test drive into the left wall
+        for _ in 0..2000 {
+            _step(&["ArrowUp", "ArrowLeft"]);
+        }
+        _step(&[SAVE_REPLAY_KEY]);
+        // Back to the editor, then race the edited track from the track select
+        _step(&["Space"]);
+        _step(&["Escape"]);
+        _step(&[]);
+        _step(&["Space"]);
+        for _ in 0..2000 {
+            _step(&["ArrowUp", "ArrowLeft"]);
+        }
+        _step(&[SAVE_REPLAY_KEY]);
+        match &_stage.machine {
+            Some(GameStageStateMachine::GameOver(_state)) => {
+                assert!(_state.material.editor.is_none());
+                assert!(!_state.material.can_save_replay());
+            }
+            _ => panic!("expected Game Over"),
+        }
+        assert!(_downloads.files().is_empty());
+    }
+
+    #[test]
+    fn edited_track_with_a_wall_gap_is_not_raced() {
+        let mut _track = (*tracks().selected()).clone();
+        check_edited_track(&_track).unwrap();
+        _track.walls[1][3] += 30.0;
+        let _error = format!("{:#}", check_edited_track(&_track).unwrap_err());
+        assert!(
+            _error.starts_with("error: gap of 30.0 between walls[1]"),
+            "{}",
+            _error
+        );
+    }
 }
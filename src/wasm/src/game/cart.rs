@@ -11,6 +11,15 @@ pub mod cart {
     /// Cart height used for collision detection and positioning
     pub const CART_HEIGHT: f32 = 50.0;
 
+    /// Fixed steps a knocked cart is stunned for before it recovers
+    pub const STUN_FRAMES: u32 = 45;
+
+    /// Share of its speed a cart keeps when it is knocked
+    const KNOCKED_SPEED_FACTOR: f32 = 0.3;
+
+    /// Heading in radians beyond which the cart is drawn turned
+    const TURNED_HEADING: f32 = 0.05;
+
     ///
     /// A cart entity in the game system.
     ///
@@ -78,89 +87,99 @@ pub mod cart {
         ///
         /// # Arguments
         /// * `velocity` - The velocity vector to apply to the cart
-        pub fn run(&mut self, velocity: Velocity) {
+        /// * `heading` - The way the cart points, in radians from straight
+        ///   up the course; positive headings turn right
+        pub fn run(&mut self, velocity: Velocity, heading: f32) {
             let _from_state_machine = self.get_state_machine();
-            let _to_state_machine = _from_state_machine.transition(Event::Run(velocity));
+            let _to_state_machine = _from_state_machine.transition(Event::Run(velocity, heading));
             self.set_state_machine(_to_state_machine);
         }
 
         ///
         /// Marks the cart as knocked (hit by collision)
         ///
-        /// Transitions the cart from running state to knocked state,
-        /// changing its visual appearance and behavior.
-        pub fn knocked(&mut self) {
-            self.state_machine = self.state_machine.clone().transition(Event::Knocked);
+        /// Transitions the cart to knocked state, changing its visual
+        /// appearance and behavior. The cart loses most of its speed, is
+        /// pushed sideways and cannot be steered until it recovers after
+        /// `STUN_FRAMES` steps. Knocking a knocked cart restarts the stun.
+        ///
+        /// # Arguments
+        /// * `_shift_x` - How far the hit pushes the cart sideways
+        pub fn knocked(&mut self, _shift_x: f32) {
+            self.state_machine = self
+                .state_machine
+                .clone()
+                .transition(Event::Knocked(_shift_x));
+        }
+
+        ///
+        /// Puts a wrecked cart back on the course
+        ///
+        /// Transitions the cart to idle state, stopped and facing straight
+        /// ahead at `position`, whatever state it was in.
+        ///
+        /// # Arguments
+        /// * `position` - Where the cart restarts
+        pub fn respawn(&mut self, position: Point) {
+            self.state_machine = self
+                .state_machine
+                .clone()
+                .transition(Event::Respawn(position));
+        }
+
+        /// Whether the cart is stunned after a hit
+        pub fn is_knocked(&self) -> bool {
+            matches!(self.state_machine, CartStateMachine::Knocked(_))
         }
 
         ///
-        /// Checks if the cart intersects with a wall line
+        /// Sweeps the cart against a wall line over one step
         ///
-        /// Performs collision detection by checking if any of the cart's boundary lines
-        /// intersect with the given wall line using line-to-line intersection algorithm.
+        /// The cart's boundary lines are moved from where they were at the
+        /// start of the step to where they are now, and the first moment any
+        /// of them touches the wall is found. Touching and collinear contact
+        /// count, and a wall cannot be skipped however far the cart moves.
         ///
         /// # Arguments
-        /// * `_wall_line` - The wall line to check intersection against
+        /// * `_wall_line` - The wall line, at its current position
+        /// * `_motion` - How far the cart moved relative to the wall during the step
         ///
         /// # Returns
-        /// * `true` - The cart is intersecting/crossing the wall line
-        /// * `false` - The cart is not intersecting the wall line
-        pub fn intersect(&self, _wall_line: Line) -> bool {
-            let mut _cart_lines = vec![];
+        /// * `Some(Contact)` - The first contact during the step
+        /// * `None` - The cart did not touch the wall
+        pub fn sweep(&self, _wall_line: Line, _motion: Velocity) -> Option<Contact> {
+            let _position = self.state_machine.context().position;
+            let _start = Point::new(_position.x - _motion.x, _position.y - _motion.y);
 
-            // upper side
-            _cart_lines.push(Line::new(
-                Point::new(
-                    self.state_machine.context().position.x - CART_WIDTH,
-                    self.state_machine.context().position.y,
-                ),
-                Point::new(
-                    self.state_machine.context().position.x + CART_WIDTH,
-                    self.state_machine.context().position.y,
-                ),
-            ));
-            // right line
-            _cart_lines.push(Line::new(
-                Point::new(
-                    self.state_machine.context().position.x + CART_WIDTH,
-                    self.state_machine.context().position.y,
-                ),
-                Point::new(
-                    self.state_machine.context().position.x + CART_WIDTH,
-                    self.state_machine.context().position.y + CART_HEIGHT,
-                ),
-            ));
-            // left line
-            _cart_lines.push(Line::new(
-                Point::new(
-                    self.state_machine.context().position.x - CART_WIDTH,
-                    self.state_machine.context().position.y - CART_HEIGHT,
-                ),
-                Point::new(
-                    self.state_machine.context().position.x - CART_WIDTH,
-                    self.state_machine.context().position.y,
-                ),
-            ));
-
-            let mut _ans: bool = false;
-
-            for _cart_line in &_cart_lines {
-                if ((_cart_line.p.x - _cart_line.q.x) * (_wall_line.p.y - _cart_line.p.y)
-                    + (_cart_line.p.y - _cart_line.q.y) * (_cart_line.p.x - _wall_line.p.x))
-                    * ((_cart_line.p.x - _cart_line.q.x) * (_wall_line.q.y - _cart_line.p.y)
-                        + (_cart_line.p.y - _cart_line.q.y) * (_cart_line.p.x - _wall_line.q.x))
-                    < 0.0
-                    && ((_wall_line.p.x - _wall_line.q.x) * (_cart_line.p.y - _wall_line.p.y)
-                        + (_wall_line.p.y - _wall_line.q.y) * (_wall_line.p.x - _cart_line.p.x))
-                        * ((_wall_line.p.x - _wall_line.q.x) * (_cart_line.q.y - _wall_line.p.y)
-                            + (_wall_line.p.y - _wall_line.q.y) * (_wall_line.p.x - _cart_line.q.x))
-                        < 0.0
-                {
-                    _ans = true;
-                    break;
+            let mut _first: Option<(f32, Point)> = None;
+            for _cart_line in cart_lines(_start) {
+                if let Some(_hit) = sweep_line(&_cart_line, _motion, &_wall_line) {
+                    if _first.is_none_or(|(t, _)| _hit.0 < t) {
+                        _first = Some(_hit);
+                    }
                 }
             }
-            return _ans;
+            let (_time, _point) = _first?;
+
+            // Unit normal of the wall, facing the side the cart came from
+            let _dx = _wall_line.q.x - _wall_line.p.x;
+            let _dy = _wall_line.q.y - _wall_line.p.y;
+            let _length = (_dx * _dx + _dy * _dy).sqrt().max(f32::EPSILON);
+            let mut _normal = Point::new(-_dy / _length, _dx / _length);
+            let _facing = _normal.x * _motion.x + _normal.y * _motion.y;
+            let _facing = if _facing.abs() > EPSILON {
+                -_facing
+            } else {
+                _normal.x * (_start.x - _point.x) + _normal.y * (_start.y - _point.y)
+            };
+            if _facing < 0.0 {
+                _normal = Point::new(-_normal.x, -_normal.y);
+            }
+            Some(Contact {
+                time: _time,
+                point: _point,
+                normal: _normal,
+            })
         }
         ///
         /// Gets the current position of the cart
@@ -181,33 +200,24 @@ pub mod cart {
         }
 
         ///
-        /// Sets the cart's direction explicitly
+        /// Gets the direction the cart is facing
         ///
-        /// # Arguments
-        /// * `direction` - The new direction for the cart
-        pub fn set_direction(&mut self, direction: CarDirection) {
-            let current_context = self.get_state_machine().context().clone();
-            let updated_context = CartContext {
-                position: current_context.position,
-                velocity: current_context.velocity,
-                direction,
-            };
+        /// # Returns
+        /// The current `CarDirection` of the cart
+        pub fn get_direction(&self) -> CarDirection {
+            self.state_machine.context().direction
+        }
 
-            // Update the context in the state machine
-            match &mut self.state_machine {
-                CartStateMachine::Idle(state) => {
-                    state.context = updated_context;
-                }
-                CartStateMachine::Running(state) => {
-                    state.context = updated_context;
-                }
-                CartStateMachine::Knocked(state) => {
-                    state.context = updated_context;
-                }
-            }
+        ///
+        /// Gets the way the cart points
+        ///
+        /// # Returns
+        /// The heading in radians from straight up the course
+        pub fn get_heading(&self) -> f32 {
+            self.state_machine.context().heading
         }
 
-        pub fn draw(&self, renderer: &Renderer) {
+        pub fn draw(&self, renderer: &dyn Renderer) {
             let state_machine = self.get_state_machine();
             let context = state_machine.context();
             let position = &Point {
@@ -239,6 +249,147 @@ pub mod cart {
         }
     }
 
+    /// Tolerance for parallel and collinear lines in collision tests
+    const EPSILON: f32 = 1e-6;
+
+    ///
+    /// Where and when the cart first touched a wall during a step
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Contact {
+        /// Fraction of the step, from 0.0 (its start) to 1.0 (its end)
+        pub time: f32,
+        /// Point of contact, with the wall at its current position
+        pub point: Point,
+        /// Unit normal of the wall, pointing towards the cart
+        pub normal: Point,
+    }
+
+    ///
+    /// Boundary lines of a cart at `_position` used for hit detection
+    ///
+    /// # Returns
+    /// The upper side, the right line and the left line
+    fn cart_lines(_position: Point) -> [Line; 3] {
+        [
+            // upper side
+            Line::new(
+                Point::new(_position.x - CART_WIDTH, _position.y),
+                Point::new(_position.x + CART_WIDTH, _position.y),
+            ),
+            // right line
+            Line::new(
+                Point::new(_position.x + CART_WIDTH, _position.y),
+                Point::new(_position.x + CART_WIDTH, _position.y + CART_HEIGHT),
+            ),
+            // left line
+            Line::new(
+                Point::new(_position.x - CART_WIDTH, _position.y - CART_HEIGHT),
+                Point::new(_position.x - CART_WIDTH, _position.y),
+            ),
+        ]
+    }
+
+    fn cross(_ax: f32, _ay: f32, _bx: f32, _by: f32) -> f32 {
+        _ax * _by - _ay * _bx
+    }
+
+    ///
+    /// Casts a ray from `_origin` along `_direction` against a line
+    ///
+    /// # Returns
+    /// The smallest `s` in `0.0..=1.0` where `_origin + s * _direction` lies
+    /// on the line, and that point
+    fn ray_hit(_origin: Point, _direction: Velocity, _line: &Line) -> Option<(f32, Point)> {
+        let _ex = _line.q.x - _line.p.x;
+        let _ey = _line.q.y - _line.p.y;
+        let _ox = _line.p.x - _origin.x;
+        let _oy = _line.p.y - _origin.y;
+        let _dd = _direction.x * _direction.x + _direction.y * _direction.y;
+        if _dd <= EPSILON {
+            return None;
+        }
+        let _scale = (_dd * (_ex * _ex + _ey * _ey)).sqrt().max(EPSILON);
+        let _denom = cross(_direction.x, _direction.y, _ex, _ey);
+        let _s = if _denom.abs() > EPSILON * _scale {
+            let _s = cross(_ox, _oy, _ex, _ey) / _denom;
+            let _u = cross(_ox, _oy, _direction.x, _direction.y) / _denom;
+            if !(0.0..=1.0).contains(&_u) {
+                return None;
+            }
+            _s
+        } else {
+            // Parallel: only a ray running along the line can touch it
+            let _offset = cross(_ox, _oy, _direction.x, _direction.y);
+            if _offset.abs() > EPSILON * _scale.max(_dd) {
+                return None;
+            }
+            let _sp = (_ox * _direction.x + _oy * _direction.y) / _dd;
+            let _sq = _sp + (_ex * _direction.x + _ey * _direction.y) / _dd;
+            if _sp.max(_sq) < 0.0 {
+                return None;
+            }
+            _sp.min(_sq).max(0.0)
+        };
+        if !(0.0..=1.0).contains(&_s) {
+            return None;
+        }
+        Some((
+            _s,
+            Point::new(_origin.x + _s * _direction.x, _origin.y + _s * _direction.y),
+        ))
+    }
+
+    ///
+    /// Finds a point shared by two lines, ends included
+    ///
+    /// # Returns
+    /// A point on both lines, or `None` if they do not touch
+    pub fn touch(_a: &Line, _b: &Line) -> Option<Point> {
+        let _direction = Velocity {
+            x: _a.q.x - _a.p.x,
+            y: _a.q.y - _a.p.y,
+        };
+        if let Some((_, _point)) = ray_hit(_a.p, _direction, _b) {
+            return Some(_point);
+        }
+        // `_a` has no length, or lies along `_b` with its start outside it
+        let _direction = Velocity {
+            x: _b.q.x - _b.p.x,
+            y: _b.q.y - _b.p.y,
+        };
+        ray_hit(_b.p, _direction, _a).map(|(_, _point)| _point)
+    }
+
+    ///
+    /// Sweeps a line by `_motion` against a fixed wall line
+    ///
+    /// The first contact is either the lines touching at the start, an end
+    /// of the moving line running into the wall, or the moving line running
+    /// into an end of the wall.
+    ///
+    /// # Returns
+    /// Fraction of `_motion` at the first contact, and the point of contact
+    /// on the wall
+    fn sweep_line(_line: &Line, _motion: Velocity, _wall: &Line) -> Option<(f32, Point)> {
+        if let Some(_point) = touch(_line, _wall) {
+            return Some((0.0, _point));
+        }
+        let _back = Velocity {
+            x: -_motion.x,
+            y: -_motion.y,
+        };
+        let mut _hits = vec![];
+        for _end_point in [_line.p, _line.q] {
+            _hits.extend(ray_hit(_end_point, _motion, _wall));
+        }
+        for _wall_point in [_wall.p, _wall.q] {
+            if let Some((_s, _)) = ray_hit(_wall_point, _back, _line) {
+                _hits.push((_s, _wall_point));
+            }
+        }
+        _hits.into_iter().min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
     ///
     /// State machine for managing cart behavior
     ///
@@ -263,12 +414,14 @@ pub mod cart {
     /// Events that can trigger state transitions in the cart's state machine.
     /// These events represent actions or occurrences that cause the cart to change state.
     pub enum Event {
-        /// Start or change the cart's movement with the specified velocity
-        Run(Velocity),
+        /// Start or change the cart's movement with the specified velocity and heading
+        Run(Velocity, f32),
         /// Update the cart's state (called each frame)
         Update,
-        /// Cart has been knocked/hit by collision
-        Knocked,
+        /// Cart has been knocked/hit by collision, and pushed sideways
+        Knocked(f32),
+        /// Wrecked cart is put back on the course at the given position
+        Respawn(Point),
     }
 
     impl CartStateMachine {
@@ -283,12 +436,27 @@ pub mod cart {
         fn transition(self, event: Event) -> Self {
             match (self.clone(), event) {
                 (CartStateMachine::Idle(state), Event::Update) => state.update().into(),
-                (CartStateMachine::Idle(state), Event::Run(velocity)) => state.run(velocity).into(),
-                (CartStateMachine::Running(state), Event::Run(velocity)) => {
-                    state.run(velocity).into()
+                (CartStateMachine::Idle(state), Event::Run(velocity, heading)) => {
+                    state.run(velocity, heading).into()
+                }
+                (CartStateMachine::Idle(state), Event::Knocked(shift_x)) => {
+                    state.knocked(shift_x).into()
+                }
+                (CartStateMachine::Running(state), Event::Run(velocity, heading)) => {
+                    state.run(velocity, heading).into()
                 }
                 (CartStateMachine::Running(state), Event::Update) => state.update().into(),
-                (CartStateMachine::Running(state), Event::Knocked) => state.knocked().into(),
+                (CartStateMachine::Running(state), Event::Knocked(shift_x)) => {
+                    state.knocked(shift_x).into()
+                }
+                (CartStateMachine::Knocked(state), Event::Update) => state.update().into(),
+                (CartStateMachine::Knocked(state), Event::Knocked(shift_x)) => {
+                    state.knocked(shift_x).into()
+                }
+                // A wrecked cart can be driving, stunned or not yet started
+                (_, Event::Respawn(position)) => {
+                    CartState::<Idle>::new(position, Velocity::default()).into()
+                }
                 _ => self,
             }
         }
@@ -409,15 +577,30 @@ pub mod cart {
         /// # Arguments
         /// * `self` - The idle cart state (consumed)
         /// * `velocity` - The velocity to apply when transitioning to running state
+        /// * `heading` - The new heading of the cart
         ///
         /// # Returns
         /// A new CartState<Running> with the applied velocity
-        pub fn run(self, velocity: Velocity) -> CartState<Running> {
+        pub fn run(self, velocity: Velocity, heading: f32) -> CartState<Running> {
             CartState {
-                context: self.context.run(velocity),
+                context: self.context.run(velocity, heading),
                 _state: Running {},
             }
         }
+        /// Transitions from idle to knocked state when hit before starting.
+        ///
+        /// # Arguments
+        /// * `self` - The idle cart state (consumed)
+        /// * `shift_x` - How far the hit pushes the cart sideways
+        ///
+        /// # Returns
+        /// CartState<Knocked> representing the cart after being hit
+        pub fn knocked(self, shift_x: f32) -> CartState<Knocked> {
+            CartState {
+                context: self.context.knocked(shift_x),
+                _state: Knocked::new(),
+            }
+        }
     }
     #[derive(Copy, Clone)]
     pub struct Running;
@@ -440,12 +623,13 @@ pub mod cart {
         /// # Arguments
         /// * `self` - The running cart state (consumed)
         /// * `velocity` - The new velocity to apply to the cart
+        /// * `heading` - The new heading of the cart
         ///
         /// # Returns
         /// CartState<Running> with updated velocity
-        pub fn run(self, velocity: Velocity) -> CartState<Running> {
+        pub fn run(self, velocity: Velocity, heading: f32) -> CartState<Running> {
             CartState {
-                context: self.context.run(velocity),
+                context: self.context.run(velocity, heading),
                 _state: Running {},
             }
         }
@@ -453,30 +637,93 @@ pub mod cart {
         ///
         /// # Arguments
         /// * `self` - The running cart state (consumed)
+        /// * `shift_x` - How far the hit pushes the cart sideways
         ///
         /// # Returns
         /// CartState<Knocked> representing the cart after being hit
-        pub fn knocked(self) -> CartState<Knocked> {
+        pub fn knocked(self, shift_x: f32) -> CartState<Knocked> {
             CartState {
-                context: self.context.knocked(),
-                _state: Knocked {},
+                context: self.context.knocked(shift_x),
+                _state: Knocked::new(),
             }
         }
     }
 
     /// Knocked state marker - represents a cart that has been hit by collision.
-    /// In the knocked state, the cart cannot perform actions and remains stationary.
+    /// In the knocked state, the cart ignores steering and keeps rolling
+    /// straight at reduced speed until the stun wears off.
     #[derive(Copy, Clone)]
-    pub struct Knocked;
+    pub struct Knocked {
+        /// Fixed steps left before the cart recovers
+        stun: u32,
+    }
+    impl Knocked {
+        fn new() -> Self {
+            Knocked { stun: STUN_FRAMES }
+        }
+    }
+
+    /// Result of updating a knocked cart for one step.
+    pub enum KnockedEndState {
+        /// The cart is still stunned
+        Stunned(CartState<Knocked>),
+        /// The stun wore off and the cart drives again
+        Recovered(CartState<Running>),
+    }
+    impl From<KnockedEndState> for CartStateMachine {
+        fn from(state: KnockedEndState) -> Self {
+            match state {
+                KnockedEndState::Stunned(knocked) => knocked.into(),
+                KnockedEndState::Recovered(running) => running.into(),
+            }
+        }
+    }
 
     /// Implementation for CartState<Knocked>.
-    /// Currently, knocked carts have no available actions and remain in this state.
-    impl CartState<Knocked> {}
+    /// Knocked carts count down their stun, can be knocked again, and
+    /// respawn when wrecked.
+    impl CartState<Knocked> {
+        /// Counts down the stun, recovering when it runs out.
+        ///
+        /// # Arguments
+        /// * `self` - The knocked cart state (consumed)
+        ///
+        /// # Returns
+        /// The knocked state while stunned, or a running state once recovered
+        pub fn update(mut self) -> KnockedEndState {
+            self.update_context();
+            self._state.stun = self._state.stun.saturating_sub(1);
+            if self._state.stun == 0 {
+                KnockedEndState::Recovered(CartState {
+                    context: self.context,
+                    _state: Running {},
+                })
+            } else {
+                KnockedEndState::Stunned(self)
+            }
+        }
+        /// Knocks the cart again, restarting the stun.
+        ///
+        /// # Arguments
+        /// * `self` - The knocked cart state (consumed)
+        /// * `shift_x` - How far the hit pushes the cart sideways
+        ///
+        /// # Returns
+        /// CartState<Knocked> with a full stun
+        pub fn knocked(self, shift_x: f32) -> CartState<Knocked> {
+            CartState {
+                context: self.context.knocked(shift_x),
+                _state: Knocked::new(),
+            }
+        }
+    }
 
     #[derive(Clone)]
     pub struct CartContext {
         position: Point,
         velocity: Velocity,
+        /// Radians from straight up the course, positive to the right
+        heading: f32,
         direction: CarDirection, // 追加：車の向き
     }
 
@@ -493,32 +740,135 @@ pub mod cart {
             CartContext {
                 position: _position,
                 velocity: _velocity,
+                heading: 0.0,
                 direction: CarDirection::Normal, // デフォルトは通常状態
             }
         }
         fn update(self) -> Self {
             self
         }
-        fn run(mut self, velocity: Velocity) -> Self {
+        fn run(mut self, velocity: Velocity, heading: f32) -> Self {
             self.velocity = velocity;
-            // 速度に応じて向きを自動設定
-            let direction = self.direction;
-            self.set_direction(direction)
+            self.heading = heading;
+            // 向きに応じて表示を自動設定
+            self.direction = if heading < -TURNED_HEADING {
+                CarDirection::Left
+            } else if heading > TURNED_HEADING {
+                CarDirection::Right
+            } else {
+                CarDirection::Normal
+            };
+            self
         }
-        fn knocked(self) -> Self {
+        fn knocked(mut self, shift_x: f32) -> Self {
+            self.position.x += shift_x;
+            self.velocity = Velocity {
+                x: 0.0,
+                y: self.velocity.y * KNOCKED_SPEED_FACTOR,
+            };
+            self.heading = 0.0;
+            self.direction = CarDirection::Normal;
             self
         }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::engine::RecordingRenderer;
+        use crate::game::physics::physics::MAX_HEADING;
 
-        /// 車の向きを設定
-        fn set_direction(mut self, direction: CarDirection) -> Self {
-            self.direction = direction;
-            // 速度に応じて自動的に向きを設定
-            if self.velocity.x < -0.1 {
-                self.direction = CarDirection::Left;
-            } else if self.velocity.x > 0.1 {
-                self.direction = CarDirection::Right;
+        fn cart_at(x: f32, y: f32) -> Cart {
+            Cart::new(Point::new(x, y), Velocity { x: 0.0, y: 0.0 })
+        }
+
+        #[test]
+        fn cart_draws_each_direction_and_knocked() {
+            let renderer = RecordingRenderer::new();
+            cart_at(400.0, CART_START_Y).draw(&renderer);
+            let mut _left = cart_at(300.0, CART_START_Y);
+            _left.run(Velocity { x: -0.8, y: 1.0 }, -MAX_HEADING);
+            _left.draw(&renderer);
+            let mut _right = cart_at(500.0, CART_START_Y);
+            _right.run(Velocity { x: 0.8, y: 1.0 }, MAX_HEADING);
+            _right.draw(&renderer);
+            let mut _knocked = cart_at(600.0, CART_START_Y);
+            _knocked.run(Velocity { x: 0.0, y: 1.0 }, 0.0);
+            _knocked.knocked(0.0);
+            _knocked.draw(&renderer);
+            renderer.assert_snapshot("cart.txt");
+        }
+
+        #[test]
+        fn knocked_cart_slows_down_and_recovers_after_the_stun() {
+            let mut _cart = cart_at(400.0, CART_START_Y);
+            _cart.run(Velocity { x: 0.8, y: 5.0 }, 0.0);
+            let _x = _cart.get_position().x;
+            _cart.knocked(-30.0);
+            assert!(_cart.is_knocked());
+            assert_eq!(_cart.get_position().x, _x - 30.0);
+            assert_eq!(_cart.get_velocity().x, 0.0);
+            assert_eq!(_cart.get_velocity().y, 5.0 * KNOCKED_SPEED_FACTOR);
+            for _ in 1..STUN_FRAMES {
+                _cart.update();
+                assert!(_cart.is_knocked());
             }
-            self
+            _cart.update();
+            assert!(!_cart.is_knocked());
+        }
+
+        #[test]
+        fn knocking_a_knocked_cart_restarts_the_stun() {
+            let mut _cart = cart_at(400.0, CART_START_Y);
+            _cart.knocked(0.0);
+            for _ in 1..STUN_FRAMES {
+                _cart.update();
+            }
+            _cart.knocked(0.0);
+            for _ in 1..STUN_FRAMES {
+                _cart.update();
+                assert!(_cart.is_knocked());
+            }
+            _cart.update();
+            assert!(!_cart.is_knocked());
+        }
+
+        #[test]
+        fn sweep_finds_a_thin_wall_far_inside_one_step() {
+            // The cart moved 1000 units in one step, from y -900 to y 100
+            let wall = Line::new(Point::new(300.0, 0.0), Point::new(500.0, 0.0));
+            let contact = cart_at(400.0, 100.0)
+                .sweep(wall, Velocity { x: 0.0, y: 1000.0 })
+                .expect("wall skipped");
+            // The right line reaches CART_HEIGHT above the cart's position
+            assert!((contact.time - 0.85).abs() < 1e-4, "time {}", contact.time);
+            assert_eq!(contact.normal, Point::new(0.0, -1.0));
+        }
+
+        #[test]
+        fn sweep_finds_a_thin_wall_crossed_diagonally() {
+            let wall = Line::new(Point::new(0.0, 0.0), Point::new(800.0, 0.0));
+            let contact = cart_at(600.0, 100.0)
+                .sweep(
+                    wall,
+                    Velocity {
+                        x: 400.0,
+                        y: 1000.0,
+                    },
+                )
+                .expect("wall skipped");
+            assert!(contact.time > 0.0 && contact.time < 1.0);
+            assert_eq!(contact.normal, Point::new(0.0, -1.0));
+        }
+
+        #[test]
+        fn sweep_misses_a_wall_beside_the_path() {
+            let wall = Line::new(Point::new(450.0, -1000.0), Point::new(450.0, 1000.0));
+            assert!(
+                cart_at(400.0, 100.0)
+                    .sweep(wall, Velocity { x: 0.0, y: 1000.0 })
+                    .is_none()
+            );
         }
     }
 }
@@ -0,0 +1,246 @@
+#[allow(clippy::module_inception)]
+pub mod checkpoint {
+    //! Checkpoints along the course.
+    //!
+    //! A checkpoint is a trigger line across the course, given in course space
+    //! like the walls of a `Track`. They are passed one after another in
+    //! driving order, heading up the course, and a lap only counts once all
+    //! of them are passed. A wrecked cart respawns at the last one passed.
+    use crate::engine::{Line, Point, Renderer};
+    use crate::game::CANVAS_HEIGHT;
+    use crate::game::cart::cart::touch;
+    use crate::game::track::track::Track;
+
+    /// Opacity of the checkpoint lines
+    const CHECKPOINT_ALPHA: f64 = 0.3;
+
+    /// Something the driver should be told about the lap in progress.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum LapWarning {
+        /// A checkpoint ahead was crossed before the ones leading to it
+        Shortcut,
+        /// The last checkpoint passed was crossed backwards
+        WrongWay,
+        /// The lap ended without every checkpoint passed, so it does not count
+        LapNotCounted,
+    }
+    impl LapWarning {
+        /// Text shown on the HUD
+        pub fn message(&self) -> &'static str {
+            match self {
+                LapWarning::Shortcut => "SHORTCUT! Checkpoint missed",
+                LapWarning::WrongWay => "WRONG WAY!",
+                LapWarning::LapNotCounted => "LAP NOT COUNTED",
+            }
+        }
+    }
+
+    /// The checkpoints of a course and how many of them this lap has passed.
+    pub struct Checkpoints {
+        lines: Vec<Line>,
+        /// Checkpoints passed this lap, which is also the index of the next one
+        passed: usize,
+    }
+    impl Checkpoints {
+        /// Reads the checkpoints of `track`, none of them passed
+        pub fn new(track: &Track) -> Self {
+            Checkpoints {
+                lines: track
+                    .checkpoint_lines()
+                    .map(|(p, q)| Line::new(p, q))
+                    .collect(),
+                passed: 0,
+            }
+        }
+        /// Whether the course has no checkpoints
+        pub fn is_empty(&self) -> bool {
+            self.lines.is_empty()
+        }
+        /// Number of checkpoints
+        pub fn len(&self) -> usize {
+            self.lines.len()
+        }
+        /// Checkpoints passed this lap
+        pub fn passed(&self) -> usize {
+            self.passed
+        }
+        ///
+        /// Follows the cart across the checkpoints
+        ///
+        /// Crossing the next checkpoint heading up the course passes it.
+        /// Crossing the last one passed backwards takes it back, and crossing
+        /// any later one is a shortcut that does not count.
+        ///
+        /// # Arguments
+        /// * `from` - Course position of the cart at the start of the step
+        /// * `to` - Course position of the cart at the end of the step
+        ///
+        /// # Returns
+        /// A warning for a wrong-way or shortcut crossing
+        pub fn update(&mut self, from: Point, to: Point) -> Option<LapWarning> {
+            let path = Line::new(from, to);
+            let crossed = |index: usize| {
+                self.lines
+                    .get(index)
+                    .filter(|line| touch(&path, line).is_some())
+                    .map(|line| ahead(line, to) - ahead(line, from))
+            };
+            if crossed(self.passed).is_some_and(|heading| heading > 0.0) {
+                self.passed += 1;
+                return None;
+            }
+            if self.passed > 0 && crossed(self.passed - 1).is_some_and(|heading| heading < 0.0) {
+                self.passed -= 1;
+                return Some(LapWarning::WrongWay);
+            }
+            if (self.passed + 1..self.lines.len())
+                .any(|index| crossed(index).is_some_and(|heading| heading > 0.0))
+            {
+                return Some(LapWarning::Shortcut);
+            }
+            None
+        }
+        ///
+        /// Ends the lap and starts the next one with no checkpoint passed
+        ///
+        /// # Returns
+        /// `LapWarning::LapNotCounted` if a checkpoint was missed
+        pub fn complete_lap(&mut self) -> Option<LapWarning> {
+            let complete = self.passed == self.lines.len();
+            self.passed = 0;
+            if complete {
+                None
+            } else {
+                Some(LapWarning::LapNotCounted)
+            }
+        }
+        /// Middle of the last checkpoint passed this lap, in course space
+        pub fn respawn_point(&self) -> Option<Point> {
+            let line = self.lines.get(self.passed.checked_sub(1)?)?;
+            Some(Point::new(
+                (line.p.x + line.q.x) / 2.0,
+                (line.p.y + line.q.y) / 2.0,
+            ))
+        }
+        ///
+        /// Draws the checkpoints on screen
+        ///
+        /// # Arguments
+        /// * `renderer` - Renderer to draw with
+        /// * `scroll` - How far the course has scrolled down the screen
+        pub fn draw(&self, renderer: &dyn Renderer, scroll: f32) {
+            renderer.set_alpha(CHECKPOINT_ALPHA);
+            for line in &self.lines {
+                let p = Point::new(line.p.x, line.p.y - scroll);
+                let q = Point::new(line.q.x, line.q.y - scroll);
+                if p.y.max(q.y) < 0.0 || p.y.min(q.y) > CANVAS_HEIGHT {
+                    continue;
+                }
+                renderer.line(&p, &q);
+            }
+            renderer.set_alpha(1.0);
+        }
+    }
+
+    /// How far `point` is on the side of `line` further up the course, scaled
+    /// by the length of `line`
+    fn ahead(line: &Line, point: Point) -> f32 {
+        let (dx, dy) = (line.q.x - line.p.x, line.q.y - line.p.y);
+        let side = dx * (point.y - line.p.y) - dy * (point.x - line.p.x);
+        // Positive on the left of `p` to `q`; flip lines drawn right to left
+        if dx < 0.0 || (dx == 0.0 && dy > 0.0) {
+            -side
+        } else {
+            side
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Checkpoints across the course at `y` 1000, 2000 and 3000; the
+        /// second one is drawn right to left
+        fn checkpoints() -> Checkpoints {
+            let json = include_str!("../../../../cart/assets/tracks/default.json");
+            let mut track = Track::from_json(json).unwrap();
+            track.checkpoints = vec![
+                [100.0, 1000.0, 700.0, 1000.0],
+                [700.0, 2000.0, 100.0, 2000.0],
+                [100.0, 3000.0, 700.0, 3000.0],
+            ];
+            Checkpoints::new(&track)
+        }
+
+        /// Drives straight up the course from `from_y` to `to_y` at `x` 400
+        fn drive(checkpoints: &mut Checkpoints, from_y: f32, to_y: f32) -> Option<LapWarning> {
+            checkpoints.update(Point::new(400.0, from_y), Point::new(400.0, to_y))
+        }
+
+        #[test]
+        fn checkpoints_pass_in_driving_order() {
+            let mut checkpoints = checkpoints();
+            assert_eq!(drive(&mut checkpoints, 900.0, 990.0), None);
+            assert_eq!(checkpoints.passed(), 0);
+            assert_eq!(drive(&mut checkpoints, 990.0, 1010.0), None);
+            assert_eq!(checkpoints.passed(), 1);
+            assert_eq!(drive(&mut checkpoints, 1990.0, 2010.0), None);
+            assert_eq!(checkpoints.passed(), 2);
+        }
+
+        #[test]
+        fn respawn_point_is_the_middle_of_the_last_checkpoint_passed() {
+            let mut checkpoints = checkpoints();
+            assert_eq!(checkpoints.respawn_point(), None);
+            drive(&mut checkpoints, 990.0, 1010.0);
+            assert_eq!(checkpoints.respawn_point(), Some(Point::new(400.0, 1000.0)));
+            drive(&mut checkpoints, 1990.0, 2010.0);
+            assert_eq!(checkpoints.respawn_point(), Some(Point::new(400.0, 2000.0)));
+            // A new lap starts again from the start line
+            checkpoints.complete_lap();
+            assert_eq!(checkpoints.respawn_point(), None);
+        }
+
+        #[test]
+        fn crossing_a_later_checkpoint_is_a_shortcut() {
+            let mut checkpoints = checkpoints();
+            drive(&mut checkpoints, 990.0, 1010.0);
+            assert_eq!(
+                drive(&mut checkpoints, 2990.0, 3010.0),
+                Some(LapWarning::Shortcut)
+            );
+            assert_eq!(checkpoints.passed(), 1);
+            assert_eq!(checkpoints.respawn_point(), Some(Point::new(400.0, 1000.0)));
+        }
+
+        #[test]
+        fn crossing_back_is_the_wrong_way() {
+            let mut checkpoints = checkpoints();
+            drive(&mut checkpoints, 990.0, 1010.0);
+            drive(&mut checkpoints, 1990.0, 2010.0);
+            // Backwards over the checkpoint drawn right to left
+            assert_eq!(
+                drive(&mut checkpoints, 2010.0, 1990.0),
+                Some(LapWarning::WrongWay)
+            );
+            assert_eq!(checkpoints.passed(), 1);
+            assert_eq!(checkpoints.respawn_point(), Some(Point::new(400.0, 1000.0)));
+            // Driving on passes it again
+            assert_eq!(drive(&mut checkpoints, 1990.0, 2010.0), None);
+            assert_eq!(checkpoints.passed(), 2);
+        }
+
+        #[test]
+        fn lap_counts_only_through_every_checkpoint() {
+            let mut checkpoints = checkpoints();
+            drive(&mut checkpoints, 990.0, 1010.0);
+            drive(&mut checkpoints, 2990.0, 3010.0);
+            assert_eq!(checkpoints.complete_lap(), Some(LapWarning::LapNotCounted));
+            assert_eq!(checkpoints.passed(), 0);
+            for y in [1000.0, 2000.0, 3000.0] {
+                assert_eq!(drive(&mut checkpoints, y - 10.0, y + 10.0), None);
+            }
+            assert_eq!(checkpoints.complete_lap(), None);
+        }
+    }
+}
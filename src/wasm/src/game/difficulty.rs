@@ -0,0 +1,147 @@
+#[allow(clippy::module_inception)]
+pub mod difficulty {
+    //! How hard the rivals race.
+    //!
+    //! A level sets the rivals' top speed, how far ahead they look for walls
+    //! and hazards, and how often they make a mistake. Catch-up mode also
+    //! speeds up rivals left behind the player and holds back those ahead,
+    //! so the race stays close.
+    use serde::{Deserialize, Serialize};
+
+    /* <-- CONSTANT VALUE */
+    /// Gap in race progress between a rival and the player at which
+    /// catch-up is strongest
+    const CATCH_UP_RANGE: f32 = 300.0;
+    /// Largest share of its pace catch-up adds to or takes from a rival
+    const CATCH_UP_STRENGTH: f32 = 0.25;
+    /* CONSTANT VALUE --> */
+
+    /// Preset for the rivals
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Level {
+        Easy,
+        #[default]
+        Normal,
+        Hard,
+    }
+    impl Level {
+        /// Factor on each rival's speed from the track
+        pub fn rival_speed(&self) -> f32 {
+            match self {
+                Level::Easy => 0.8,
+                Level::Normal => 1.0,
+                Level::Hard => 1.2,
+            }
+        }
+        /// How far ahead rivals look for walls and hazards to dodge
+        pub fn reaction(&self) -> f32 {
+            match self {
+                Level::Easy => 30.0,
+                Level::Normal => 50.0,
+                Level::Hard => 80.0,
+            }
+        }
+        /// How many mistakes a rival makes per second, on average
+        pub fn mistakes_per_second(&self) -> f64 {
+            match self {
+                Level::Easy => 0.25,
+                Level::Normal => 0.1,
+                Level::Hard => 0.02,
+            }
+        }
+        /// Name shown on the title screen
+        pub fn name(&self) -> &'static str {
+            match self {
+                Level::Easy => "Easy",
+                Level::Normal => "Normal",
+                Level::Hard => "Hard",
+            }
+        }
+        /// The next harder level, wrapping round to the easiest
+        pub fn next(&self) -> Level {
+            match self {
+                Level::Easy => Level::Normal,
+                Level::Normal => Level::Hard,
+                Level::Hard => Level::Easy,
+            }
+        }
+        /// The next easier level, wrapping round to the hardest
+        pub fn previous(&self) -> Level {
+            match self {
+                Level::Easy => Level::Hard,
+                Level::Normal => Level::Easy,
+                Level::Hard => Level::Normal,
+            }
+        }
+    }
+
+    /// Difficulty chosen on the title screen
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Difficulty {
+        pub level: Level,
+        /// Whether rivals adjust their pace by how far they are from the player
+        pub catch_up: bool,
+    }
+    impl Difficulty {
+        ///
+        /// Factor on a rival's pace from catch-up mode
+        ///
+        /// # Arguments
+        /// * `gap` - How far the rival is ahead of the player in laps and
+        ///   distance; negative when behind
+        ///
+        /// # Returns
+        /// Above 1.0 for a rival behind the player, below 1.0 for one ahead,
+        /// and always 1.0 without catch-up
+        pub fn pace(&self, gap: f32) -> f32 {
+            if !self.catch_up {
+                return 1.0;
+            }
+            1.0 - (gap / CATCH_UP_RANGE).clamp(-1.0, 1.0) * CATCH_UP_STRENGTH
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn catch_up() -> Difficulty {
+            Difficulty {
+                level: Level::Normal,
+                catch_up: true,
+            }
+        }
+
+        #[test]
+        fn pace_is_strongest_at_the_ends_of_the_range() {
+            assert_eq!(catch_up().pace(-CATCH_UP_RANGE), 1.0 + CATCH_UP_STRENGTH);
+            assert_eq!(catch_up().pace(CATCH_UP_RANGE), 1.0 - CATCH_UP_STRENGTH);
+            assert_eq!(catch_up().pace(0.0), 1.0);
+            assert_eq!(
+                catch_up().pace(CATCH_UP_RANGE / 2.0),
+                1.0 - CATCH_UP_STRENGTH / 2.0
+            );
+        }
+
+        #[test]
+        fn pace_is_held_beyond_the_range() {
+            assert_eq!(
+                catch_up().pace(-CATCH_UP_RANGE * 10.0),
+                1.0 + CATCH_UP_STRENGTH
+            );
+            assert_eq!(
+                catch_up().pace(CATCH_UP_RANGE * 10.0),
+                1.0 - CATCH_UP_STRENGTH
+            );
+        }
+
+        #[test]
+        fn pace_without_catch_up_is_even() {
+            let difficulty = Difficulty::default();
+            for gap in [-CATCH_UP_RANGE * 2.0, 0.0, CATCH_UP_RANGE * 2.0] {
+                assert_eq!(difficulty.pace(gap), 1.0);
+            }
+        }
+    }
+}
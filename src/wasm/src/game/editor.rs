@@ -0,0 +1,604 @@
+#[allow(clippy::module_inception)]
+pub mod editor {
+    //! Course editing with the mouse.
+    //!
+    //! The editor changes a `Track` in course space. The view scrolls along
+    //! the course: `scroll` is the course `y` shown at the bottom of the canvas.
+    //!
+    //! * Press on empty ground to add the current tool's item: drag out a
+    //!   wall, drop a fruit tree, or place the goal.
+    //! * Press on a wall corner, tree or goal to drag it. Corners shared by
+    //!   several walls move together. Hold Shift on a corner to draw a new
+    //!   wall from it instead.
+    //! * Delete or Backspace removes the tree or wall under the pointer.
+    //! * Z undoes the last change, up to `MAX_UNDO` changes back.
+    use crate::engine::{KeyState, MouseState, Point, Renderer};
+    use crate::game::track::track::{Decoration, Fruit, Track};
+    use crate::game::{CANVAS_HEIGHT, CANVAS_WIDTH};
+
+    /* <-- CONSTANT VALUE */
+    const FONT_COLOR: &str = "green";
+    const HIGHLIGHT_COLOR: &str = "orange";
+    /// How close the pointer must be to grab an item
+    const PICK_DISTANCE: f32 = 12.0;
+    /// Shorter drags do not create a wall
+    const MIN_WALL_LENGTH: f32 = 5.0;
+    const SCROLL_STEP: f32 = 20.0;
+    /// Changes kept for undo
+    const MAX_UNDO: usize = 50;
+    const HANDLE_SIZE: f64 = 8.0;
+    const FRUITS: [Fruit; 5] = [
+        Fruit::Apple,
+        Fruit::Orange,
+        Fruit::Cherry,
+        Fruit::Lemon,
+        Fruit::Plum,
+    ];
+    const MESSAGE_X: f32 = 30.0;
+    const MESSAGE_TOOL_Y: f32 = 960.0;
+    const MESSAGE_HELP_Y: f32 = 925.0;
+    const MESSAGE_STATUS_Y: f32 = 30.0;
+    /* CONSTANT VALUE --> */
+
+    /// What a press on empty ground adds
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Tool {
+        Wall,
+        Tree,
+        Goal,
+    }
+    impl Tool {
+        pub fn name(&self) -> &'static str {
+            match self {
+                Tool::Wall => "Wall",
+                Tool::Tree => "Tree",
+                Tool::Goal => "Goal",
+            }
+        }
+    }
+
+    /// An item the pointer can grab
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Handle {
+        /// Every wall end lying on this point
+        Corner(Point),
+        Tree(usize),
+        Goal,
+    }
+
+    #[derive(Clone, Copy)]
+    enum Drag {
+        Move(Handle),
+        /// A new wall from this point to the pointer
+        NewWall(Point),
+    }
+
+    /// A track being edited, with the editing view around it.
+    #[derive(Clone)]
+    pub struct Editor {
+        pub track: Track,
+        pub tool: Tool,
+        /// Shown under the help text, e.g. the result of an import
+        pub message: String,
+        scroll: f32,
+        /// Pointer position in course space
+        pointer: Option<Point>,
+        drag: Option<Drag>,
+        fruit: usize,
+        /// The track before each change, latest last
+        history: Vec<Track>,
+        /// Whether the current drag is already in `history`
+        drag_recorded: bool,
+        last_keystate: KeyState,
+        last_pressed: bool,
+    }
+    impl Editor {
+        ///
+        /// Starts editing `track` from the start of the course
+        ///
+        /// # Arguments
+        /// * `track` - Track to edit
+        /// * `keystate` - Keys held now; they act only once released and pressed again
+        pub fn new(track: Track, keystate: &KeyState) -> Self {
+            Editor {
+                track,
+                tool: Tool::Wall,
+                message: String::new(),
+                scroll: 0.0,
+                pointer: None,
+                drag: None,
+                fruit: 0,
+                history: vec![],
+                drag_recorded: false,
+                last_keystate: keystate.clone(),
+                last_pressed: false,
+            }
+        }
+
+        /// Continues editing after a test drive; `keystate` is handled like in `new`
+        pub fn resume(&mut self, keystate: &KeyState) {
+            self.drag = None;
+            self.last_pressed = false;
+            self.last_keystate = keystate.clone();
+        }
+
+        /// Whether `code` went down since the previous `update`
+        pub fn is_pushed(&self, keystate: &KeyState, code: &str) -> bool {
+            keystate.is_pressed(code) && !self.last_keystate.is_pressed(code)
+        }
+
+        ///
+        /// Applies one fixed step of input
+        ///
+        /// # Arguments
+        /// * `keystate` - Keys held
+        /// * `mouse` - Pointer over the canvas
+        pub fn update(&mut self, keystate: &KeyState, mouse: &MouseState) {
+            if keystate.is_pressed("ArrowUp") {
+                self.scroll += SCROLL_STEP;
+            }
+            if keystate.is_pressed("ArrowDown") {
+                self.scroll = (self.scroll - SCROLL_STEP).max(0.0);
+            }
+            if self.is_pushed(keystate, "Digit1") {
+                self.tool = Tool::Wall;
+            } else if self.is_pushed(keystate, "Digit2") {
+                self.tool = Tool::Tree;
+            } else if self.is_pushed(keystate, "Digit3") {
+                self.tool = Tool::Goal;
+            }
+
+            if self.is_pushed(keystate, "KeyZ") && self.drag.is_none() {
+                self.undo();
+            }
+
+            self.pointer = mouse.position().map(|p| Point::new(p.x, p.y + self.scroll));
+            if let Some(_pointer) = self.pointer {
+                if mouse.is_pressed() && !self.last_pressed {
+                    self.press(
+                        _pointer,
+                        keystate.is_pressed("ShiftLeft") || keystate.is_pressed("ShiftRight"),
+                    );
+                } else if mouse.is_pressed() {
+                    self.drag_to(_pointer);
+                } else if self.last_pressed {
+                    self.release(_pointer);
+                }
+                if self.is_pushed(keystate, "Delete") || self.is_pushed(keystate, "Backspace") {
+                    self.delete(_pointer);
+                }
+            }
+
+            self.last_pressed = mouse.is_pressed();
+            self.last_keystate = keystate.clone();
+        }
+
+        /// Keeps the track as it is now for `undo`
+        fn remember(&mut self) {
+            if self.history.len() == MAX_UNDO {
+                self.history.remove(0);
+            }
+            self.history.push(self.track.clone());
+        }
+
+        /// Goes back to the track before the last change
+        pub fn undo(&mut self) {
+            match self.history.pop() {
+                Some(track) => {
+                    self.track = track;
+                    self.message = String::new();
+                }
+                None => self.message = "Nothing to undo".to_string(),
+            }
+        }
+
+        fn press(&mut self, pointer: Point, shift: bool) {
+            // Moves are remembered once the handle leaves its place
+            self.drag_recorded = false;
+            self.drag = match (self.pick(pointer), self.tool) {
+                (Some(Handle::Corner(corner)), _) if shift => Some(Drag::NewWall(corner)),
+                (Some(handle), _) => Some(Drag::Move(handle)),
+                (None, Tool::Wall) => Some(Drag::NewWall(pointer)),
+                (None, Tool::Tree) => {
+                    self.remember();
+                    self.drag_recorded = true;
+                    self.track.decorations.push(Decoration {
+                        x: pointer.x,
+                        y: pointer.y,
+                        fruit: FRUITS[self.fruit],
+                    });
+                    self.fruit = (self.fruit + 1) % FRUITS.len();
+                    Some(Drag::Move(Handle::Tree(self.track.decorations.len() - 1)))
+                }
+                (None, Tool::Goal) => {
+                    self.remember();
+                    self.drag_recorded = true;
+                    self.track.finish_line = pointer;
+                    Some(Drag::Move(Handle::Goal))
+                }
+            };
+        }
+
+        fn drag_to(&mut self, pointer: Point) {
+            let Some(Drag::Move(handle)) = self.drag else {
+                return;
+            };
+            if self.handle_position(handle) == Some(pointer) {
+                return;
+            }
+            if !self.drag_recorded {
+                self.remember();
+                self.drag_recorded = true;
+            }
+            match handle {
+                Handle::Corner(corner) => {
+                    for wall in self.track.walls.iter_mut() {
+                        for end in [0, 2] {
+                            if wall[end] == corner.x && wall[end + 1] == corner.y {
+                                wall[end] = pointer.x;
+                                wall[end + 1] = pointer.y;
+                            }
+                        }
+                    }
+                    self.drag = Some(Drag::Move(Handle::Corner(pointer)));
+                }
+                Handle::Tree(index) => {
+                    if let Some(tree) = self.track.decorations.get_mut(index) {
+                        tree.x = pointer.x;
+                        tree.y = pointer.y;
+                    }
+                }
+                Handle::Goal => self.track.finish_line = pointer,
+            }
+        }
+
+        fn release(&mut self, pointer: Point) {
+            if let Some(Drag::NewWall(start)) = self.drag {
+                // Ending on a corner joins the new wall to it
+                let end = match self.pick_corner(pointer) {
+                    Some(corner) => corner,
+                    None => pointer,
+                };
+                if distance(start, end) >= MIN_WALL_LENGTH {
+                    self.remember();
+                    self.track.walls.push([start.x, start.y, end.x, end.y]);
+                }
+            }
+            self.drag = None;
+        }
+
+        fn delete(&mut self, pointer: Point) {
+            if let Some(Handle::Tree(index)) = self.pick(pointer) {
+                self.remember();
+                self.track.decorations.remove(index);
+                return;
+            }
+            if let Some(index) = self.pick_wall(pointer) {
+                self.remember();
+                self.track.walls.remove(index);
+            }
+        }
+
+        /// Where `handle` is now; `None` for a tree that is gone
+        fn handle_position(&self, handle: Handle) -> Option<Point> {
+            match handle {
+                Handle::Corner(corner) => Some(corner),
+                Handle::Tree(index) => self
+                    .track
+                    .decorations
+                    .get(index)
+                    .map(|tree| Point::new(tree.x, tree.y)),
+                Handle::Goal => Some(self.track.finish_line),
+            }
+        }
+
+        /// The item under `pointer`; the goal wins over trees, trees over corners
+        fn pick(&self, pointer: Point) -> Option<Handle> {
+            if distance(pointer, self.track.finish_line) < PICK_DISTANCE {
+                return Some(Handle::Goal);
+            }
+            if let Some(index) = self
+                .track
+                .decorations
+                .iter()
+                .position(|tree| distance(pointer, Point::new(tree.x, tree.y)) < PICK_DISTANCE)
+            {
+                return Some(Handle::Tree(index));
+            }
+            self.pick_corner(pointer).map(Handle::Corner)
+        }
+
+        fn pick_corner(&self, pointer: Point) -> Option<Point> {
+            self.track
+                .wall_lines()
+                .flat_map(|(p, q)| [p, q])
+                .find(|corner| distance(pointer, *corner) < PICK_DISTANCE)
+        }
+
+        fn pick_wall(&self, pointer: Point) -> Option<usize> {
+            self.track
+                .wall_lines()
+                .position(|(p, q)| segment_distance(pointer, p, q) < PICK_DISTANCE)
+        }
+
+        fn to_screen(&self, p: Point) -> Point {
+            Point::new(p.x, p.y - self.scroll)
+        }
+
+        pub fn draw(&self, renderer: &dyn Renderer) {
+            let _hover = self.pointer.and_then(|pointer| self.pick(pointer));
+            let _color = |handle: Handle| {
+                if _hover == Some(handle) {
+                    HIGHLIGHT_COLOR
+                } else {
+                    FONT_COLOR
+                }
+            };
+
+            for (_p, _q) in self.track.wall_lines() {
+                renderer.line(&self.to_screen(_p), &self.to_screen(_q));
+            }
+            for (_p, _q) in self.track.wall_lines() {
+                for _corner in [_p, _q] {
+                    let _corner_color = _color(Handle::Corner(_corner));
+                    renderer.rect(
+                        &self.to_screen(_corner),
+                        HANDLE_SIZE,
+                        HANDLE_SIZE,
+                        _corner_color,
+                        _corner_color,
+                        1.0,
+                    );
+                }
+            }
+            for (_index, _tree) in self.track.decorations.iter().enumerate() {
+                let _position = self.to_screen(Point::new(_tree.x, _tree.y));
+                renderer.draw_fruit_tree(&_position, _tree.fruit.name());
+                renderer.arc(
+                    &_position,
+                    PICK_DISTANCE as f64,
+                    None,
+                    _color(Handle::Tree(_index)),
+                    1.0,
+                );
+            }
+            renderer.text(
+                &self.to_screen(self.track.finish_line),
+                "GOAL",
+                _color(Handle::Goal),
+                "32px myfont",
+                "center",
+            );
+            renderer.arc(
+                &self.to_screen(self.track.start),
+                6.0,
+                Some(FONT_COLOR),
+                FONT_COLOR,
+                1.0,
+            );
+            if let (Some(Drag::NewWall(_start)), Some(_pointer)) = (self.drag, self.pointer) {
+                renderer.line(&self.to_screen(_start), &self.to_screen(_pointer));
+            }
+
+            renderer.text(
+                &Point::new(MESSAGE_X, MESSAGE_TOOL_Y),
+                format!(
+                    "EDIT {}  Tool: {}  y: {:.0}",
+                    self.track.name,
+                    self.tool.name(),
+                    self.scroll
+                )
+                .as_str(),
+                FONT_COLOR,
+                "28px selif",
+                "left",
+            );
+            renderer.text(
+                &Point::new(MESSAGE_X, MESSAGE_HELP_Y),
+                "1 Wall 2 Tree 3 Goal  Del Delete  Z Undo  ▲▼ Scroll  T Test drive  S Export  O Import  Esc Back",
+                FONT_COLOR,
+                "18px selif",
+                "left",
+            );
+            renderer.text(
+                &Point::new(CANVAS_WIDTH / 2.0, MESSAGE_STATUS_Y),
+                &self.message,
+                FONT_COLOR,
+                "24px selif",
+                "center",
+            );
+            // Hint at the course continuing off screen
+            if self.scroll > 0.0 {
+                renderer.text(
+                    &Point::new(CANVAS_WIDTH - MESSAGE_X, MESSAGE_STATUS_Y),
+                    "▼",
+                    FONT_COLOR,
+                    "24px selif",
+                    "right",
+                );
+            }
+            renderer.text(
+                &Point::new(CANVAS_WIDTH - MESSAGE_X, CANVAS_HEIGHT - 100.0),
+                "▲",
+                FONT_COLOR,
+                "24px selif",
+                "right",
+            );
+        }
+    }
+
+    fn distance(a: Point, b: Point) -> f32 {
+        ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+    }
+
+    /// Distance from `p` to the segment `a`-`b`
+    fn segment_distance(p: Point, a: Point, b: Point) -> f32 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx * dx + dy * dy;
+        if length == 0.0 {
+            return distance(p, a);
+        }
+        let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / length).clamp(0.0, 1.0);
+        distance(p, Point::new(a.x + t * dx, a.y + t * dy))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// The default track with two walls meeting at (100, 300), no trees
+        /// and the goal far up the course
+        fn editor() -> Editor {
+            let mut track =
+                Track::from_json(include_str!("../../../../cart/assets/tracks/default.json"))
+                    .unwrap();
+            track.walls = vec![[100.0, 100.0, 100.0, 300.0], [100.0, 300.0, 300.0, 500.0]];
+            track.decorations.clear();
+            track.finish_line = Point::new(400.0, 5000.0);
+            Editor::new(track, &KeyState::new())
+        }
+
+        fn keys(codes: &[&str]) -> KeyState {
+            let mut keystate = KeyState::new();
+            codes.iter().for_each(|code| keystate.set_pressed(code));
+            keystate
+        }
+
+        fn mouse(x: f32, y: f32, pressed: bool) -> MouseState {
+            let mut mouse = MouseState::new();
+            mouse.set_position(Point::new(x, y));
+            mouse.set_pressed(pressed);
+            mouse
+        }
+
+        /// Presses at `from`, drags to `to` and releases there
+        fn drag(editor: &mut Editor, from: (f32, f32), to: (f32, f32)) {
+            editor.update(&KeyState::new(), &mouse(from.0, from.1, true));
+            editor.update(&KeyState::new(), &mouse(to.0, to.1, true));
+            editor.update(&KeyState::new(), &mouse(to.0, to.1, false));
+        }
+
+        fn push(editor: &mut Editor, code: &str, at: (f32, f32)) {
+            editor.update(&keys(&[code]), &mouse(at.0, at.1, false));
+            editor.update(&KeyState::new(), &mouse(at.0, at.1, false));
+        }
+
+        #[test]
+        fn new_wall_snaps_onto_a_nearby_corner() {
+            let mut editor = editor();
+            drag(&mut editor, (500.0, 100.0), (306.0, 495.0));
+            assert_eq!(editor.track.walls[2], [500.0, 100.0, 300.0, 500.0]);
+        }
+
+        #[test]
+        fn short_drag_adds_no_wall() {
+            let mut editor = editor();
+            drag(&mut editor, (500.0, 100.0), (502.0, 102.0));
+            assert_eq!(editor.track.walls.len(), 2);
+        }
+
+        #[test]
+        fn goal_is_picked_before_trees_and_trees_before_corners() {
+            let mut editor = editor();
+            let corner = Point::new(100.0, 300.0);
+            editor.track.decorations.push(Decoration {
+                x: 105.0,
+                y: 300.0,
+                fruit: Fruit::Apple,
+            });
+            editor.track.finish_line = Point::new(100.0, 305.0);
+            assert_eq!(editor.pick(corner), Some(Handle::Goal));
+            editor.track.finish_line = Point::new(400.0, 5000.0);
+            assert_eq!(editor.pick(corner), Some(Handle::Tree(0)));
+            editor.track.decorations.clear();
+            assert_eq!(editor.pick(corner), Some(Handle::Corner(corner)));
+            assert_eq!(editor.pick(Point::new(700.0, 700.0)), None);
+        }
+
+        #[test]
+        fn dragging_a_corner_moves_every_wall_end_on_it() {
+            let mut editor = editor();
+            drag(&mut editor, (104.0, 296.0), (150.0, 320.0));
+            assert_eq!(
+                editor.track.walls,
+                vec![[100.0, 100.0, 150.0, 320.0], [150.0, 320.0, 300.0, 500.0]]
+            );
+        }
+
+        #[test]
+        fn delete_removes_the_tree_before_the_wall_under_the_pointer() {
+            let mut editor = editor();
+            editor.tool = Tool::Tree;
+            drag(&mut editor, (100.0, 200.0), (100.0, 200.0));
+            assert_eq!(editor.track.decorations.len(), 1);
+            push(&mut editor, "Delete", (100.0, 200.0));
+            assert!(editor.track.decorations.is_empty());
+            assert_eq!(editor.track.walls.len(), 2);
+            push(&mut editor, "Backspace", (100.0, 200.0));
+            assert_eq!(editor.track.walls, vec![[100.0, 300.0, 300.0, 500.0]]);
+        }
+
+        #[test]
+        fn pointer_follows_the_scroll() {
+            let mut editor = editor();
+            editor.tool = Tool::Tree;
+            editor.update(&keys(&["ArrowUp"]), &MouseState::new());
+            drag(&mut editor, (500.0, 100.0), (500.0, 100.0));
+            let tree = editor.track.decorations[0];
+            assert_eq!(
+                Point::new(tree.x, tree.y),
+                Point::new(500.0, 100.0 + SCROLL_STEP)
+            );
+        }
+
+        #[test]
+        fn undo_reverts_one_change_at_a_time() {
+            let mut editor = editor();
+            drag(&mut editor, (500.0, 100.0), (500.0, 400.0));
+            drag(&mut editor, (500.0, 400.0), (600.0, 400.0));
+            assert_eq!(editor.track.walls[2], [500.0, 100.0, 600.0, 400.0]);
+            push(&mut editor, "KeyZ", (0.0, 0.0));
+            assert_eq!(editor.track.walls[2], [500.0, 100.0, 500.0, 400.0]);
+            push(&mut editor, "KeyZ", (0.0, 0.0));
+            assert_eq!(editor.track.walls.len(), 2);
+            push(&mut editor, "KeyZ", (0.0, 0.0));
+            assert_eq!(editor.message, "Nothing to undo");
+        }
+
+        #[test]
+        fn grabbing_without_moving_is_not_a_change() {
+            let mut editor = editor();
+            drag(&mut editor, (100.0, 300.0), (100.0, 300.0));
+            assert!(editor.history.is_empty());
+        }
+
+        #[test]
+        fn undo_keeps_only_the_latest_changes() {
+            let mut editor = editor();
+            for step in 0..MAX_UNDO + 5 {
+                let x = 20.0 * step as f32;
+                drag(&mut editor, (x, 700.0), (x, 800.0));
+            }
+            for _ in 0..MAX_UNDO {
+                editor.undo();
+            }
+            assert_eq!(editor.track.walls.len(), 2 + 5);
+        }
+
+        #[test]
+        fn exported_track_loads_back() {
+            let mut editor = editor();
+            drag(&mut editor, (500.0, 100.0), (500.0, 400.0));
+            editor.tool = Tool::Tree;
+            drag(&mut editor, (600.0, 200.0), (650.0, 250.0));
+            editor.tool = Tool::Goal;
+            drag(&mut editor, (400.0, 900.0), (400.0, 900.0));
+            let json = editor.track.to_json().unwrap();
+            let loaded = Track::from_json(&json).unwrap();
+            assert_eq!(loaded.to_json().unwrap(), json);
+            assert_eq!(loaded.walls, editor.track.walls);
+            assert_eq!(loaded.decorations[0].x, 650.0);
+            assert_eq!(loaded.finish_line, Point::new(400.0, 900.0));
+        }
+    }
+}
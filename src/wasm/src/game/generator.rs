@@ -0,0 +1,565 @@
+#[allow(clippy::module_inception)]
+pub mod generator {
+    //! Procedural courses.
+    //!
+    //! A course is a chain of sections between control points along `y`.
+    //! Each control point gives the corridor centre and half width, and the
+    //! left and right walls join the control points with straight segments.
+    //! Because both walls are linear between the same control points, the
+    //! corridor is never narrower than at its narrowest control point.
+    //!
+    //! Everything is drawn from a `ChaCha8Rng`, whose output is the same on
+    //! every platform, so a seed always gives the same course. Positions are
+    //! rounded to whole units to keep exported track files readable.
+    use crate::engine::Point;
+    use crate::game::cart::cart::CART_WIDTH;
+    use crate::game::hazard::hazard::{Hazard, HazardKind, HazardSpawn};
+    use crate::game::pad::pad::{PAD_WIDTH, PadKind, PadSpawn};
+    use crate::game::surface::surface::{Surface, SurfaceZone};
+    use crate::game::track::track::{Decoration, Fruit, RivalSpawn, Track};
+    use crate::game::{CANVAS_HEIGHT, CANVAS_WIDTH, CART_START_Y, distance_to_course};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    /* <-- CONSTANT VALUE */
+    /// Narrowest gap between two walls: room for three carts side by side
+    pub const MIN_CORRIDOR: f32 = CART_WIDTH * 2.0 * 3.0;
+    /// Widest corridor, as on the start straight
+    const MAX_CORRIDOR: f32 = 600.0;
+    /// Walls stay this far inside the canvas
+    const WALL_MARGIN: f32 = 50.0;
+    /// Length of the straights at both ends of the course
+    const STRAIGHT_LENGTH: f32 = 1000.0;
+    const SECTION_MIN: f32 = 400.0;
+    const SECTION_MAX: f32 = 800.0;
+    /// Largest sideways shift of the corridor centre per unit of `y`
+    const MAX_SLOPE: f32 = 0.5;
+    const ISLAND_CHANCE: f64 = 0.35;
+    const ISLAND_MIN_HALF_WIDTH: f32 = 30.0;
+    /// Gap left above and below an island inside its section
+    const ISLAND_GAP: f32 = 100.0;
+    const LAP_LENGTH_MIN: u32 = 40;
+    const LAP_LENGTH_MAX: u32 = 55;
+    const LAP_LENGTH_STEP: f32 = 100.0;
+    const TREE_COUNT: usize = 10;
+    const TREE_MARGIN: f32 = 40.0;
+    const TREE_EDGE: f32 = 20.0;
+    const FRUITS: [Fruit; 5] = [
+        Fruit::Apple,
+        Fruit::Orange,
+        Fruit::Cherry,
+        Fruit::Lemon,
+        Fruit::Plum,
+    ];
+    /// Rival start positions on the start straight, with their number
+    const RIVAL_STARTS: [(f32, f32, usize); 3] =
+        [(320.0, 200.0, 2), (220.0, 120.0, 2), (580.0, 100.0, 1)];
+    const RIVAL_SPEED_MIN: f32 = 1.0;
+    const RIVAL_SPEED_MAX: f32 = 2.0;
+    /// Seconds added to the race time for each respawn
+    const RESPAWN_PENALTY: f32 = 3.0;
+    /// A checkpoint is placed at every this many control points
+    const CHECKPOINT_EVERY: usize = 2;
+    /// Width of the grass verge inside each wall
+    const GRASS_WIDTH: f32 = 20.0;
+    /// How far a sand trap reaches beyond its island
+    const SAND_MARGIN: f32 = 40.0;
+    /// A row of item boxes is placed at every this many control points
+    const ITEM_ROW_EVERY: usize = 3;
+    /// Chance of a hazard halfway along a section without an island
+    const HAZARD_CHANCE: f64 = 0.4;
+    const HAZARD_KINDS: [HazardKind; 3] = [HazardKind::Oil, HazardKind::Cone, HazardKind::Barrel];
+    /* CONSTANT VALUE --> */
+
+    /// Corridor at one control point
+    #[derive(Clone, Copy)]
+    struct Section {
+        y: f32,
+        centre: f32,
+        half_width: f32,
+    }
+    impl Section {
+        fn left(&self) -> f32 {
+            self.centre - self.half_width
+        }
+        fn right(&self) -> f32 {
+            self.centre + self.half_width
+        }
+    }
+
+    ///
+    /// Generates a course
+    ///
+    /// # Arguments
+    /// * `seed` - Any number; the same seed always gives the same course
+    ///
+    /// # Returns
+    /// A valid track named after the seed
+    pub fn generate(seed: u64) -> Track {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let lap_length = rng.gen_range(LAP_LENGTH_MIN..=LAP_LENGTH_MAX) as f32 * LAP_LENGTH_STEP;
+        // The course must fill the screen ahead of the cart when the lap ends
+        let finish = distance_to_course(lap_length);
+        let course_end = finish + CANVAS_HEIGHT - CART_START_Y;
+        let final_straight = course_end - STRAIGHT_LENGTH - CANVAS_HEIGHT / 2.0;
+
+        let straight = |y: f32| Section {
+            y,
+            centre: CANVAS_WIDTH / 2.0,
+            half_width: MAX_CORRIDOR / 2.0,
+        };
+        let mut sections = vec![straight(0.0), straight(STRAIGHT_LENGTH)];
+        let mut islands: Vec<[f32; 4]> = vec![];
+        let mut last = sections[1];
+        // The section into the final straight is left at least SECTION_MIN
+        // plus room for a full bend
+        while final_straight - last.y >= SECTION_MIN + SECTION_MAX {
+            let length = rng.gen_range(SECTION_MIN..=SECTION_MAX).round();
+            let y = last.y + length;
+            let next = if rng.gen_bool(ISLAND_CHANCE)
+                && last.half_width - MIN_CORRIDOR >= ISLAND_MIN_HALF_WIDTH
+            {
+                islands.extend(island(&mut rng, &last, length));
+                Section { y, ..last }
+            } else {
+                bend(&mut rng, &last, length)
+            };
+            sections.push(next);
+            last = next;
+        }
+        sections.push(straight(final_straight));
+        sections.push(straight(course_end));
+
+        let mut walls: Vec<[f32; 4]> = vec![];
+        for pair in sections.windows(2) {
+            walls.push([pair[0].left(), pair[0].y, pair[1].left(), pair[1].y]);
+        }
+        for pair in sections.windows(2) {
+            walls.push([pair[0].right(), pair[0].y, pair[1].right(), pair[1].y]);
+        }
+        walls.extend(islands.iter().copied());
+
+        // Checkpoints span the corridor at control points from the end of
+        // the start straight to the start of the final one, where they are
+        // clear of the islands
+        let checkpoints = sections[1..sections.len() - 1]
+            .iter()
+            .step_by(CHECKPOINT_EVERY)
+            .map(|section| [section.left(), section.y, section.right(), section.y])
+            .collect();
+
+        let mut surfaces = verges(&sections);
+        surfaces.extend(islands.chunks(4).map(sand_trap));
+        let pads = islands.chunks(4).flat_map(boost_pads).collect();
+        // Rows of item boxes across the corridor, starting after the first
+        // checkpoint; control points are clear of the islands
+        let item_boxes = sections[2..sections.len() - 1]
+            .iter()
+            .step_by(ITEM_ROW_EVERY)
+            .flat_map(|section| {
+                [-0.5, 0.0, 0.5].map(|side| {
+                    Point::new(
+                        (section.centre + side * section.half_width).round(),
+                        section.y,
+                    )
+                })
+            })
+            .collect();
+        let racing_line = racing_line(&sections, &islands);
+
+        let decorations = trees(&mut rng, &sections, course_end);
+        let rivals = RIVAL_STARTS
+            .iter()
+            .map(|&(x, y, no)| RivalSpawn {
+                x,
+                y,
+                speed: (rng.gen_range(RIVAL_SPEED_MIN..=RIVAL_SPEED_MAX) * 10.0).round() / 10.0,
+                no,
+            })
+            .collect();
+        let hazards = hazards(&mut rng, &sections, &islands);
+
+        Track {
+            name: format!("seed {}", seed),
+            lap_length,
+            sectors: vec![(lap_length / 3.0).round(), (lap_length * 2.0 / 3.0).round()],
+            start: Point::new(CANVAS_WIDTH / 2.0, CART_START_Y),
+            finish_line: Point::new(CANVAS_WIDTH / 2.0, finish + 10.0),
+            walls,
+            checkpoints,
+            respawn_penalty: RESPAWN_PENALTY,
+            surfaces,
+            pads,
+            item_boxes,
+            hazards,
+            decorations,
+            rivals,
+            racing_line,
+        }
+    }
+
+    /// Seed of the day for a `YYYY-MM-DD` date, e.g. 20240131
+    pub fn seed_of_the_day(date: &str) -> u64 {
+        date.chars()
+            .filter_map(|c| c.to_digit(10))
+            .fold(0, |seed, digit| seed * 10 + digit as u64)
+    }
+
+    /// A section `length` further on, with a new width and centre
+    fn bend(rng: &mut ChaCha8Rng, last: &Section, length: f32) -> Section {
+        let half_width = rng
+            .gen_range(MIN_CORRIDOR / 2.0..=MAX_CORRIDOR / 2.0)
+            .round();
+        let shift = length * MAX_SLOPE;
+        let low = (WALL_MARGIN + half_width).max(last.centre - shift);
+        let high = (CANVAS_WIDTH - WALL_MARGIN - half_width).min(last.centre + shift);
+        // A wide corridor far to one side may not be reachable; narrow it
+        let (centre, half_width) = if low <= high {
+            (rng.gen_range(low..=high).round(), half_width)
+        } else {
+            let centre = last.centre;
+            let room = (centre - WALL_MARGIN).min(CANVAS_WIDTH - WALL_MARGIN - centre);
+            (centre, half_width.min(room))
+        };
+        Section {
+            y: last.y + length,
+            centre,
+            half_width,
+        }
+    }
+
+    /// A diamond island in a straight section, leaving `MIN_CORRIDOR` on both sides
+    fn island(rng: &mut ChaCha8Rng, section: &Section, length: f32) -> Vec<[f32; 4]> {
+        let half_width = rng
+            .gen_range(ISLAND_MIN_HALF_WIDTH..=section.half_width - MIN_CORRIDOR)
+            .round();
+        let room = section.half_width - MIN_CORRIDOR - half_width;
+        let x = section.centre + rng.gen_range(-room..=room).round();
+        let y = section.y + (length / 2.0).round();
+        let half_height = (length / 2.0).round() - ISLAND_GAP;
+        let corners = [
+            (x, y - half_height),
+            (x + half_width, y),
+            (x, y + half_height),
+            (x - half_width, y),
+        ];
+        (0..corners.len())
+            .map(|i| {
+                let (p, q) = (corners[i], corners[(i + 1) % corners.len()]);
+                [p.0, p.1, q.0, q.1]
+            })
+            .collect()
+    }
+
+    /// Grass verges running inside the left and right walls
+    fn verges(sections: &[Section]) -> Vec<SurfaceZone> {
+        let verge = |wall: fn(&Section) -> f32, inward: f32| SurfaceZone {
+            surface: Surface::Grass,
+            points: sections
+                .iter()
+                .map(|section| [wall(section), section.y])
+                .chain(
+                    sections
+                        .iter()
+                        .rev()
+                        .map(|section| [wall(section) + inward, section.y]),
+                )
+                .collect(),
+        };
+        vec![
+            verge(Section::left, GRASS_WIDTH),
+            verge(Section::right, -GRASS_WIDTH),
+        ]
+    }
+
+    /// A sand trap around the diamond island made of `walls`
+    fn sand_trap(walls: &[[f32; 4]]) -> SurfaceZone {
+        let corners: Vec<[f32; 2]> = walls.iter().map(|w| [w[0], w[1]]).collect();
+        let x = corners.iter().map(|c| c[0]).sum::<f32>() / corners.len() as f32;
+        let y = corners.iter().map(|c| c[1]).sum::<f32>() / corners.len() as f32;
+        SurfaceZone {
+            surface: Surface::Sand,
+            points: corners
+                .iter()
+                .map(|c| {
+                    let (dx, dy) = (c[0] - x, c[1] - y);
+                    let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+                    [
+                        (c[0] + dx / length * SAND_MARGIN).round(),
+                        (c[1] + dy / length * SAND_MARGIN).round(),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Boost pads beside the widest point of the diamond island made of
+    /// `walls`, just clear of its sand trap, for carts taking the tight line
+    fn boost_pads(walls: &[[f32; 4]]) -> [PadSpawn; 2] {
+        // The second and fourth corners are the island's right and left tips
+        let offset = SAND_MARGIN + PAD_WIDTH / 2.0;
+        let pad = |corner: &[f32; 4], side: f32| PadSpawn {
+            kind: PadKind::Boost,
+            x: corner[0] + side * offset,
+            y: corner[1],
+        };
+        [pad(&walls[1], 1.0), pad(&walls[3], -1.0)]
+    }
+
+    /// Racing line through the corridor centres, passing each island on its
+    /// wider side from its top corner to its bottom one
+    fn racing_line(sections: &[Section], islands: &[[f32; 4]]) -> Vec<Point> {
+        // The line holds its last waypoint to the end of the course, whose
+        // far edge is off the course
+        let mut points: Vec<Point> = sections[..sections.len() - 1]
+            .iter()
+            .map(|section| Point::new(section.centre, section.y))
+            .collect();
+        for walls in islands.chunks(4) {
+            // The corners run top, right tip, bottom, left tip
+            let (top, right, bottom, left) = (walls[0], walls[1], walls[2], walls[3]);
+            let Some(section) = sections.iter().rev().find(|section| section.y <= right[1]) else {
+                continue;
+            };
+            let x = if section.right() - right[0] >= left[0] - section.left() {
+                (right[0] + section.right()) / 2.0
+            } else {
+                (section.left() + left[0]) / 2.0
+            };
+            points.extend([top[1], right[1], bottom[1]].map(|y| Point::new(x.round(), y)));
+        }
+        points.sort_by(|a, b| a.y.total_cmp(&b.y));
+        points.dedup_by(|b, a| b.y <= a.y);
+        points
+    }
+
+    /// Hazards halfway along sections between the straights, beside one wall
+    /// and leaving `MIN_CORRIDOR` open beside them. Sections with an island
+    /// are left clear, and control points hold the checkpoints and item boxes.
+    fn hazards(
+        rng: &mut ChaCha8Rng,
+        sections: &[Section],
+        islands: &[[f32; 4]],
+    ) -> Vec<HazardSpawn> {
+        let mut hazards = vec![];
+        for pair in sections[1..sections.len() - 2].windows(2) {
+            if !rng.gen_bool(HAZARD_CHANCE) {
+                continue;
+            }
+            let kind = HAZARD_KINDS[rng.gen_range(0..HAZARD_KINDS.len())];
+            let left_side = rng.gen_bool(0.5);
+            let y = ((pair[0].y + pair[1].y) / 2.0).round();
+            if islands
+                .iter()
+                .any(|wall| (pair[0].y..=pair[1].y).contains(&wall[1]))
+            {
+                continue;
+            }
+            // The walls are straight along the section, so the corridor
+            // beside the hazard is narrowest at its top or bottom
+            let (radius_x, radius_y) = kind.radius();
+            let t = |y: f32| (y - pair[0].y) / (pair[1].y - pair[0].y);
+            let wall = |side: fn(&Section) -> f32, y: f32| {
+                side(&pair[0]) + (side(&pair[1]) - side(&pair[0])) * t(y)
+            };
+            let (low, high) = (y - radius_y, y + radius_y);
+            let left = wall(Section::left, low).max(wall(Section::left, high));
+            let right = wall(Section::right, low).min(wall(Section::right, high));
+            let x = if left_side {
+                (left + GRASS_WIDTH + radius_x).ceil()
+            } else {
+                (right - GRASS_WIDTH - radius_x).floor()
+            };
+            let spawn = HazardSpawn { kind, x, y };
+            // A cart must still fit through on the far side
+            let (clear_left, clear_right) = if left_side {
+                (right - MIN_CORRIDOR, right)
+            } else {
+                (left, left + MIN_CORRIDOR)
+            };
+            if !Hazard::from_spawn(&spawn).blocks(clear_left, clear_right, low, high) {
+                hazards.push(spawn);
+            }
+        }
+        hazards
+    }
+
+    /// Fruit trees outside the walls, where there is room for them
+    fn trees(rng: &mut ChaCha8Rng, sections: &[Section], course_end: f32) -> Vec<Decoration> {
+        let mut decorations = vec![];
+        for _ in 0..TREE_COUNT {
+            let y = rng
+                .gen_range(STRAIGHT_LENGTH / 2.0..course_end - STRAIGHT_LENGTH / 2.0)
+                .round();
+            let fruit = FRUITS[rng.gen_range(0..FRUITS.len())];
+            let left_side = rng.gen_bool(0.5);
+            let Some(pair) = sections.windows(2).find(|pair| pair[1].y >= y) else {
+                continue;
+            };
+            let t = (y - pair[0].y) / (pair[1].y - pair[0].y);
+            let left = pair[0].left() + (pair[1].left() - pair[0].left()) * t;
+            let right = pair[0].right() + (pair[1].right() - pair[0].right()) * t;
+            // The wall may run further out above or below; keep clear of both ends
+            let left = left.min(pair[0].left()).min(pair[1].left()) - TREE_MARGIN;
+            let right = right.max(pair[0].right()).max(pair[1].right()) + TREE_MARGIN;
+            let x = if left_side && left > TREE_EDGE {
+                (TREE_EDGE + left) / 2.0
+            } else if right < CANVAS_WIDTH - TREE_EDGE {
+                (right + CANVAS_WIDTH - TREE_EDGE) / 2.0
+            } else if left > TREE_EDGE {
+                (TREE_EDGE + left) / 2.0
+            } else {
+                continue;
+            };
+            decorations.push(Decoration {
+                x: x.round(),
+                y,
+                fruit,
+            });
+        }
+        decorations
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::game::racing_line::racing_line::RacingLine;
+        use crate::game::track_check::track_check::check_track;
+
+        /// Seeds of the tests: a run of small ones and a few seeds of the day
+        fn seeds() -> impl Iterator<Item = u64> {
+            (0..24).chain([20240131, 20240229, 20251231, u64::MAX])
+        }
+
+        /// Where the walls cross the line across the course at `y`, left to right
+        fn crossings(track: &Track, y: f32) -> Vec<f32> {
+            let mut xs: Vec<f32> = track
+                .wall_lines()
+                .filter(|(p, q)| p.y.min(q.y) < y && y < p.y.max(q.y))
+                .map(|(p, q)| p.x + (q.x - p.x) * (y - p.y) / (q.y - p.y))
+                .collect();
+            xs.sort_by(f32::total_cmp);
+            xs
+        }
+
+        #[test]
+        fn same_seed_gives_same_track() {
+            for seed in seeds() {
+                assert_eq!(
+                    generate(seed).to_json().unwrap(),
+                    generate(seed).to_json().unwrap(),
+                    "seed {}",
+                    seed
+                );
+            }
+            assert_ne!(
+                generate(1).to_json().unwrap(),
+                generate(2).to_json().unwrap()
+            );
+        }
+
+        #[test]
+        fn generated_tracks_are_valid() {
+            for seed in seeds() {
+                let track = generate(seed);
+                track.validate().unwrap();
+                assert_eq!(track.name, format!("seed {}", seed));
+            }
+        }
+
+        #[test]
+        fn generated_tracks_pass_track_check() {
+            for seed in seeds() {
+                let issues: Vec<String> = check_track(&generate(seed))
+                    .iter()
+                    .map(|issue| issue.to_string())
+                    .collect();
+                assert!(issues.is_empty(), "seed {}: {:?}", seed, issues);
+            }
+        }
+
+        #[test]
+        fn corridors_are_never_narrower_than_the_minimum() {
+            for seed in seeds() {
+                let track = generate(seed);
+                let end = track
+                    .wall_lines()
+                    .map(|(p, q)| p.y.max(q.y))
+                    .fold(0.0, f32::max);
+                let mut y = 0.5;
+                while y < end {
+                    // Left wall, then island sides in pairs, then right wall:
+                    // every even gap is open course
+                    let xs = crossings(&track, y);
+                    assert_eq!(xs.len() % 2, 0, "seed {} y {}: {:?}", seed, y, xs);
+                    for pair in xs.chunks(2) {
+                        assert!(
+                            pair[1] - pair[0] >= MIN_CORRIDOR - 0.5,
+                            "seed {} y {}: {:?}",
+                            seed,
+                            y,
+                            xs
+                        );
+                    }
+                    y += 50.0;
+                }
+            }
+        }
+
+        #[test]
+        fn hazards_leave_a_corridor_beside_them() {
+            let mut count = 0;
+            for seed in seeds() {
+                let track = generate(seed);
+                for hazard in &track.hazards {
+                    // Walls crossing the hazard's row, nearest on each side
+                    let xs = crossings(&track, hazard.y);
+                    let left = xs
+                        .iter()
+                        .copied()
+                        .filter(|x| *x < hazard.x)
+                        .fold(0.0, f32::max);
+                    let right = xs
+                        .iter()
+                        .copied()
+                        .filter(|x| *x > hazard.x)
+                        .fold(CANVAS_WIDTH, f32::min);
+                    let (radius_x, _) = hazard.kind.radius();
+                    let open = (hazard.x - radius_x - left).max(right - hazard.x - radius_x);
+                    assert!(
+                        open >= MIN_CORRIDOR,
+                        "seed {}: {:?} leaves {}",
+                        seed,
+                        hazard,
+                        open
+                    );
+                }
+                count += track.hazards.len();
+            }
+            assert!(count > 0);
+        }
+
+        #[test]
+        fn racing_line_stays_on_the_open_course() {
+            for seed in seeds() {
+                let track = generate(seed);
+                let line = RacingLine::new(&track).expect("no racing line");
+                let last = track.racing_line[track.racing_line.len() - 1].y;
+                let mut y = track.racing_line[0].y + 0.5;
+                while y < last {
+                    let x = line.x_at(y);
+                    let xs = crossings(&track, y);
+                    let left = xs.iter().filter(|wall_x| **wall_x < x).count();
+                    assert_eq!(left % 2, 1, "seed {} y {}: x {} in {:?}", seed, y, x, xs);
+                    y += 25.0;
+                }
+            }
+        }
+
+        #[test]
+        fn seed_of_the_day_reads_the_date_digits() {
+            assert_eq!(seed_of_the_day("2024-01-31"), 20240131);
+            assert_ne!(seed_of_the_day("2024-02-01"), seed_of_the_day("2024-01-02"));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+#[allow(clippy::module_inception)]
+pub mod ghost {
+    //! Ghost car replaying the player's best lap.
+    //!
+    //! The ghost is only drawn; it takes no part in collision detection.
+    use crate::engine::{Point, Renderer};
+    use crate::game::cart::cart::CarDirection;
+    use crate::game::{CANVAS_HEIGHT, CART_START_Y, distance_to_course};
+
+    /// Opacity of the ghost car
+    const GHOST_ALPHA: f64 = 0.35;
+    /// Margin above and below the canvas where the ghost is still drawn
+    const GHOST_DRAW_MARGIN: f32 = 50.0;
+
+    /// Cart position at one fixed step of a lap
+    #[derive(Clone, Copy)]
+    pub struct GhostSample {
+        pub x: f32,
+        pub distance: f32,
+        pub direction: CarDirection,
+    }
+
+    /// Per-frame cart positions of one complete lap.
+    #[derive(Clone, Default)]
+    pub struct Ghost {
+        samples: Vec<GhostSample>,
+    }
+    impl Ghost {
+        pub fn new(samples: Vec<GhostSample>) -> Self {
+            Ghost { samples }
+        }
+        /// Lap time of the ghost in fixed steps
+        pub fn frames(&self) -> u32 {
+            self.samples.len() as u32
+        }
+        ///
+        /// Draws the ghost relative to the live cart
+        ///
+        /// # Arguments
+        /// * `renderer` - Renderer to draw with
+        /// * `frame` - Fixed steps since the live cart started its lap
+        /// * `distance` - Lap distance of the live cart
+        pub fn draw(&self, renderer: &dyn Renderer, frame: u32, distance: f32) {
+            // Past the end of its lap the ghost waits on the finish line
+            let Some(sample) = self
+                .samples
+                .get(frame as usize)
+                .or_else(|| self.samples.last())
+            else {
+                return;
+            };
+            let _y = CART_START_Y + distance_to_course(sample.distance - distance);
+            if !(-GHOST_DRAW_MARGIN..=CANVAS_HEIGHT + GHOST_DRAW_MARGIN).contains(&_y) {
+                return;
+            }
+            let position = &Point::new(sample.x, _y);
+            renderer.set_alpha(GHOST_ALPHA);
+            match sample.direction {
+                CarDirection::Left => renderer.draw_left_facing_racing_car(position),
+                CarDirection::Right => renderer.draw_right_facing_racing_car(position),
+                CarDirection::Normal => renderer.draw_normal_racing_car(position),
+            }
+            renderer.set_alpha(1.0);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::engine::{DrawCommand, RecordingRenderer};
+
+        fn ghost_at(distance: f32) -> Ghost {
+            Ghost::new(vec![GhostSample {
+                x: 300.0,
+                distance,
+                direction: CarDirection::Normal,
+            }])
+        }
+
+        #[test]
+        fn ghost_is_drawn_twice_its_lead_ahead_of_the_cart() {
+            let renderer = RecordingRenderer::new();
+            ghost_at(250.0).draw(&renderer, 0, 100.0);
+            let commands = renderer.commands();
+            assert_eq!(
+                commands.first(),
+                Some(&DrawCommand::Alpha { alpha: GHOST_ALPHA })
+            );
+            assert_eq!(commands.last(), Some(&DrawCommand::Alpha { alpha: 1.0 }));
+            // The first wheel of the car is drawn level with its position
+            let Some(DrawCommand::Arc { center, .. }) = commands.get(1) else {
+                panic!("ghost car not drawn: {:?}", commands);
+            };
+            assert_eq!(center.y, CART_START_Y + 300.0);
+        }
+
+        #[test]
+        fn ghost_off_screen_is_not_drawn() {
+            let renderer = RecordingRenderer::new();
+            ghost_at(1000.0).draw(&renderer, 0, 0.0);
+            assert!(renderer.commands().is_empty());
+        }
+    }
+}
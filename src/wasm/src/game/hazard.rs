@@ -0,0 +1,263 @@
+#[allow(clippy::all)]
+pub mod hazard {
+    //! Hazards on the course.
+    //!
+    //! Hazards sit on the course and scroll with it like the walls, but a cart
+    //! can drive into them: an oil slick spins the cart out, a cone slows it
+    //! and is knocked aside, and a barrel knocks the cart back. Each kind has
+    //! its own collider, an ellipse around its centre.
+    use crate::engine::{Point, Renderer, Velocity};
+    use crate::game::cart::cart::CART_WIDTH;
+    use crate::game::{Piece, State, StateMachine};
+    use serde::{Deserialize, Serialize};
+
+    /* <-- CONSTANT VALUE */
+    /// Share of a scattered cone's speed kept from one step to the next
+    const SCATTER_DECAY: f32 = 0.9;
+    /// Speed per step a cone is knocked aside with
+    const SCATTER_SPEED: f32 = 6.0;
+    /* CONSTANT VALUE --> */
+
+    /// What a hazard does to a cart driving into it
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum HazardKind {
+        Oil,
+        Cone,
+        Barrel,
+    }
+    impl HazardKind {
+        /// Half extents of the hazard across and along the course
+        pub fn radius(&self) -> (f32, f32) {
+            match self {
+                HazardKind::Oil => (30.0, 20.0),
+                HazardKind::Cone => (8.0, 8.0),
+                HazardKind::Barrel => (16.0, 16.0),
+            }
+        }
+    }
+
+    /// Where a hazard stands on the course, as stored in a track file
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct HazardSpawn {
+        pub kind: HazardKind,
+        /// Centre of the hazard in course space
+        pub x: f32,
+        pub y: f32,
+    }
+
+    /// An oil slick, cone or barrel on the course.
+    ///
+    /// `p` is the lower left corner and `q` the upper right one of the
+    /// hazard's bounds.
+    #[derive(Clone, Copy)]
+    pub struct Hazard {
+        pub state_machine: StateMachine,
+        pub kind: HazardKind,
+        /// Speed per step of a cone knocked aside; a scattered cone no
+        /// longer gets in the way
+        scatter: Option<Velocity>,
+    }
+    impl Piece for Hazard {
+        /// Creates a barrel between the corners `p` and `q`
+        fn new(p: Point, q: Point, velocity: Velocity) -> Self {
+            Hazard {
+                state_machine: StateMachine::Running(State::new(p, q, velocity)),
+                kind: HazardKind::Barrel,
+                scatter: None,
+            }
+        }
+        fn get_state_machine(&self) -> StateMachine {
+            self.state_machine
+        }
+        fn set_state_machine(&mut self, _state_machine: StateMachine) {
+            self.state_machine = _state_machine.update();
+        }
+
+        /// Draws the hazard in the style of its kind
+        fn draw(&self, renderer: &dyn Renderer) {
+            let _center = self.center();
+            let (_rx, _ry) = self.kind.radius();
+            match self.kind {
+                HazardKind::Oil => {
+                    renderer.ellipse(
+                        &_center,
+                        _rx as f64,
+                        _ry as f64,
+                        Some("#1a1a1a"),
+                        "#4d4d4d",
+                        1.0,
+                    );
+                    renderer.ellipse(
+                        &Point::new(_center.x - _rx / 3.0, _center.y + _ry / 3.0),
+                        (_rx / 3.0) as f64,
+                        (_ry / 4.0) as f64,
+                        Some("#5a4a7a"),
+                        "#5a4a7a",
+                        1.0,
+                    );
+                }
+                HazardKind::Cone if self.scatter.is_some() => {
+                    // Knocked over, lying on its side
+                    renderer.ellipse(
+                        &_center,
+                        (_rx * 1.5) as f64,
+                        (_ry / 2.0) as f64,
+                        Some("#ff8c00"),
+                        "#cc5500",
+                        1.0,
+                    );
+                }
+                HazardKind::Cone => {
+                    renderer.polygon(
+                        &[
+                            Point::new(_center.x - _rx, _center.y - _ry),
+                            Point::new(_center.x + _rx, _center.y - _ry),
+                            Point::new(_center.x, _center.y + _ry * 1.5),
+                        ],
+                        "#ff8c00",
+                    );
+                    renderer.rect(&_center, _rx as f64, 3.0, "white", "white", 0.0);
+                }
+                HazardKind::Barrel => {
+                    renderer.arc(&_center, _rx as f64, Some("#b22222"), "#333333", 2.0);
+                    renderer.arc(&_center, (_rx / 2.0) as f64, None, "#333333", 1.5);
+                }
+            }
+        }
+    }
+    impl Hazard {
+        /// Creates the hazard described in a track file at its start position
+        pub fn from_spawn(spawn: &HazardSpawn) -> Self {
+            let (_rx, _ry) = spawn.kind.radius();
+            let mut _hazard = Hazard::new(
+                Point::new(spawn.x - _rx, spawn.y - _ry),
+                Point::new(spawn.x + _rx, spawn.y + _ry),
+                Velocity { x: 0.0, y: 0.0 },
+            );
+            _hazard.kind = spawn.kind;
+            _hazard
+        }
+
+        /// Centre of the hazard on screen
+        pub fn center(&self) -> Point {
+            let (_p, _q) = (self.p(), self.q());
+            Point::new((_p.x + _q.x) / 2.0, (_p.y + _q.y) / 2.0)
+        }
+
+        /// Whether the hazard can still be hit; a scattered cone cannot
+        pub fn is_standing(&self) -> bool {
+            self.scatter.is_none()
+        }
+
+        /// Whether a cart at `position` drives into the hazard
+        pub fn touches(&self, position: Point) -> bool {
+            if !self.is_standing() {
+                return false;
+            }
+            let _center = self.center();
+            let (_rx, _ry) = self.kind.radius();
+            let _dx = (position.x - _center.x) / (_rx + CART_WIDTH / 2.0);
+            let _dy = (position.y - _center.y) / _ry;
+            _dx * _dx + _dy * _dy <= 1.0
+        }
+
+        ///
+        /// Whether the hazard stands in part of the course
+        ///
+        /// # Arguments
+        /// * `_left` - Left edge of the part
+        /// * `_right` - Right edge of the part
+        /// * `_low` - Lower edge of the part
+        /// * `_high` - Upper edge of the part
+        pub fn blocks(&self, _left: f32, _right: f32, _low: f32, _high: f32) -> bool {
+            let (_p, _q) = (self.p(), self.q());
+            self.is_standing() && _right >= _p.x && _left <= _q.x && _high >= _p.y && _low <= _q.y
+        }
+
+        /// Knocks a cone aside, away from a cart at `position` and up the course
+        pub fn scatter(&mut self, position: Point) {
+            let _side = if self.center().x < position.x {
+                -1.0
+            } else {
+                1.0
+            };
+            self.scatter = Some(Velocity {
+                x: _side * SCATTER_SPEED,
+                y: SCATTER_SPEED,
+            });
+        }
+
+        /// Moves a scattered cone on by one step, slowing it down
+        pub fn slide(&mut self) {
+            let Some(_scatter) = self.scatter else {
+                return;
+            };
+            let (_p, _q) = (self.p(), self.q());
+            self.state_machine = StateMachine::Running(State::new(
+                Point::new(_p.x + _scatter.x, _p.y + _scatter.y),
+                Point::new(_q.x + _scatter.x, _q.y + _scatter.y),
+                Velocity { x: 0.0, y: 0.0 },
+            ));
+            self.scatter = Some(Velocity {
+                x: _scatter.x * SCATTER_DECAY,
+                y: _scatter.y * SCATTER_DECAY,
+            });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// A hazard of `kind` centred on (400, 500)
+        fn hazard(kind: HazardKind) -> Hazard {
+            Hazard::from_spawn(&HazardSpawn {
+                kind,
+                x: 400.0,
+                y: 500.0,
+            })
+        }
+
+        #[test]
+        fn cone_stops_colliding_once_scattered() {
+            let mut _cone = hazard(HazardKind::Cone);
+            assert!(_cone.touches(Point::new(400.0, 500.0)));
+            _cone.scatter(Point::new(410.0, 500.0));
+            assert!(!_cone.is_standing());
+            assert!(!_cone.touches(Point::new(400.0, 500.0)));
+            assert!(!_cone.blocks(0.0, 800.0, 0.0, 1000.0));
+        }
+
+        #[test]
+        fn scattered_cone_slides_away_from_the_cart_and_slows_down() {
+            let mut _cone = hazard(HazardKind::Cone);
+            _cone.scatter(Point::new(410.0, 500.0));
+            let mut _center = _cone.center();
+            let mut _step = f32::INFINITY;
+            for _ in 0..30 {
+                _cone.slide();
+                let _dx = _cone.center().x - _center.x;
+                let _dy = _cone.center().y - _center.y;
+                // Away from the cart on its right, and up the course
+                assert!(_dx < 0.0 && _dy > 0.0);
+                assert!(_dy < _step);
+                _step = _dy;
+                _center = _cone.center();
+            }
+            assert!(_step < SCATTER_SPEED * 0.1);
+        }
+
+        #[test]
+        fn standing_hazard_blocks_the_parts_it_overlaps() {
+            let _barrel = hazard(HazardKind::Barrel);
+            let (_rx, _ry) = HazardKind::Barrel.radius();
+            assert!(_barrel.blocks(380.0, 390.0, 490.0, 510.0));
+            assert!(_barrel.blocks(400.0 + _rx, 700.0, 0.0, 1000.0));
+            assert!(!_barrel.blocks(400.0 + _rx + 1.0, 700.0, 0.0, 1000.0));
+            assert!(!_barrel.blocks(100.0, 400.0 - _rx - 1.0, 0.0, 1000.0));
+            assert!(!_barrel.blocks(0.0, 800.0, 500.0 + _ry + 1.0, 1000.0));
+        }
+    }
+}
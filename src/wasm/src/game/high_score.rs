@@ -0,0 +1,193 @@
+#[allow(clippy::module_inception)]
+pub mod high_score {
+    //! Best times kept across page reloads.
+    use crate::engine::{Clock, Storage};
+    use anyhow::{Context, Result};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    /// Storage key of the serialized `HighScores`
+    const STORAGE_KEY: &str = "cartclaude.highscores";
+    /// Number of entries kept in the ranking table of each track
+    pub const RANKING_SIZE: usize = 10;
+
+    /// One finished race in a ranking table
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct ScoreEntry {
+        /// Race time in milliseconds
+        pub time: i32,
+        /// Date of the race as `YYYY-MM-DD`
+        pub date: String,
+    }
+
+    /// Best times on one track; times on tracks of different lengths are
+    /// never compared.
+    #[derive(Clone, Default, Serialize, Deserialize)]
+    pub struct TrackScores {
+        /// Best total race time, 0 when never finished
+        pub best_total: i32,
+        /// Fastest races, quickest first
+        pub ranking: Vec<ScoreEntry>,
+    }
+
+    /// Persisted best times, keyed by track name.
+    #[derive(Clone, Default, Serialize, Deserialize)]
+    pub struct HighScores {
+        pub tracks: BTreeMap<String, TrackScores>,
+    }
+    impl HighScores {
+        ///
+        /// Adds a finished race
+        ///
+        /// # Arguments
+        /// * `track` - Name of the track raced on
+        /// * `time` - Race time in milliseconds
+        /// * `date` - Date of the race as `YYYY-MM-DD`
+        pub fn record(&mut self, track: &str, time: i32, date: String) {
+            let scores = self.tracks.entry(track.into()).or_default();
+            if scores.best_total == 0 || time < scores.best_total {
+                scores.best_total = time;
+            }
+            scores.ranking.push(ScoreEntry { time, date });
+            scores.ranking.sort_by_key(|entry| entry.time);
+            scores.ranking.truncate(RANKING_SIZE);
+        }
+    }
+
+    /// `HighScores` together with where they are saved.
+    pub struct ScoreBoard {
+        pub scores: HighScores,
+        storage: Box<dyn Storage>,
+        clock: Box<dyn Clock>,
+    }
+    impl ScoreBoard {
+        ///
+        /// Loads the saved high scores
+        ///
+        /// Missing or unreadable data starts an empty board, so a broken
+        /// save never prevents the game from starting.
+        ///
+        /// # Arguments
+        /// * `storage` - Where the high scores are saved
+        /// * `clock` - Gives the date of each new record
+        pub fn load(storage: Box<dyn Storage>, clock: Box<dyn Clock>) -> Self {
+            let scores = match Self::read(storage.as_ref()) {
+                Ok(scores) => scores,
+                Err(err) => {
+                    log!("Error loading high scores {:#?}", err);
+                    HighScores::default()
+                }
+            };
+            ScoreBoard {
+                scores,
+                storage,
+                clock,
+            }
+        }
+
+        fn read(storage: &dyn Storage) -> Result<HighScores> {
+            match storage.get(STORAGE_KEY)? {
+                Some(json) => serde_json::from_str(&json).context("Error parsing high scores"),
+                None => Ok(HighScores::default()),
+            }
+        }
+
+        /// Best time on `track` in milliseconds, 0 when never finished
+        pub fn best_time(&self, track: &str) -> i32 {
+            self.scores
+                .tracks
+                .get(track)
+                .map_or(0, |scores| scores.best_total)
+        }
+
+        /// Fastest races on `track`, quickest first
+        pub fn ranking(&self, track: &str) -> &[ScoreEntry] {
+            self.scores
+                .tracks
+                .get(track)
+                .map_or(&[], |scores| scores.ranking.as_slice())
+        }
+
+        /// Adds a finished race and saves the board
+        pub fn record(&mut self, track: &str, time: i32) -> Result<()> {
+            self.scores.record(track, time, self.clock.today());
+            let json =
+                serde_json::to_string(&self.scores).context("Error serializing high scores")?;
+            self.storage.set(STORAGE_KEY, &json)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::engine::{MemoryStorage, SimulatedClock};
+        use std::rc::Rc;
+
+        /// 2024-02-29 in milliseconds since the Unix epoch
+        const LEAP_DAY: f64 = 1_709_164_800_000.0;
+
+        fn load(storage: &Rc<MemoryStorage>) -> ScoreBoard {
+            ScoreBoard::load(
+                Box::new(Rc::clone(storage)),
+                Box::new(SimulatedClock::new(LEAP_DAY)),
+            )
+        }
+
+        fn times(entries: &[ScoreEntry]) -> Vec<(i32, &str)> {
+            entries
+                .iter()
+                .map(|entry| (entry.time, entry.date.as_str()))
+                .collect()
+        }
+
+        #[test]
+        fn scoreboard_survives_a_reload() {
+            let storage = Rc::new(MemoryStorage::new());
+            let mut board = load(&storage);
+            assert_eq!(board.best_time("default"), 0);
+            board.record("default", 65_000).unwrap();
+            board.record("default", 61_500).unwrap();
+            board.record("mirror", 70_250).unwrap();
+
+            let reloaded = load(&storage);
+            assert_eq!(reloaded.best_time("default"), 61_500);
+            assert_eq!(reloaded.best_time("mirror"), 70_250);
+            assert_eq!(reloaded.best_time("seed 1"), 0);
+            assert_eq!(
+                times(reloaded.ranking("default")),
+                vec![(61_500, "2024-02-29"), (65_000, "2024-02-29")]
+            );
+            assert_eq!(
+                times(reloaded.ranking("mirror")),
+                vec![(70_250, "2024-02-29")]
+            );
+            assert!(reloaded.ranking("seed 1").is_empty());
+        }
+
+        #[test]
+        fn tracks_keep_their_own_best_and_ranking() {
+            let mut scores = HighScores::default();
+            scores.record("long", 90_000, "2024-01-01".into());
+            scores.record("short", 30_000, "2024-01-02".into());
+            scores.record("long", 95_000, "2024-01-03".into());
+            // A quick time on a short track is no record on a long one
+            assert_eq!(scores.tracks["long"].best_total, 90_000);
+            assert_eq!(scores.tracks["short"].best_total, 30_000);
+            assert_eq!(scores.tracks["long"].ranking.len(), 2);
+            assert_eq!(scores.tracks["short"].ranking.len(), 1);
+        }
+
+        #[test]
+        fn ranking_keeps_the_fastest_races() {
+            let mut scores = HighScores::default();
+            for time in (1..=RANKING_SIZE as i32 + 5).rev() {
+                scores.record("default", time * 1000, "2024-01-01".into());
+            }
+            let ranking = &scores.tracks["default"].ranking;
+            assert_eq!(ranking.len(), RANKING_SIZE);
+            assert_eq!(ranking[0].time, 1000);
+            assert_eq!(ranking[RANKING_SIZE - 1].time, RANKING_SIZE as i32 * 1000);
+            assert_eq!(scores.tracks["default"].best_total, 1000);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+#[allow(clippy::module_inception)]
+pub mod item {
+    //! Item boxes and the items they give.
+    //!
+    //! Item boxes sit on the course and scroll with it like the walls. A cart
+    //! with an empty item slot that drives through a box takes one random
+    //! item and keeps it until its driver uses it. Oil slicks dropped on the
+    //! course scroll the same way until a cart runs into them.
+    use crate::engine::{Point, Renderer, Velocity};
+    use crate::game::cart::cart::CART_WIDTH;
+    use crate::game::{Piece, State, StateMachine};
+    use rand::Rng;
+
+    /* <-- CONSTANT VALUE */
+    /// Key that uses the item in the slot
+    pub const USE_ITEM_KEY: &str = "KeyX";
+    /// Size of an item box along both sides
+    const ITEM_BOX_SIZE: f32 = 30.0;
+    /// Half the length of an oil slick across the course
+    const OIL_SLICK_RADIUS_X: f32 = 24.0;
+    /// Half the length of an oil slick along the course
+    const OIL_SLICK_RADIUS_Y: f32 = 16.0;
+    /// How far behind a cart its oil slick is dropped
+    pub const OIL_SLICK_DROP: f32 = 70.0;
+    /// Radius of the bubble drawn around a shielded cart
+    const SHIELD_RADIUS: f64 = 28.0;
+    /// Every item, in the order they are drawn from
+    const ITEMS: [Item; 3] = [Item::Shield, Item::SpeedBurst, Item::OilSlick];
+    /* CONSTANT VALUE --> */
+
+    /// What an item box gives
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Item {
+        /// Absorbs the next wall hit or oil slick
+        Shield,
+        /// A short boost, as from a boost pad
+        SpeedBurst,
+        /// Dropped behind the cart; carts running into it swerve
+        OilSlick,
+    }
+    impl Item {
+        /// Draws one item, each as likely as the others
+        pub fn random(rng: &mut impl Rng) -> Item {
+            ITEMS[rng.gen_range(0..ITEMS.len())]
+        }
+        /// Name shown in the item slot
+        pub fn name(&self) -> &'static str {
+            match self {
+                Item::Shield => "Shield",
+                Item::SpeedBurst => "Burst",
+                Item::OilSlick => "Oil",
+            }
+        }
+    }
+
+    /// A box giving a random item, once per lap.
+    ///
+    /// `p` is the lower left corner and `q` the upper right one.
+    #[derive(Clone, Copy)]
+    pub struct ItemBox {
+        pub state_machine: StateMachine,
+        /// Whether a cart already took the item this lap
+        pub used: bool,
+    }
+    impl Piece for ItemBox {
+        fn new(p: Point, q: Point, velocity: Velocity) -> Self {
+            ItemBox {
+                state_machine: StateMachine::Running(State::new(p, q, velocity)),
+                used: false,
+            }
+        }
+        fn get_state_machine(&self) -> StateMachine {
+            self.state_machine
+        }
+        fn set_state_machine(&mut self, _state_machine: StateMachine) {
+            self.state_machine = _state_machine.update();
+        }
+
+        /// Draws the box with a question mark, unless it was taken
+        fn draw(&self, renderer: &dyn Renderer) {
+            if self.used {
+                return;
+            }
+            let (_p, _q) = (self.p(), self.q());
+            let _center = Point::new((_p.x + _q.x) / 2.0, (_p.y + _q.y) / 2.0);
+            renderer.rect(
+                &_center,
+                (_q.x - _p.x) as f64,
+                (_q.y - _p.y) as f64,
+                "#3399ff",
+                "white",
+                2.0,
+            );
+            renderer.text(
+                &Point::new(_center.x, _center.y - 8.0),
+                "?",
+                "white",
+                "20 myfont",
+                "center",
+            );
+        }
+    }
+    impl ItemBox {
+        /// Creates a box centred on `center` at its start position
+        pub fn at(center: Point) -> Self {
+            ItemBox::new(
+                Point::new(
+                    center.x - ITEM_BOX_SIZE / 2.0,
+                    center.y - ITEM_BOX_SIZE / 2.0,
+                ),
+                Point::new(
+                    center.x + ITEM_BOX_SIZE / 2.0,
+                    center.y + ITEM_BOX_SIZE / 2.0,
+                ),
+                Velocity { x: 0.0, y: 0.0 },
+            )
+        }
+
+        /// Whether the box still holds its item and a cart at `position` drives through it
+        pub fn touches(&self, position: Point) -> bool {
+            let (_p, _q) = (self.p(), self.q());
+            !self.used
+                && position.x >= _p.x - CART_WIDTH / 2.0
+                && position.x <= _q.x + CART_WIDTH / 2.0
+                && position.y >= _p.y
+                && position.y <= _q.y
+        }
+    }
+
+    /// Oil dropped on the course.
+    ///
+    /// `p` is the lower left corner and `q` the upper right one of the
+    /// ellipse's bounds.
+    #[derive(Clone, Copy)]
+    pub struct OilSlick {
+        pub state_machine: StateMachine,
+    }
+    impl Piece for OilSlick {
+        fn new(p: Point, q: Point, velocity: Velocity) -> Self {
+            OilSlick {
+                state_machine: StateMachine::Running(State::new(p, q, velocity)),
+            }
+        }
+        fn get_state_machine(&self) -> StateMachine {
+            self.state_machine
+        }
+        fn set_state_machine(&mut self, _state_machine: StateMachine) {
+            self.state_machine = _state_machine.update();
+        }
+
+        /// Draws the slick as a dark ellipse
+        fn draw(&self, renderer: &dyn Renderer) {
+            let (_p, _q) = (self.p(), self.q());
+            renderer.ellipse(
+                &Point::new((_p.x + _q.x) / 2.0, (_p.y + _q.y) / 2.0),
+                ((_q.x - _p.x) / 2.0) as f64,
+                ((_q.y - _p.y) / 2.0) as f64,
+                Some("#222222"),
+                "#555555",
+                1.0,
+            );
+        }
+    }
+    impl OilSlick {
+        /// Drops a slick centred on `center`
+        pub fn at(center: Point) -> Self {
+            OilSlick::new(
+                Point::new(center.x - OIL_SLICK_RADIUS_X, center.y - OIL_SLICK_RADIUS_Y),
+                Point::new(center.x + OIL_SLICK_RADIUS_X, center.y + OIL_SLICK_RADIUS_Y),
+                Velocity { x: 0.0, y: 0.0 },
+            )
+        }
+
+        /// Whether a cart at `position` runs into the slick
+        pub fn touches(&self, position: Point) -> bool {
+            let (_p, _q) = (self.p(), self.q());
+            let _dx = (position.x - (_p.x + _q.x) / 2.0) / (OIL_SLICK_RADIUS_X + CART_WIDTH / 2.0);
+            let _dy = (position.y - (_p.y + _q.y) / 2.0) / OIL_SLICK_RADIUS_Y;
+            _dx * _dx + _dy * _dy <= 1.0
+        }
+
+        /// Whether the slick scrolled off the bottom of the screen
+        pub fn is_behind(&self) -> bool {
+            self.q().y < 0.0
+        }
+    }
+
+    /// Draws the bubble of a shield around a cart at `position`
+    pub fn draw_shield(renderer: &dyn Renderer, position: Point) {
+        renderer.arc(&position, SHIELD_RADIUS, None, "#66ccff", 3.0);
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        #[test]
+        fn item_box_touches_a_cart_overlapping_it_until_used() {
+            let mut _item_box = ItemBox::at(Point::new(400.0, 500.0));
+            assert!(_item_box.touches(Point::new(400.0, 500.0)));
+            let _edge = ITEM_BOX_SIZE / 2.0 + CART_WIDTH / 2.0;
+            assert!(_item_box.touches(Point::new(400.0 + _edge, 500.0)));
+            assert!(_item_box.touches(Point::new(400.0 - _edge, 500.0)));
+            assert!(!_item_box.touches(Point::new(400.0 + _edge + 1.0, 500.0)));
+            assert!(!_item_box.touches(Point::new(400.0, 500.0 + ITEM_BOX_SIZE)));
+            _item_box.used = true;
+            assert!(!_item_box.touches(Point::new(400.0, 500.0)));
+        }
+
+        #[test]
+        fn oil_slick_touches_a_cart_within_its_ellipse() {
+            let _oil_slick = OilSlick::at(Point::new(400.0, 500.0));
+            assert!(_oil_slick.touches(Point::new(400.0, 500.0)));
+            let _edge = OIL_SLICK_RADIUS_X + CART_WIDTH / 2.0;
+            assert!(_oil_slick.touches(Point::new(400.0 + _edge, 500.0)));
+            assert!(_oil_slick.touches(Point::new(400.0, 500.0 - OIL_SLICK_RADIUS_Y)));
+            assert!(!_oil_slick.touches(Point::new(400.0 + _edge + 1.0, 500.0)));
+            // Inside the bounds but outside the ellipse
+            assert!(!_oil_slick.touches(Point::new(
+                400.0 + _edge * 0.9,
+                500.0 + OIL_SLICK_RADIUS_Y * 0.9
+            )));
+        }
+
+        #[test]
+        fn oil_slick_is_behind_once_scrolled_off_the_screen() {
+            let mut _oil_slick = OilSlick::at(Point::new(400.0, OIL_SLICK_RADIUS_Y));
+            assert!(!_oil_slick.is_behind());
+            _oil_slick.run(Velocity { x: 0.0, y: 10.0 });
+            assert!(!_oil_slick.is_behind());
+            _oil_slick.run(Velocity {
+                x: 0.0,
+                y: OIL_SLICK_RADIUS_Y * 2.0,
+            });
+            assert!(_oil_slick.is_behind());
+        }
+
+        #[test]
+        fn seeded_random_items_repeat_and_cover_every_item() {
+            let mut _first = ChaCha8Rng::seed_from_u64(7);
+            let mut _second = ChaCha8Rng::seed_from_u64(7);
+            let _items: Vec<Item> = (0..300).map(|_| Item::random(&mut _first)).collect();
+            let _again: Vec<Item> = (0..300).map(|_| Item::random(&mut _second)).collect();
+            assert_eq!(_items, _again);
+            for _item in ITEMS {
+                let _count = _items.iter().filter(|item| **item == _item).count();
+                assert!(
+                    (60..=140).contains(&_count),
+                    "{:?} drawn {} times",
+                    _item,
+                    _count
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,208 @@
+#[allow(clippy::module_inception)]
+pub mod lap_timer {
+    //! Lap and sector split timing.
+    //!
+    //! Times are counted in fixed steps like `Race::frames`; use
+    //! `frames_to_millis` to display them.
+    use crate::game::race::race::frames_to_millis;
+
+    /// Time of one complete lap
+    #[derive(Clone, Default)]
+    pub struct LapTime {
+        /// Lap time in fixed steps
+        pub frames: u32,
+        /// Fixed steps from the lap start to each sector split
+        pub splits: Vec<u32>,
+    }
+
+    /// Records lap and sector times and compares them with the best lap.
+    #[derive(Clone)]
+    pub struct LapTimer {
+        /// Lap distances of the sector splits, in ascending order
+        sectors: Vec<f32>,
+        /// Frame at which the current lap started
+        lap_start: u32,
+        /// Splits passed during the current lap
+        current_splits: Vec<u32>,
+        /// Every completed lap of this race
+        pub laps: Vec<LapTime>,
+        /// Fastest lap so far, kept between races
+        pub best_lap: Option<LapTime>,
+        /// Milliseconds gained (negative) or lost against the best lap at the
+        /// last split or lap line passed
+        pub delta: Option<i32>,
+    }
+    impl LapTimer {
+        ///
+        /// Creates a timer for a lap divided at the given distances
+        ///
+        /// # Arguments
+        /// * `sectors` - Lap distances where a split time is taken
+        pub fn new(sectors: Vec<f32>) -> Self {
+            LapTimer {
+                sectors,
+                lap_start: 0,
+                current_splits: vec![],
+                laps: vec![],
+                best_lap: None,
+                delta: None,
+            }
+        }
+
+        ///
+        /// Takes the split times passed in this step
+        ///
+        /// # Arguments
+        /// * `frame` - Current `Race::frames`
+        /// * `distance` - Current lap distance of the cart
+        pub fn update(&mut self, frame: u32, distance: f32) {
+            while let Some(&sector) = self.sectors.get(self.current_splits.len()) {
+                if distance < sector {
+                    break;
+                }
+                let split = frame - self.lap_start;
+                let index = self.current_splits.len();
+                if let Some(best) = self.best_lap.as_ref().and_then(|lap| lap.splits.get(index)) {
+                    self.delta = Some(frames_to_millis(split) - frames_to_millis(*best));
+                }
+                self.current_splits.push(split);
+            }
+        }
+
+        /// Closes the current lap at `frame` and starts the next one
+        pub fn complete_lap(&mut self, frame: u32) {
+            let lap = LapTime {
+                frames: frame - self.lap_start,
+                splits: std::mem::take(&mut self.current_splits),
+            };
+            self.lap_start = frame;
+            match &self.best_lap {
+                Some(best) => {
+                    self.delta = Some(frames_to_millis(lap.frames) - frames_to_millis(best.frames));
+                    if lap.frames < best.frames {
+                        self.best_lap = Some(lap.clone());
+                    }
+                }
+                None => self.best_lap = Some(lap.clone()),
+            }
+            self.laps.push(lap);
+        }
+
+        /// Drops the current lap without timing it and starts the next one at `frame`
+        pub fn discard_lap(&mut self, frame: u32) {
+            self.lap_start = frame;
+            self.current_splits.clear();
+        }
+
+        /// Fixed steps since the current lap started
+        pub fn current_lap(&self, frame: u32) -> u32 {
+            frame - self.lap_start
+        }
+    }
+
+    /// Formats a delta in milliseconds as `+0.42` or `-0.10`
+    pub fn format_delta(delta: i32) -> String {
+        format!("{:+.2}", delta as f64 / 1000.0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Sector splits at lap distances 1000 and 2000
+        fn timer() -> LapTimer {
+            LapTimer::new(vec![1000.0, 2000.0])
+        }
+
+        /// Drives one lap of 3000 at `speed` distance per step, from `frame`
+        fn drive_lap(timer: &mut LapTimer, frame: u32, speed: f32) -> u32 {
+            let mut _frame = frame;
+            let mut _distance = 0.0;
+            while _distance < 3000.0 {
+                _frame += 1;
+                _distance += speed;
+                timer.update(_frame, _distance);
+            }
+            timer.complete_lap(_frame);
+            _frame
+        }
+
+        #[test]
+        fn splits_are_taken_once_per_sector() {
+            let mut timer = timer();
+            timer.update(10, 500.0);
+            timer.update(20, 1000.0);
+            timer.update(30, 1500.0);
+            // One long step can pass both splits
+            timer.update(40, 2500.0);
+            timer.update(50, 2600.0);
+            timer.complete_lap(60);
+            assert_eq!(timer.laps.len(), 1);
+            assert_eq!(timer.laps[0].frames, 60);
+            assert_eq!(timer.laps[0].splits, vec![20, 40]);
+            assert_eq!(timer.current_lap(75), 15);
+        }
+
+        #[test]
+        fn discarded_lap_is_not_timed() {
+            let mut timer = timer();
+            timer.update(20, 1000.0);
+            timer.discard_lap(60);
+            assert!(timer.laps.is_empty());
+            assert_eq!(timer.best_lap.as_ref().map(|lap| lap.frames), None);
+            assert_eq!(timer.current_lap(75), 15);
+            // The next lap takes its splits afresh
+            timer.update(80, 1000.0);
+            timer.complete_lap(100);
+            assert_eq!(timer.laps.len(), 1);
+            assert_eq!(timer.laps[0].frames, 40);
+            assert_eq!(timer.laps[0].splits, vec![20]);
+        }
+
+        #[test]
+        fn delta_compares_with_the_best_lap() {
+            let mut timer = timer();
+            let _frame = drive_lap(&mut timer, 0, 10.0);
+            assert_eq!(timer.best_lap.as_ref().unwrap().frames, 300);
+            assert_eq!(timer.delta, None);
+
+            // Slower through the first sector
+            timer.update(_frame + 125, 1000.0);
+            assert_eq!(
+                timer.delta,
+                Some(frames_to_millis(125) - frames_to_millis(100))
+            );
+            // A faster lap becomes the best one
+            timer.complete_lap(_frame + 250);
+            assert_eq!(
+                timer.delta,
+                Some(frames_to_millis(250) - frames_to_millis(300))
+            );
+            assert_eq!(timer.best_lap.as_ref().unwrap().frames, 250);
+            assert_eq!(timer.laps.len(), 2);
+        }
+
+        #[test]
+        fn unfinished_lap_is_never_recorded() {
+            let mut timer = timer();
+            let _frame = drive_lap(&mut timer, 0, 10.0);
+            timer.update(_frame + 50, 1000.0);
+            // A new race keeps only the best lap
+            let mut _next = LapTimer::new(vec![1000.0, 2000.0]);
+            _next.best_lap = timer.best_lap.clone();
+            assert!(_next.laps.is_empty());
+            assert_eq!(_next.best_lap.as_ref().unwrap().splits, vec![100, 200]);
+            assert_eq!(_next.delta, None);
+        }
+
+        #[test]
+        fn delta_shows_its_sign_and_rounds_to_hundredths() {
+            assert_eq!(format_delta(420), "+0.42");
+            assert_eq!(format_delta(-100), "-0.10");
+            assert_eq!(format_delta(0), "+0.00");
+            assert_eq!(format_delta(1234), "+1.23");
+            assert_eq!(format_delta(-1236), "-1.24");
+            assert_eq!(format_delta(12_345), "+12.35");
+        }
+    }
+}
@@ -3,8 +3,14 @@
 
 #[allow(clippy::all)]
 pub mod music {
+    use crate::game::pad::pad::PadKind;
     use crate::game::{Audio, Sound};
 
+    /// Playback rate of the pad sound on a boost pad: a rising beep
+    const BOOST_SOUND_RATE: f32 = 1.5;
+    /// Playback rate of the pad sound on a slow strip: a low beep
+    const SLOW_SOUND_RATE: f32 = 0.6;
+
     /// Music struct manages audio playback for game sounds.
     /// Contains audio interface and sound data for sound effect playback.
     #[derive(Clone)]
@@ -41,5 +47,24 @@ pub mod music {
             }
             self
         }
+        /// Plays the sound of driving onto a pad, pitched by its kind.
+        /// Logs an error message if sound playback fails.
+        ///
+        /// # Arguments
+        /// * `self` - Music instance (consumed and returned)
+        /// * `kind` - Kind of pad the cart drove onto
+        ///
+        /// # Returns
+        /// The same Music instance for method chaining
+        pub fn play_pad_sound(self, kind: PadKind) -> Self {
+            let _rate = match kind {
+                PadKind::Boost => BOOST_SOUND_RATE,
+                PadKind::Slow => SLOW_SOUND_RATE,
+            };
+            if let Err(err) = self.audio.play_sound_at_rate(&self.sound, _rate) {
+                log!("Error playing pad sound {:#?}", err);
+            }
+            self
+        }
     }
 }
@@ -2,25 +2,43 @@
 pub mod ornament {
     //! ornament related functions.
     use crate::engine::{Point, Velocity};
-    use crate::game::{Piece, Renderer, STAGE_GOAL, State, StateMachine};
+    use crate::game::track::track::{Decoration, Track};
+    use crate::game::{Piece, Renderer, State, StateMachine};
 
     /* <-- CONSTANT VALUE */
     const FONT_COLOR: &str = "green";
-    const GOAL_X: f32 = 100.0;
-    const GOAL_Y: f32 = STAGE_GOAL + 3560.0;
     const GOAL: [&str; 2] = [
         "□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■",
         "□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■□□■",
     ];
     const GOAL_DISTANCE: f32 = 25.0;
 
+    /// Decorations of a course: the finish banner and the fruit trees.
+    /// `p` is the scroll offset applied to every decoration.
     pub struct Ornament {
         pub state_machine: StateMachine,
+        finish_line: Option<Point>,
+        trees: Vec<Decoration>,
+    }
+    impl Ornament {
+        /// Creates the decorations of `track` at their start positions
+        pub fn from_track(track: &Track) -> Self {
+            let mut _ornament = Ornament::new(
+                Point::default(),
+                Point::default(),
+                Velocity { x: 0.0, y: 0.0 },
+            );
+            _ornament.finish_line = Some(track.finish_line);
+            _ornament.trees = track.decorations.clone();
+            _ornament
+        }
     }
     impl Piece for Ornament {
         fn new(p: Point, q: Point, velocity: Velocity) -> Self {
             Ornament {
                 state_machine: StateMachine::Running(State::new(p, q, velocity)),
+                finish_line: None,
+                trees: vec![],
             }
         }
         fn get_state_machine(&self) -> StateMachine {
@@ -30,47 +48,53 @@ pub mod ornament {
             self.state_machine = _state_machine.update();
         }
 
-        fn draw(&self, renderer: &Renderer) {
+        fn draw(&self, renderer: &dyn Renderer) {
+            let _offset = self.state_machine.context().p;
             // GOAL
-            let mut _distance: f32 = 0.0;
-            for _goal in &GOAL {
-                renderer.text(
-                    &Point {
-                        x: GOAL_X + self.state_machine.context().p.x,
-                        y: GOAL_Y + self.state_machine.context().p.y + _distance,
-                    },
-                    _goal,
-                    FONT_COLOR,
-                    "32 myfont",
-                    "center",
-                );
-                _distance += GOAL_DISTANCE;
+            if let Some(_finish_line) = self.finish_line {
+                let mut _distance: f32 = 0.0;
+                for _goal in &GOAL {
+                    renderer.text(
+                        &Point {
+                            x: _finish_line.x + _offset.x,
+                            y: _finish_line.y + _offset.y + _distance,
+                        },
+                        _goal,
+                        FONT_COLOR,
+                        "32 myfont",
+                        "center",
+                    );
+                    _distance += GOAL_DISTANCE;
+                }
             }
 
-            // Fruit trees with different fruits
-            let trees = [
-                (30.0, 500.0, "apple"),    // Red apple tree
-                (400.0, 900.0, "orange"),  // Orange tree
-                (400.0, 1400.0, "cherry"), // Cherry tree
-                (120.0, 1900.0, "lemon"),  // Lemon tree
-                (620.0, 2400.0, "plum"),   // Plum tree
-                (240.0, 2700.0, "apple"),  // Another apple tree
-                (620.0, 3600.0, "orange"), // Another orange tree
-                (400.0, 4600.0, "cherry"), // Another cherry tree
-                (320.0, 5400.0, "lemon"),  // Another lemon tree
-                (-50.0, 6400.0, "plum"),   // Another plum tree
-            ];
-
             // Draw fruit trees using Canvas instead of ASCII
-            for tree in trees.iter() {
+            for tree in self.trees.iter() {
                 renderer.draw_fruit_tree(
                     &Point {
-                        x: tree.0 + self.state_machine.context().p.x,
-                        y: tree.1 + self.state_machine.context().p.y,
+                        x: tree.x + _offset.x,
+                        y: tree.y + _offset.y,
                     },
-                    tree.2, // fruit type
+                    tree.fruit.name(),
                 );
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::engine::RecordingRenderer;
+
+        #[test]
+        fn ornament_draws_goal_and_trees_at_its_scroll_offset() {
+            let _json = include_str!("../../../../cart/assets/tracks/default.json");
+            let mut _ornament = Ornament::from_track(&Track::from_json(_json).unwrap());
+            _ornament.run(Velocity { x: 0.0, y: 200.0 });
+            _ornament.update();
+            let renderer = RecordingRenderer::new();
+            _ornament.draw(&renderer);
+            renderer.assert_snapshot("ornament.txt");
+        }
+    }
 }
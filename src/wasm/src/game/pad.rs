@@ -0,0 +1,221 @@
+#[allow(clippy::module_inception)]
+pub mod pad {
+    //! Boost pads and slow strips.
+    //!
+    //! Pads lie flat on the course and scroll with it like the walls. Driving
+    //! onto a pad gives the cart a push along its heading and changes its top
+    //! speed for a while: a boost pad lets it run above `TOP_SPEED`, a slow
+    //! strip holds it well below. Each pad works once per lap.
+    use crate::engine::{Point, Renderer, Velocity};
+    use crate::game::cart::cart::CART_WIDTH;
+    use crate::game::{Piece, State, StateMachine};
+    use serde::{Deserialize, Serialize};
+
+    /* <-- CONSTANT VALUE */
+    /// Size of a pad across the course
+    pub const PAD_WIDTH: f32 = 40.0;
+    /// Size of a pad along the course
+    pub const PAD_HEIGHT: f32 = 60.0;
+    /// Top speed factor while a boost lasts
+    const BOOST_TOP_SPEED: f32 = 1.4;
+    /// Speed per second a boost pad adds along the heading
+    const BOOST_IMPULSE: f32 = 120.0;
+    /// Seconds a boost lasts
+    const BOOST_SECONDS: f32 = 1.5;
+    /// Top speed factor while a slow strip holds the cart back
+    const SLOW_TOP_SPEED: f32 = 0.5;
+    /// Speed per second a slow strip takes away along the heading
+    const SLOW_IMPULSE: f32 = -90.0;
+    /// Seconds a slow strip holds the cart back
+    const SLOW_SECONDS: f32 = 1.0;
+    /* CONSTANT VALUE --> */
+
+    /// What a pad does to a cart driving onto it
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum PadKind {
+        Boost,
+        Slow,
+    }
+    impl PadKind {
+        /// Factor of the top speed while the pad's effect lasts
+        pub fn top_speed(&self) -> f32 {
+            match self {
+                PadKind::Boost => BOOST_TOP_SPEED,
+                PadKind::Slow => SLOW_TOP_SPEED,
+            }
+        }
+        /// Speed per second added along the heading on driving onto the pad
+        pub fn impulse(&self) -> f32 {
+            match self {
+                PadKind::Boost => BOOST_IMPULSE,
+                PadKind::Slow => SLOW_IMPULSE,
+            }
+        }
+        /// Seconds the pad's effect lasts
+        pub fn seconds(&self) -> f32 {
+            match self {
+                PadKind::Boost => BOOST_SECONDS,
+                PadKind::Slow => SLOW_SECONDS,
+            }
+        }
+        /// Fill, outline and marking colours of the pad
+        fn colors(&self) -> (&'static str, &'static str, &'static str) {
+            match self {
+                PadKind::Boost => ("#ffcc00", "#ff6600", "#ff3300"),
+                PadKind::Slow => ("#8b5a2b", "#4a2f16", "#f0e0c0"),
+            }
+        }
+        /// Marking painted on the pad, pointing the way it pushes
+        fn marking(&self) -> &'static str {
+            match self {
+                PadKind::Boost => "^^",
+                PadKind::Slow => "vv",
+            }
+        }
+    }
+
+    /// Where a pad lies on the course, as stored in a track file
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct PadSpawn {
+        pub kind: PadKind,
+        /// Centre of the pad in course space
+        pub x: f32,
+        pub y: f32,
+    }
+
+    /// A boost pad or slow strip on the course.
+    ///
+    /// `p` is the lower left corner and `q` the upper right one.
+    #[derive(Clone, Copy)]
+    pub struct Pad {
+        pub state_machine: StateMachine,
+        pub kind: PadKind,
+        /// Whether the pad already worked this lap
+        pub used: bool,
+    }
+    impl Piece for Pad {
+        /// Creates a boost pad between the corners `p` and `q`
+        fn new(p: Point, q: Point, velocity: Velocity) -> Self {
+            Pad {
+                state_machine: StateMachine::Running(State::new(p, q, velocity)),
+                kind: PadKind::Boost,
+                used: false,
+            }
+        }
+        fn get_state_machine(&self) -> StateMachine {
+            self.state_machine
+        }
+        fn set_state_machine(&mut self, _state_machine: StateMachine) {
+            self.state_machine = _state_machine.update();
+        }
+
+        /// Draws the pad as a filled rectangle with its marking on top
+        fn draw(&self, renderer: &dyn Renderer) {
+            let (_p, _q) = (self.p(), self.q());
+            let _center = Point::new((_p.x + _q.x) / 2.0, (_p.y + _q.y) / 2.0);
+            let (_fill, _stroke, _marking) = self.kind.colors();
+            renderer.rect(
+                &_center,
+                (_q.x - _p.x) as f64,
+                (_q.y - _p.y) as f64,
+                _fill,
+                _stroke,
+                2.0,
+            );
+            renderer.text(
+                &Point::new(_center.x, _center.y - 8.0),
+                self.kind.marking(),
+                _marking,
+                "20 myfont",
+                "center",
+            );
+        }
+    }
+    impl Pad {
+        /// Creates the pad described in a track file at its start position
+        pub fn from_spawn(spawn: &PadSpawn) -> Self {
+            let mut _pad = Pad::new(
+                Point::new(spawn.x - PAD_WIDTH / 2.0, spawn.y - PAD_HEIGHT / 2.0),
+                Point::new(spawn.x + PAD_WIDTH / 2.0, spawn.y + PAD_HEIGHT / 2.0),
+                Velocity { x: 0.0, y: 0.0 },
+            );
+            _pad.kind = spawn.kind;
+            _pad
+        }
+
+        ///
+        /// Uses the pad if the cart has just driven onto it
+        ///
+        /// # Arguments
+        /// * `position` - Screen position of the cart, which counts as on the
+        ///   pad a little beyond its sides
+        ///
+        /// # Returns
+        /// The pad's kind the first time the cart is on it this lap
+        pub fn drive_over(&mut self, position: Point) -> Option<PadKind> {
+            let (_p, _q) = (self.p(), self.q());
+            if self.used
+                || position.x < _p.x - CART_WIDTH / 2.0
+                || position.x > _q.x + CART_WIDTH / 2.0
+                || position.y < _p.y
+                || position.y > _q.y
+            {
+                return None;
+            }
+            self.used = true;
+            Some(self.kind)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// A pad of `kind` centred on (400, 500)
+        fn pad(kind: PadKind) -> Pad {
+            Pad::from_spawn(&PadSpawn {
+                kind,
+                x: 400.0,
+                y: 500.0,
+            })
+        }
+
+        #[test]
+        fn pad_works_once_until_it_is_rebuilt() {
+            let mut _pad = pad(PadKind::Boost);
+            assert_eq!(
+                _pad.drive_over(Point::new(400.0, 500.0)),
+                Some(PadKind::Boost)
+            );
+            assert_eq!(_pad.drive_over(Point::new(400.0, 510.0)), None);
+            // The race rebuilds its pads for each lap
+            let mut _pad = pad(PadKind::Slow);
+            assert_eq!(
+                _pad.drive_over(Point::new(400.0, 500.0)),
+                Some(PadKind::Slow)
+            );
+        }
+
+        #[test]
+        fn cart_overlapping_the_side_of_a_pad_drives_over_it() {
+            let _edge = PAD_WIDTH / 2.0 + CART_WIDTH / 2.0;
+            assert!(
+                pad(PadKind::Boost)
+                    .drive_over(Point::new(400.0 - _edge, 500.0))
+                    .is_some()
+            );
+            assert!(
+                pad(PadKind::Boost)
+                    .drive_over(Point::new(400.0 + _edge + 1.0, 500.0))
+                    .is_none()
+            );
+            assert!(
+                pad(PadKind::Boost)
+                    .drive_over(Point::new(400.0, 500.0 + PAD_HEIGHT))
+                    .is_none()
+            );
+        }
+    }
+}
@@ -0,0 +1,473 @@
+#[allow(clippy::module_inception)]
+pub mod physics {
+    //! Vehicle dynamics of the player's cart.
+    //!
+    //! The cart has a heading as well as a velocity. Steering turns the
+    //! heading, the throttle and brake act along it, and lateral grip pulls
+    //! the velocity round to follow it, so a cart turned sharply at speed
+    //! drifts before it grips. Tuning values are per second and are scaled by
+    //! the fixed step, so handling does not depend on the frame rate.
+    //!
+    //! The rest of the race counts velocity per fixed step, so `step` takes
+    //! and returns velocities in that unit.
+    use crate::engine::{FRAME_SIZE, KeyState, Velocity};
+
+    /* <-- CONSTANT VALUE */
+    /// Fastest speed along the heading on asphalt, per second
+    pub const TOP_SPEED: f32 = 300.0;
+    /// Speed gained per second with the throttle held
+    const ACCELERATION: f32 = 108.0;
+    /// Speed lost per second with the brake held
+    const BRAKING: f32 = 216.0;
+    /// Share of the speed lost to drag per second
+    const DRAG: f32 = 0.1;
+    /// Heading change per second at full lock, in radians
+    const STEERING_RATE: f32 = 1.5;
+    /// Furthest the heading may turn from straight up the course, in radians
+    pub const MAX_HEADING: f32 = 0.35;
+    /// Below this speed per second steering is proportionally slower
+    const STEERING_FULL_SPEED: f32 = 60.0;
+    /// Rate per second at which sideways sliding dies away
+    const GRIP: f32 = 8.0;
+    /* CONSTANT VALUE --> */
+
+    /// How a surface changes the handling, as factors of the tuning values.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Handling {
+        pub acceleration: f32,
+        pub top_speed: f32,
+        pub grip: f32,
+        pub drag: f32,
+    }
+    impl Handling {
+        /// Handling on asphalt, with every tuning value as it is
+        pub const NORMAL: Handling = Handling {
+            acceleration: 1.0,
+            top_speed: 1.0,
+            grip: 1.0,
+            drag: 1.0,
+        };
+    }
+
+    /// Driver input for one fixed step.
+    #[derive(Clone, Copy, Default)]
+    pub struct Controls {
+        pub throttle: bool,
+        pub brake: bool,
+        /// -1.0 for full left lock, 1.0 for full right lock
+        pub steer: f32,
+        /// Steer back towards straight up the course
+        pub straighten: bool,
+    }
+    impl Controls {
+        /// Reads the controls from the keys held down
+        pub fn from_keys(keystate: &KeyState) -> Self {
+            let mut steer = 0.0;
+            if keystate.is_pressed("ArrowLeft") {
+                steer -= 1.0;
+            }
+            if keystate.is_pressed("ArrowRight") {
+                steer += 1.0;
+            }
+            Controls {
+                throttle: keystate.is_pressed("ArrowUp"),
+                brake: keystate.is_pressed("Space"),
+                steer,
+                straighten: keystate.is_pressed("ArrowDown"),
+            }
+        }
+    }
+
+    /// Length of a fixed step in seconds
+    fn step_seconds() -> f32 {
+        (FRAME_SIZE / 1000.0) as f32
+    }
+
+    ///
+    /// Advances the cart's motion by one fixed step
+    ///
+    /// # Arguments
+    /// * `velocity` - Velocity per fixed step
+    /// * `heading` - Heading in radians; 0.0 is straight up the course and
+    ///   positive headings turn right
+    /// * `controls` - Driver input held during the step
+    /// * `handling` - Handling of the surface under the cart
+    ///
+    /// # Returns
+    /// The new velocity per fixed step and the new heading
+    pub fn step(
+        velocity: Velocity,
+        heading: f32,
+        controls: &Controls,
+        handling: &Handling,
+    ) -> (Velocity, f32) {
+        let dt = step_seconds();
+        let (velocity, heading) = advance(
+            Velocity {
+                x: velocity.x / dt,
+                y: velocity.y / dt,
+            },
+            heading,
+            controls,
+            handling,
+            dt,
+        );
+        (
+            Velocity {
+                x: velocity.x * dt,
+                y: velocity.y * dt,
+            },
+            heading,
+        )
+    }
+
+    ///
+    /// Advances the cart's motion by `dt` seconds
+    ///
+    /// # Arguments
+    /// * `velocity` - Velocity per second
+    /// * `heading` - Heading in radians
+    /// * `controls` - Driver input held for `dt`
+    /// * `handling` - Handling of the surface under the cart
+    /// * `dt` - Length of the step in seconds
+    ///
+    /// # Returns
+    /// The new velocity per second and the new heading
+    fn advance(
+        velocity: Velocity,
+        heading: f32,
+        controls: &Controls,
+        handling: &Handling,
+        dt: f32,
+    ) -> (Velocity, f32) {
+        let (vx, vy) = (velocity.x, velocity.y);
+
+        // Steering needs some speed to bite
+        let (sin, cos) = heading.sin_cos();
+        let bite = ((vx * sin + vy * cos) / STEERING_FULL_SPEED).clamp(0.0, 1.0);
+        let mut heading = heading + controls.steer.clamp(-1.0, 1.0) * STEERING_RATE * bite * dt;
+        if controls.straighten {
+            let turn = STEERING_RATE * dt;
+            heading -= heading.clamp(-turn, turn);
+        }
+        let heading = heading.clamp(-MAX_HEADING, MAX_HEADING);
+
+        // The velocity lags behind a turn: what no longer runs along the
+        // new heading is sideways sliding, which grip takes away
+        let (sin, cos) = heading.sin_cos();
+        let mut forward = vx * sin + vy * cos;
+        let lateral = vx * cos - vy * sin;
+        if controls.throttle {
+            forward += ACCELERATION * handling.acceleration * dt;
+        }
+        if controls.brake {
+            forward -= BRAKING * dt;
+        }
+        forward -= forward * DRAG * handling.drag * dt;
+        // A slow surface, or a boost running out, brakes a fast cart down
+        // to its top speed
+        let top_speed = TOP_SPEED * handling.top_speed;
+        if forward > top_speed {
+            forward = (forward - BRAKING * dt).max(top_speed);
+        }
+        let forward = forward.max(0.0);
+        let lateral = lateral * (-GRIP * handling.grip * dt).exp();
+
+        (
+            Velocity {
+                x: forward * sin + lateral * cos,
+                // The course never scrolls backwards
+                y: (forward * cos - lateral * sin).max(0.0),
+            },
+            heading,
+        )
+    }
+
+    ///
+    /// Pushes the cart along its heading, as a boost pad or slow strip does
+    ///
+    /// # Arguments
+    /// * `velocity` - Velocity per fixed step
+    /// * `heading` - Heading in radians
+    /// * `speed` - Speed per second to add along the heading; negative to
+    ///   slow the cart, though never into reverse
+    ///
+    /// # Returns
+    /// The new velocity per fixed step
+    pub fn impulse(velocity: Velocity, heading: f32, speed: f32) -> Velocity {
+        let dt = step_seconds();
+        let (sin, cos) = heading.sin_cos();
+        let forward = (velocity.x * sin + velocity.y * cos) / dt;
+        let change = speed.max(-forward) * dt;
+        Velocity {
+            x: velocity.x + change * sin,
+            y: (velocity.y + change * cos).max(0.0),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const THROTTLE: Controls = Controls {
+            throttle: true,
+            brake: false,
+            steer: 0.0,
+            straighten: false,
+        };
+
+        /// Drives for `seconds` in steps of `dt`, from a standstill
+        ///
+        /// # Returns
+        /// Distance covered across and up the course, and the final velocity
+        fn drive(seconds: f32, dt: f32, controls: &Controls) -> (f32, f32, Velocity) {
+            let mut velocity = Velocity::default();
+            let mut heading = 0.0;
+            let (mut x, mut y) = (0.0, 0.0);
+            for _ in 0..(seconds / dt).round() as u32 {
+                (velocity, heading) = advance(velocity, heading, controls, &Handling::NORMAL, dt);
+                x += velocity.x * dt;
+                y += velocity.y * dt;
+            }
+            (x, y, velocity)
+        }
+
+        #[test]
+        fn distance_does_not_depend_on_the_step_size() {
+            let steer = Controls {
+                steer: 1.0,
+                ..THROTTLE
+            };
+            for controls in [THROTTLE, steer] {
+                let (x60, y60, _) = drive(4.0, 1.0 / 60.0, &controls);
+                let (x120, y120, _) = drive(4.0, 1.0 / 120.0, &controls);
+                let (x30, y30, _) = drive(4.0, 1.0 / 30.0, &controls);
+                assert!((y120 - y60).abs() < y60 * 0.01, "{} vs {}", y120, y60);
+                assert!((y30 - y60).abs() < y60 * 0.01, "{} vs {}", y30, y60);
+                assert!((x120 - x60).abs() < 2.0, "{} vs {}", x120, x60);
+                assert!((x30 - x60).abs() < 2.0, "{} vs {}", x30, x60);
+            }
+        }
+
+        #[test]
+        fn top_speed_is_the_same_per_second_at_any_step_size() {
+            let (_, _, velocity) = drive(60.0, step_seconds(), &THROTTLE);
+            assert_eq!(velocity.y, TOP_SPEED);
+            let (_, _, velocity) = drive(60.0, 1.0 / 240.0, &THROTTLE);
+            assert_eq!(velocity.y, TOP_SPEED);
+
+            // 300 per second is 5 per fixed step of the race
+            let mut velocity = Velocity::default();
+            let mut heading = 0.0;
+            for _ in 0..3600 {
+                (velocity, heading) = step(velocity, heading, &THROTTLE, &Handling::NORMAL);
+            }
+            assert!((velocity.y - 5.0).abs() < 1e-4, "{}", velocity.y);
+        }
+
+        #[test]
+        fn drag_slows_a_coasting_cart() {
+            let coast = Controls::default();
+            let (mut velocity, mut heading) = (Velocity { x: 0.0, y: 200.0 }, 0.0);
+            let mut last = velocity.y;
+            for _ in 0..60 {
+                (velocity, heading) =
+                    advance(velocity, heading, &coast, &Handling::NORMAL, 1.0 / 60.0);
+                assert!(velocity.y < last);
+                last = velocity.y;
+            }
+            // About a tenth of the speed goes in a second
+            assert!(
+                (velocity.y - 200.0 * (-DRAG).exp()).abs() < 1.0,
+                "{}",
+                velocity.y
+            );
+        }
+
+        #[test]
+        fn sideways_slide_dies_away() {
+            let coast = Controls::default();
+            let (mut velocity, mut heading) = (Velocity { x: 100.0, y: 200.0 }, 0.0);
+            for _ in 0..30 {
+                (velocity, heading) =
+                    advance(velocity, heading, &coast, &Handling::NORMAL, 1.0 / 60.0);
+            }
+            // Half a second of grip leaves about e^-4 of the slide
+            assert!(velocity.x.abs() < 100.0 * 0.02, "{}", velocity.x);
+            assert!(velocity.y > 150.0);
+        }
+
+        #[test]
+        fn course_never_scrolls_backwards() {
+            let brake = Controls {
+                brake: true,
+                ..Controls::default()
+            };
+            let (velocity, _) = advance(
+                Velocity { x: 0.0, y: 1.0 },
+                0.0,
+                &brake,
+                &Handling::NORMAL,
+                1.0 / 60.0,
+            );
+            assert_eq!(velocity.y, 0.0);
+            // A slide back down the course while heading up it
+            let (velocity, _) = advance(
+                Velocity { x: 0.0, y: -50.0 },
+                0.0,
+                &brake,
+                &Handling::NORMAL,
+                1.0 / 60.0,
+            );
+            assert_eq!(velocity.y, 0.0);
+        }
+
+        /// Drives for ten seconds at 60 steps a second on `handling`, from
+        /// `velocity` per second
+        fn drive_on(handling: &Handling, controls: &Controls, velocity: Velocity) -> Velocity {
+            let (mut velocity, mut heading) = (velocity, 0.0);
+            for _ in 0..600 {
+                (velocity, heading) = advance(velocity, heading, controls, handling, 1.0 / 60.0);
+            }
+            velocity
+        }
+
+        #[test]
+        fn slow_surface_lowers_the_top_speed() {
+            let mud = Handling {
+                top_speed: 0.4,
+                ..Handling::NORMAL
+            };
+            let velocity = drive_on(&mud, &THROTTLE, Velocity::default());
+            assert!(velocity.y <= TOP_SPEED * 0.4, "{}", velocity.y);
+            assert!(velocity.y > TOP_SPEED * 0.39, "{}", velocity.y);
+            // A cart arriving at full speed is braked down to it
+            let velocity = drive_on(
+                &mud,
+                &THROTTLE,
+                Velocity {
+                    x: 0.0,
+                    y: TOP_SPEED,
+                },
+            );
+            assert_eq!(velocity.y, TOP_SPEED * 0.4);
+        }
+
+        #[test]
+        fn low_grip_keeps_the_cart_sliding() {
+            let ice = Handling {
+                grip: 0.1,
+                ..Handling::NORMAL
+            };
+            let slide = Velocity { x: 100.0, y: 200.0 };
+            let (mut on_ice, mut on_asphalt) = (slide, slide);
+            let (mut ice_heading, mut asphalt_heading) = (0.0, 0.0);
+            let coast = Controls::default();
+            for _ in 0..30 {
+                (on_ice, ice_heading) = advance(on_ice, ice_heading, &coast, &ice, 1.0 / 60.0);
+                (on_asphalt, asphalt_heading) = advance(
+                    on_asphalt,
+                    asphalt_heading,
+                    &coast,
+                    &Handling::NORMAL,
+                    1.0 / 60.0,
+                );
+            }
+            // Half a second of a tenth of the grip leaves about e^-0.4 of it
+            assert!(on_ice.x > 60.0, "{}", on_ice.x);
+            assert!(on_ice.x > on_asphalt.x * 10.0);
+        }
+
+        /// Top speed per fixed step
+        fn top_speed() -> f32 {
+            TOP_SPEED * step_seconds()
+        }
+
+        #[test]
+        fn boost_impulse_decays_back_to_the_top_speed() {
+            let top = Velocity {
+                x: 0.0,
+                y: top_speed(),
+            };
+            let mut velocity = impulse(top, 0.0, 120.0);
+            assert!((velocity.y - (TOP_SPEED + 120.0) * step_seconds()).abs() < 1e-4);
+            let mut heading = 0.0;
+            let mut last = velocity.y;
+            while velocity.y > top_speed() {
+                (velocity, heading) = step(velocity, heading, &THROTTLE, &Handling::NORMAL);
+                assert!(velocity.y < last);
+                last = velocity.y;
+            }
+            assert_eq!(velocity.y, top_speed());
+        }
+
+        #[test]
+        fn boost_raises_the_speed_cap_and_a_slow_strip_lowers_it() {
+            let boost = Handling {
+                top_speed: 1.4,
+                ..Handling::NORMAL
+            };
+            let mut velocity = impulse(Velocity::default(), 0.0, 120.0);
+            let mut heading = 0.0;
+            for _ in 0..1200 {
+                (velocity, heading) = step(velocity, heading, &THROTTLE, &boost);
+            }
+            assert!(velocity.y > top_speed() * 1.3, "{}", velocity.y);
+            assert!(velocity.y <= top_speed() * 1.4 + 1e-4, "{}", velocity.y);
+
+            let slow = Handling {
+                top_speed: 0.5,
+                ..Handling::NORMAL
+            };
+            velocity = impulse(velocity, heading, -90.0);
+            for _ in 0..600 {
+                (velocity, heading) = step(velocity, heading, &THROTTLE, &slow);
+            }
+            assert!(
+                (velocity.y - top_speed() * 0.5).abs() < 1e-4,
+                "{}",
+                velocity.y
+            );
+        }
+
+        #[test]
+        fn slow_impulse_never_reverses_the_cart() {
+            let crawl = Velocity { x: 0.0, y: 0.5 };
+            assert_eq!(impulse(crawl, 0.0, -90.0), Velocity::default());
+            // Turned to the right, only the speed along the heading is taken
+            let heading = MAX_HEADING;
+            let (sin, cos) = heading.sin_cos();
+            let velocity = impulse(Velocity { x: sin, y: cos }, heading, -90.0);
+            assert!(velocity.x.abs() < 1e-6 && velocity.y.abs() < 1e-6);
+        }
+
+        #[test]
+        fn heading_stays_within_full_lock() {
+            let steer = Controls {
+                steer: 1.0,
+                ..THROTTLE
+            };
+            let (mut velocity, mut heading) = (Velocity { x: 0.0, y: 200.0 }, 0.0);
+            for _ in 0..600 {
+                (velocity, heading) =
+                    advance(velocity, heading, &steer, &Handling::NORMAL, 1.0 / 60.0);
+            }
+            assert_eq!(heading, MAX_HEADING);
+            let straighten = Controls {
+                straighten: true,
+                ..THROTTLE
+            };
+            for _ in 0..60 {
+                (velocity, heading) = advance(
+                    velocity,
+                    heading,
+                    &straighten,
+                    &Handling::NORMAL,
+                    1.0 / 60.0,
+                );
+            }
+            assert_eq!(heading, 0.0);
+            assert!(velocity.y > 0.0);
+        }
+    }
+}
@@ -0,0 +1,1492 @@
+#[allow(clippy::module_inception)]
+pub mod race {
+    //! Race simulation independent of the browser.
+    //!
+    //! `Race` owns every moving element of a run (cart, walls, ornaments and
+    //! rival carts) and advances them one fixed step at a time. It never touches
+    //! `web_sys`, so it can be driven from `cargo test` on a native target.
+    use crate::engine::{FRAME_SIZE, KeyState, Point, Renderer, Velocity};
+    use crate::game::cart::cart::*;
+    use crate::game::checkpoint::checkpoint::*;
+    use crate::game::difficulty::difficulty::*;
+    use crate::game::ghost::ghost::*;
+    use crate::game::hazard::hazard::*;
+    use crate::game::item::item::*;
+    use crate::game::lap_timer::lap_timer::*;
+    use crate::game::ornament::ornament::*;
+    use crate::game::pad::pad::*;
+    use crate::game::physics::physics::*;
+    use crate::game::racing_line::racing_line::*;
+    use crate::game::rival_cart::rival_cart::*;
+    use crate::game::surface::surface::*;
+    use crate::game::track::track::*;
+    use crate::game::wall::wall::*;
+    use crate::game::{Piece, course_to_distance, distance_to_course};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::rc::Rc;
+
+    /// Number of laps needed to finish a race
+    pub const RACE_LAPS: i32 = 3;
+
+    /// Damage at which the cart is wrecked and the race ends
+    pub const DAMAGE_LIMIT: f32 = 100.0;
+    /// Damage of any wall hit, however soft
+    const WALL_DAMAGE: f32 = 10.0;
+    /// Extra wall damage per unit of speed into the wall
+    const WALL_DAMAGE_PER_SPEED: f32 = 8.0;
+    /// Damage of bumping into a rival cart
+    const RIVAL_DAMAGE: f32 = 15.0;
+    /// How far a hit pushes the cart away from what it hit
+    const KNOCKBACK_DISTANCE: f32 = 30.0;
+    /// Damage of running into a barrel
+    const BARREL_DAMAGE: f32 = 15.0;
+    /// Speed per second a cone takes off the cart along its heading
+    const CONE_IMPULSE: f32 = -60.0;
+    /// Seed of the item draws and rival mistakes; a fixed seed keeps replays
+    /// frame exact
+    const ITEM_SEED: u64 = 0x17E5;
+    /// Fewest steps a rival holds an item before using it
+    const RIVAL_HOLD_MIN: u32 = 30;
+    /// Most steps a rival holds an item before using it
+    const RIVAL_HOLD_MAX: u32 = 300;
+
+    /// Result of advancing the race by one fixed step.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum RaceStatus {
+        /// The race goes on
+        Running,
+        /// The cart took `DAMAGE_LIMIT` damage from walls and rival carts
+        Crashed,
+        /// The cart completed `RACE_LAPS` laps
+        Finished,
+    }
+
+    /// A car in the race.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Racer {
+        Player,
+        /// The rival cart at this index in `Race::rival_carts`
+        Rival(usize),
+    }
+    impl Racer {
+        /// Name shown in the race results
+        pub fn name(&self) -> String {
+            match self {
+                Racer::Player => "YOU".to_string(),
+                Racer::Rival(_index) => format!("CPU {}", _index + 1),
+            }
+        }
+    }
+
+    /// Where a car stands in the race.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Standing {
+        pub racer: Racer,
+        /// Race time at which the car finished, in fixed steps
+        pub finish: Option<u32>,
+        /// Laps and distance driven, in units of distance
+        pub progress: f32,
+    }
+
+    /// All simulated elements of a single run.
+    pub struct Race {
+        pub cart: Cart,
+        pub ornaments: Vec<Ornament>,
+        pub walls: Vec<Wall>,
+        pub pads: Vec<Pad>,
+        pub item_boxes: Vec<ItemBox>,
+        pub oil_slicks: Vec<OilSlick>,
+        pub hazards: Vec<Hazard>,
+        /// Broad phase for collisions against `walls`
+        wall_index: WallIndex,
+        pub rival_carts: Vec<RivalCart>,
+        /// Line the rival carts steer along, if the track has one
+        racing_line: Option<RacingLine>,
+        pub distance: f32,
+        pub lap_count: i32,
+        /// Race time in fixed steps: steps simulated since the start plus
+        /// respawn penalties
+        pub frames: u32,
+        /// Cart positions of the current lap
+        lap_samples: Vec<GhostSample>,
+        /// Whether the current lap was driven without a respawn; only clean
+        /// laps can become the ghost
+        lap_clean: bool,
+        /// Best lap driven so far, drawn as a ghost car
+        pub ghost: Option<Ghost>,
+        pub lap_timer: LapTimer,
+        /// Course being raced
+        pub track: Rc<Track>,
+        /// Where the cart last hit a wall
+        pub contact: Option<Contact>,
+        /// Damage taken from hits, up to `DAMAGE_LIMIT`
+        pub damage: f32,
+        pub checkpoints: Checkpoints,
+        /// Race time of the last respawn
+        pub last_respawn: Option<u32>,
+        /// Cars that completed `RACE_LAPS` laps, in finishing order, with the
+        /// race time they finished at; rivals still racing when the player
+        /// finishes get the time their pace would have finished at
+        pub finishes: Vec<(Racer, u32)>,
+        /// Last lap warning, with the race time it was given
+        pub warning: Option<(LapWarning, u32)>,
+        /// Pad whose effect the cart is under, with the steps it has left
+        pub pad_effect: Option<(PadKind, u32)>,
+        /// Pad the cart drove onto during the last step
+        pub pad_hit: Option<PadKind>,
+        /// Item in the cart's slot
+        pub item: Option<Item>,
+        /// Whether a shield is up to absorb the next wall hit or oil slick
+        pub shield: bool,
+        /// Whether `USE_ITEM_KEY` was held in the previous step, so a held
+        /// key uses only one item
+        item_key_held: bool,
+        /// Draws the items of the cart and the rivals, and the rivals' mistakes
+        rng: ChaCha8Rng,
+        /// How hard the rivals race
+        pub difficulty: Difficulty,
+    }
+    impl Race {
+        /// Creates a race on `track` with every element at its start position,
+        /// and rivals racing at `difficulty`.
+        pub fn new(track: Rc<Track>, difficulty: Difficulty) -> Self {
+            let _level = difficulty.level;
+            let _rival_carts = track
+                .rivals
+                .iter()
+                .map(|r| {
+                    // Rivals on the grid ahead of the cart start with a lead;
+                    // the course scrolls twice the distance driven
+                    RivalCart::new(
+                        Point { x: r.x, y: r.y },
+                        r.speed * _level.rival_speed(),
+                        course_to_distance(r.y - track.start.y),
+                        r.no,
+                        _level.reaction(),
+                    )
+                })
+                .collect();
+            let _walls = new_walls(&track);
+            Race {
+                cart: Cart::new(track.start, Velocity { x: 0.0, y: 0.0 }),
+                ornaments: new_ornaments(&track),
+                wall_index: WallIndex::new(&_walls),
+                walls: _walls,
+                pads: new_pads(&track),
+                item_boxes: new_item_boxes(&track),
+                oil_slicks: vec![],
+                hazards: new_hazards(&track),
+                rival_carts: _rival_carts,
+                racing_line: RacingLine::new(&track),
+                distance: 0.0,
+                lap_count: 0,
+                frames: 0,
+                lap_samples: vec![],
+                lap_clean: true,
+                ghost: None,
+                lap_timer: LapTimer::new(track.sectors.clone()),
+                contact: None,
+                damage: 0.0,
+                checkpoints: Checkpoints::new(&track),
+                last_respawn: None,
+                finishes: vec![],
+                warning: None,
+                pad_effect: None,
+                pad_hit: None,
+                item: None,
+                shield: false,
+                item_key_held: false,
+                rng: ChaCha8Rng::seed_from_u64(ITEM_SEED),
+                difficulty,
+                track,
+            }
+        }
+
+        ///
+        /// Advances the race by one fixed step
+        ///
+        /// # Arguments
+        /// * `_keystate` - Keys held down during this step
+        ///
+        /// # Returns
+        /// The `RaceStatus` after the step
+        pub fn update(&mut self, _keystate: &KeyState) -> RaceStatus {
+            self.frames += 1;
+
+            // Check if cart completed one lap
+            let mut _velocity: Velocity = self.cart.get_velocity();
+            self.distance += _velocity.y;
+
+            let _lap_length = self.track.lap_length;
+            if self.distance > _lap_length {
+                self.distance = 0.0; // Reset distance for next lap
+                // Only a lap through every checkpoint counts
+                match self.checkpoints.complete_lap() {
+                    None => {
+                        self.lap_count += 1;
+                        self.complete_lap();
+                        self.lap_timer.complete_lap(self.frames);
+                    }
+                    Some(_warning) => {
+                        self.warning = Some((_warning, self.frames));
+                        self.lap_samples.clear();
+                        self.lap_clean = true;
+                        self.lap_timer.discard_lap(self.frames);
+                    }
+                }
+
+                // Reset walls and ornaments to original positions; the
+                // wall index was built from these same positions
+                self.walls = new_walls(&self.track);
+                self.ornaments = new_ornaments(&self.track);
+                self.pads = new_pads(&self.track);
+                self.item_boxes = new_item_boxes(&self.track);
+                self.oil_slicks.clear();
+                self.hazards = new_hazards(&self.track);
+
+                // Check if cart completed 3 laps
+                if self.lap_count >= RACE_LAPS {
+                    self.finishes.push((Racer::Player, self.frames));
+                    self.project_rival_finishes();
+                    return RaceStatus::Finished;
+                }
+            }
+
+            // Rival Cart late or early lap adjust: a rival more than half a
+            // lap of course away is drawn where it is on the player's lap.
+            // This only moves it on screen; its distance is its own
+            let _lap_pixels = distance_to_course(_lap_length);
+            for r in &mut self.rival_carts {
+                let _gap = r.get_position().y - self.cart.get_position().y;
+                if _gap > _lap_pixels / 2.0 {
+                    let _p = Point::new(r.get_position().x, r.get_position().y - _lap_pixels);
+                    r.set_position(_p);
+                } else if _gap < -_lap_pixels / 2.0 {
+                    let _p = Point::new(r.get_position().x, r.get_position().y + _lap_pixels);
+                    r.set_position(_p);
+                }
+            }
+
+            // Boost pads and slow strips under the cart
+            self.pad_effect = self
+                .pad_effect
+                .filter(|(_, _frames)| *_frames > 1)
+                .map(|(_kind, _frames)| (_kind, _frames - 1));
+            let _cart_position = self.cart.get_position();
+            self.pad_hit = self
+                .pads
+                .iter_mut()
+                .find_map(|pad| pad.drive_over(_cart_position));
+            // Item boxes under the cart and the use item key
+            self.pick_up_item();
+            let _pressed = _keystate.is_pressed(USE_ITEM_KEY);
+            let _burst = if _pressed && !self.item_key_held {
+                self.use_item()
+            } else {
+                None
+            };
+            self.item_key_held = _pressed;
+            // A speed burst pushes the cart like a boost pad
+            let _push = self.pad_hit.or(_burst);
+            if let Some(_kind) = _push {
+                self.pad_effect = Some((_kind, seconds_to_frames(_kind.seconds())));
+            }
+
+            // Hazards the cart runs into; one may knock it and cut its speed
+            let _hazard_push = self.hit_hazard();
+            if self.cart.is_knocked() {
+                _velocity = self.cart.get_velocity();
+            }
+
+            // A knocked cart cannot be driven until it recovers
+            let mut _heading = self.cart.get_heading();
+            if !self.cart.is_knocked() {
+                let _course_position = Point::new(
+                    self.cart.get_position().x,
+                    self.cart.get_position().y + self.scroll(),
+                );
+                let mut _handling = surface_at(&self.track.surfaces, _course_position).handling();
+                if let Some((_kind, _)) = self.pad_effect {
+                    _handling.top_speed *= _kind.top_speed();
+                }
+                (_velocity, _heading) = step(
+                    _velocity,
+                    _heading,
+                    &Controls::from_keys(_keystate),
+                    &_handling,
+                );
+                if let Some(_kind) = _push {
+                    _velocity = impulse(_velocity, _heading, _kind.impulse());
+                }
+                if let Some(_speed) = _hazard_push {
+                    _velocity = impulse(_velocity, _heading, _speed);
+                }
+            }
+            // Positions at the start of the step, for swept collision
+            let _start_x = self.cart.get_position().x;
+            let _start_wall_y = self.walls.first().map_or(0.0, |wall| wall.p().y);
+            let _start_scroll = self.scroll();
+            self.cart.run(_velocity, _heading);
+
+            // Ornament
+            self.ornaments.iter_mut().for_each(|ornament| {
+                ornament.run(Velocity {
+                    x: 0.0,
+                    y: _velocity.y,
+                });
+            });
+
+            // walls update
+            self.walls.iter_mut().for_each(|wall| {
+                wall.run(Velocity {
+                    x: 0.0,
+                    y: _velocity.y,
+                });
+            });
+            self.pads.iter_mut().for_each(|pad| {
+                pad.run(Velocity {
+                    x: 0.0,
+                    y: _velocity.y,
+                });
+            });
+            self.item_boxes.iter_mut().for_each(|item_box| {
+                item_box.run(Velocity {
+                    x: 0.0,
+                    y: _velocity.y,
+                });
+            });
+            self.oil_slicks.iter_mut().for_each(|oil_slick| {
+                oil_slick.run(Velocity {
+                    x: 0.0,
+                    y: _velocity.y,
+                });
+            });
+            self.hazards.iter_mut().for_each(|hazard| {
+                hazard.run(Velocity {
+                    x: 0.0,
+                    y: _velocity.y,
+                });
+            });
+
+            // Check Cart for Rival Cart
+            if self.hit_rival_cart() && self.damage >= DAMAGE_LIMIT {
+                return RaceStatus::Crashed;
+            }
+
+            // Update cart
+            self.cart.update();
+
+            // Rivals now and then make a mistake, more often at easier levels
+            let _mistake_chance = self.difficulty.level.mistakes_per_second() * FRAME_SIZE / 1000.0;
+            for _rival in &mut self.rival_carts {
+                if !_rival.is_making_mistake() && self.rng.gen_bool(_mistake_chance) {
+                    _rival.make_mistake();
+                }
+            }
+
+            // Update all rival carts check_collision and adjust
+            let _progress = self.lap_count as f32 * _lap_length + self.distance;
+            let _difficulty = self.difficulty;
+            let _around = Surroundings {
+                walls: &self.walls,
+                wall_index: &self.wall_index,
+                cart_velocity: self.cart.get_velocity(),
+                lap_length: _lap_length,
+                surfaces: &self.track.surfaces,
+                scroll: self.scroll(),
+                hazards: &self.hazards,
+                racing_line: self.racing_line.as_ref(),
+            };
+            self.rival_carts.iter_mut().for_each(|rival_cart| {
+                // Catch-up goes by race progress, the way the standings do
+                let _gap = rival_cart.get_laps() as f32 * _lap_length + rival_cart.get_distance()
+                    - _progress;
+                rival_cart.update(&_around, _difficulty.pace(_gap));
+            });
+            // Rivals finish in the order they complete `RACE_LAPS` laps
+            for (_index, _rival) in self.rival_carts.iter().enumerate() {
+                let _racer = Racer::Rival(_index);
+                if _rival.get_laps() >= RACE_LAPS
+                    && !self
+                        .finishes
+                        .iter()
+                        .any(|(_finished, _)| *_finished == _racer)
+                {
+                    self.finishes.push((_racer, self.frames));
+                }
+            }
+            self.ornaments.iter_mut().for_each(|ornament| {
+                ornament.update();
+            });
+            self.walls.iter_mut().for_each(|wall| {
+                wall.update();
+            });
+            self.pads.iter_mut().for_each(|pad| {
+                pad.update();
+            });
+            self.item_boxes.iter_mut().for_each(|item_box| {
+                item_box.update();
+            });
+            self.oil_slicks.iter_mut().for_each(|oil_slick| {
+                oil_slick.update();
+            });
+            self.oil_slicks.retain(|oil_slick| !oil_slick.is_behind());
+            self.hazards.iter_mut().for_each(|hazard| {
+                hazard.update();
+                hazard.slide();
+            });
+            self.rival_items();
+
+            // Check Cart for Walls over the whole step
+            if let Some((_contact, _motion)) = self.hit_wall(_start_x, _start_wall_y) {
+                self.contact = Some(_contact);
+                self.knock_off_wall(&_contact, _motion);
+            }
+
+            // Check collision with rival carts and oil slicks
+            self.hit_rival_cart();
+            self.hit_oil_slick();
+            if self.damage >= DAMAGE_LIMIT {
+                return RaceStatus::Crashed;
+            }
+
+            let _cart_position = self.cart.get_position();
+            if let Some(_warning) = self.checkpoints.update(
+                Point::new(_start_x, _cart_position.y + _start_scroll),
+                Point::new(_cart_position.x, _cart_position.y + self.scroll()),
+            ) {
+                self.warning = Some((_warning, self.frames));
+            }
+            self.lap_timer.update(self.frames, self.distance);
+            self.lap_samples.push(GhostSample {
+                x: self.cart.get_position().x,
+                distance: self.distance,
+                direction: self.cart.get_direction(),
+            });
+            RaceStatus::Running
+        }
+
+        /// Keeps the lap just completed as the ghost if it is the fastest
+        fn complete_lap(&mut self) {
+            let _samples = std::mem::take(&mut self.lap_samples);
+            let _clean = std::mem::replace(&mut self.lap_clean, true);
+            if _clean
+                && self
+                    .ghost
+                    .as_ref()
+                    .is_none_or(|ghost| (_samples.len() as u32) < ghost.frames())
+            {
+                self.ghost = Some(Ghost::new(_samples));
+            }
+        }
+
+        ///
+        /// Puts the wrecked cart back at the last checkpoint passed this lap
+        ///
+        /// The cart restarts stopped and undamaged in the middle of the
+        /// checkpoint, or on the start line if none was passed yet, and the
+        /// track's respawn penalty is added to the race time.
+        ///
+        /// # Returns
+        /// `false` if the track has no checkpoints, so the race cannot go on
+        pub fn respawn(&mut self) -> bool {
+            if self.checkpoints.is_empty() {
+                return false;
+            }
+            let _start = self.track.start;
+            let _point = self.checkpoints.respawn_point().unwrap_or(_start);
+            let _scroll = self.scroll();
+            self.walls = new_walls(&self.track);
+            self.ornaments = new_ornaments(&self.track);
+            self.pads = new_pads(&self.track);
+            self.item_boxes = new_item_boxes(&self.track);
+            self.oil_slicks.clear();
+            self.hazards = new_hazards(&self.track);
+            self.scroll_course(_point.y - _start.y);
+            self.hold_rivals(_scroll);
+            self.distance = course_to_distance(_point.y - _start.y);
+            self.cart.respawn(Point::new(_point.x, _start.y));
+            self.damage = 0.0;
+            self.contact = None;
+            self.pad_effect = None;
+            self.frames += seconds_to_frames(self.track.respawn_penalty);
+            self.last_respawn = Some(self.frames);
+            self.lap_clean = false;
+            true
+        }
+
+        /// How far the course has scrolled down the screen since the lap started
+        pub fn scroll(&self) -> f32 {
+            self.walls
+                .first()
+                .map_or(0.0, |wall| self.track.walls[0][1] - wall.p().y)
+        }
+
+        ///
+        /// Where every car stands in the race
+        ///
+        /// # Returns
+        /// The cars that finished in finishing order, then the others from
+        /// the furthest on
+        pub fn standings(&self) -> Vec<Standing> {
+            let _lap_length = self.track.lap_length;
+            let _finish = |racer: Racer| {
+                self.finishes
+                    .iter()
+                    .find(|(_finished, _)| *_finished == racer)
+                    .map(|(_, _frames)| *_frames)
+            };
+            let mut _standings = vec![Standing {
+                racer: Racer::Player,
+                finish: _finish(Racer::Player),
+                progress: self.lap_count as f32 * _lap_length + self.distance,
+            }];
+            for (_index, _rival) in self.rival_carts.iter().enumerate() {
+                _standings.push(Standing {
+                    racer: Racer::Rival(_index),
+                    finish: _finish(Racer::Rival(_index)),
+                    progress: _rival.get_laps() as f32 * _lap_length + _rival.get_distance(),
+                });
+            }
+            _standings.sort_by(|a, b| match (a.finish, b.finish) {
+                (Some(_a), Some(_b)) => _a.cmp(&_b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => b.progress.total_cmp(&a.progress),
+            });
+            _standings
+        }
+
+        /// Gives every rival still racing the finish time its average pace
+        /// since the start would reach the end of the race at
+        fn project_rival_finishes(&mut self) {
+            let _lap_length = self.track.lap_length;
+            let _frames = self.frames.max(1) as f32;
+            let mut _projected = vec![];
+            for (_index, (_rival, _spawn)) in
+                self.rival_carts.iter().zip(&self.track.rivals).enumerate()
+            {
+                let _racer = Racer::Rival(_index);
+                if self
+                    .finishes
+                    .iter()
+                    .any(|(_finished, _)| *_finished == _racer)
+                {
+                    continue;
+                }
+                let _progress = _rival.get_laps() as f32 * _lap_length + _rival.get_distance();
+                let _driven = _progress - course_to_distance(_spawn.y - self.track.start.y);
+                if _driven <= 0.0 {
+                    continue;
+                }
+                let _left = RACE_LAPS as f32 * _lap_length - _progress;
+                let _finish = self.frames + (_left.max(0.0) * _frames / _driven).ceil() as u32;
+                _projected.push((_racer, _finish));
+            }
+            _projected.sort_by_key(|(_, _finish)| *_finish);
+            self.finishes.extend(_projected);
+        }
+
+        /// Place of the player in the race, counting from 1
+        pub fn position(&self) -> usize {
+            self.standings()
+                .iter()
+                .position(|_standing| _standing.racer == Racer::Player)
+                .map_or(1, |_index| _index + 1)
+        }
+
+        /// Race time in milliseconds, counted in fixed steps
+        pub fn elapsed(&self) -> i32 {
+            frames_to_millis(self.frames)
+        }
+
+        ///
+        /// Finds the first wall the cart touched during the step
+        ///
+        /// # Arguments
+        /// * `_start_x` - Cart `x` at the start of the step
+        /// * `_start_wall_y` - `y` of the first wall at the start of the step
+        ///
+        /// # Returns
+        /// The earliest contact, if any, and the cart's motion relative to the walls
+        fn hit_wall(&self, _start_x: f32, _start_wall_y: f32) -> Option<(Contact, Velocity)> {
+            let _position = self.cart.get_position();
+            // Walls move and the cart stays put along `y`, so relative to
+            // the walls the cart moves by what they scrolled
+            let _motion = Velocity {
+                x: _position.x - _start_x,
+                y: _start_wall_y - self.walls.first().map_or(0.0, |wall| wall.p().y),
+            };
+            let _low = _position.y.min(_position.y - _motion.y) - CART_HEIGHT;
+            let _high = _position.y.max(_position.y - _motion.y) + CART_HEIGHT;
+            self.wall_index
+                .near(&self.walls, _low, _high)
+                .into_iter()
+                .filter(|_wall| _wall.p().y.min(_wall.q().y) <= self.distance)
+                .filter_map(|_wall| self.cart.sweep(_wall.get_line(), _motion))
+                .min_by(|a, b| a.time.total_cmp(&b.time))
+                .map(|_contact| (_contact, _motion))
+        }
+
+        ///
+        /// Bounces the cart off a wall it hit
+        ///
+        /// The cart is put back where it touched the wall, pushed away along
+        /// the wall normal and knocked. Only the first hit of a stun does
+        /// damage, rising with the speed into the wall, unless a shield
+        /// absorbs it.
+        ///
+        /// # Arguments
+        /// * `_contact` - Where and when the cart touched the wall
+        /// * `_motion` - The cart's motion relative to the walls over the step
+        fn knock_off_wall(&mut self, _contact: &Contact, _motion: Velocity) {
+            // Motion is in course units; see `hit_wall`
+            let _speed = course_to_distance(
+                -(_motion.x * _contact.normal.x + _motion.y * _contact.normal.y),
+            );
+            if !self.cart.is_knocked() {
+                if self.shield {
+                    // The shield takes the hit instead of the cart
+                    self.shield = false;
+                } else {
+                    self.damage += WALL_DAMAGE + WALL_DAMAGE_PER_SPEED * _speed.max(0.0);
+                }
+            }
+            let _rewind = 1.0 - _contact.time;
+            self.knock_back(Velocity {
+                x: _contact.normal.x * KNOCKBACK_DISTANCE - _motion.x * _rewind,
+                y: _contact.normal.y * KNOCKBACK_DISTANCE - _motion.y * _rewind,
+            });
+        }
+
+        /// Knocks the cart if it touches any rival cart
+        fn hit_rival_cart(&mut self) -> bool {
+            if self.cart.is_knocked() {
+                return false;
+            }
+            let cart_position = self.cart.get_position();
+            let Some(_rival) = self
+                .rival_carts
+                .iter()
+                .find(|rival_cart| rival_cart.check_collision_with_cart(cart_position))
+            else {
+                return false;
+            };
+            let _dx = cart_position.x - _rival.get_position().x;
+            let _dy = cart_position.y - _rival.get_position().y;
+            let _length = (_dx * _dx + _dy * _dy).sqrt().max(f32::EPSILON);
+            self.damage += RIVAL_DAMAGE;
+            self.knock_back(Velocity {
+                x: _dx / _length * KNOCKBACK_DISTANCE,
+                y: _dy / _length * KNOCKBACK_DISTANCE,
+            });
+            true
+        }
+
+        /// Gives the cart a random item from a box it drives through, if its slot is empty
+        fn pick_up_item(&mut self) {
+            if self.item.is_some() {
+                return;
+            }
+            let _position = self.cart.get_position();
+            if let Some(_item_box) = self
+                .item_boxes
+                .iter_mut()
+                .find(|item_box| item_box.touches(_position))
+            {
+                _item_box.used = true;
+                self.item = Some(Item::random(&mut self.rng));
+            }
+        }
+
+        ///
+        /// Uses the item in the cart's slot
+        ///
+        /// # Returns
+        /// `PadKind::Boost` when the item was a speed burst, for the caller
+        /// to push the cart as a boost pad does
+        fn use_item(&mut self) -> Option<PadKind> {
+            match self.item.take()? {
+                Item::Shield => self.shield = true,
+                Item::SpeedBurst => return Some(PadKind::Boost),
+                Item::OilSlick => {
+                    let _position = self.cart.get_position();
+                    self.oil_slicks.push(OilSlick::at(Point::new(
+                        _position.x,
+                        _position.y - OIL_SLICK_DROP,
+                    )));
+                }
+            }
+            None
+        }
+
+        /// Lets rivals take items from boxes, use them and swerve off oil slicks
+        fn rival_items(&mut self) {
+            for _rival in &mut self.rival_carts {
+                let _position = _rival.get_position();
+                if !_rival.holds_item()
+                    && let Some(_item_box) = self
+                        .item_boxes
+                        .iter_mut()
+                        .find(|item_box| item_box.touches(_position))
+                {
+                    _item_box.used = true;
+                    let _item = Item::random(&mut self.rng);
+                    _rival.give_item(_item, self.rng.gen_range(RIVAL_HOLD_MIN..=RIVAL_HOLD_MAX));
+                }
+                if _rival.use_item() == Some(Item::OilSlick) {
+                    self.oil_slicks.push(OilSlick::at(Point::new(
+                        _position.x,
+                        _position.y - OIL_SLICK_DROP,
+                    )));
+                }
+                if let Some(_index) = self
+                    .oil_slicks
+                    .iter()
+                    .position(|oil_slick| oil_slick.touches(_position))
+                {
+                    self.oil_slicks.remove(_index);
+                    _rival.swerve();
+                }
+            }
+        }
+
+        /// Spins the cart out on an oil slick it runs into, unless a shield absorbs it
+        fn hit_oil_slick(&mut self) {
+            let _position = self.cart.get_position();
+            let Some(_index) = self
+                .oil_slicks
+                .iter()
+                .position(|oil_slick| oil_slick.touches(_position))
+            else {
+                return;
+            };
+            self.oil_slicks.remove(_index);
+            self.spin_out();
+        }
+
+        /// Spins the cart out on oil, unless a shield absorbs it
+        fn spin_out(&mut self) {
+            if self.shield {
+                self.shield = false;
+            } else if !self.cart.is_knocked() {
+                self.cart.knocked(0.0);
+            }
+        }
+
+        ///
+        /// Lets the first hazard the cart runs into act on it
+        ///
+        /// Oil spins the cart out, a barrel knocks it back and does damage,
+        /// and a cone is knocked aside and slows the cart.
+        ///
+        /// # Returns
+        /// The speed per second a cone takes off along the heading
+        fn hit_hazard(&mut self) -> Option<f32> {
+            let _position = self.cart.get_position();
+            let _index = self
+                .hazards
+                .iter()
+                .position(|hazard| hazard.touches(_position))?;
+            match self.hazards[_index].kind {
+                HazardKind::Oil => self.spin_out(),
+                HazardKind::Cone => {
+                    self.hazards[_index].scatter(_position);
+                    return Some(CONE_IMPULSE);
+                }
+                HazardKind::Barrel => {
+                    if !self.cart.is_knocked() {
+                        self.damage += BARREL_DAMAGE;
+                    }
+                    let _center = self.hazards[_index].center();
+                    let _dx = _position.x - _center.x;
+                    let _dy = _position.y - _center.y;
+                    let _length = (_dx * _dx + _dy * _dy).sqrt().max(f32::EPSILON);
+                    self.knock_back(Velocity {
+                        x: _dx / _length * KNOCKBACK_DISTANCE,
+                        y: _dy / _length * KNOCKBACK_DISTANCE,
+                    });
+                }
+            }
+            None
+        }
+
+        ///
+        /// Knocks the cart and moves it relative to the course
+        ///
+        /// The cart only moves sideways on screen; moving it along the
+        /// course scrolls the walls and ornaments back instead.
+        ///
+        /// # Arguments
+        /// * `_shift` - How far to move the cart relative to the course
+        fn knock_back(&mut self, _shift: Velocity) {
+            let _scroll = self.scroll();
+            self.cart.knocked(_shift.x);
+            self.scroll_course(_shift.y);
+            self.hold_rivals(_scroll);
+            self.distance += course_to_distance(_shift.y);
+        }
+
+        /// Moves the rival carts on screen so they stay where they were on the
+        /// course when it had scrolled by `_scroll`
+        fn hold_rivals(&mut self, _scroll: f32) {
+            let _shift = self.scroll() - _scroll;
+            for _rival in &mut self.rival_carts {
+                let _position = _rival.get_position();
+                _rival.set_position(Point::new(_position.x, _position.y - _shift));
+            }
+        }
+
+        /// Scrolls the walls, ornaments, pads, item boxes, oil slicks and
+        /// hazards down the screen by `_scroll`
+        fn scroll_course(&mut self, _scroll: f32) {
+            let _velocity = Velocity { x: 0.0, y: _scroll };
+            self.walls.iter_mut().for_each(|wall| {
+                wall.run(_velocity);
+            });
+            self.ornaments.iter_mut().for_each(|ornament| {
+                ornament.run(_velocity);
+            });
+            self.pads.iter_mut().for_each(|pad| {
+                pad.run(_velocity);
+            });
+            self.item_boxes.iter_mut().for_each(|item_box| {
+                item_box.run(_velocity);
+            });
+            self.oil_slicks.iter_mut().for_each(|oil_slick| {
+                oil_slick.run(_velocity);
+            });
+            self.hazards.iter_mut().for_each(|hazard| {
+                hazard.run(_velocity);
+            });
+        }
+
+        /// Draws every race element
+        pub fn draw(&self, renderer: &dyn Renderer) {
+            draw_surfaces(renderer, &self.track.surfaces, self.scroll());
+            self.pads.iter().for_each(|pad| {
+                pad.draw(renderer);
+            });
+            self.oil_slicks.iter().for_each(|oil_slick| {
+                oil_slick.draw(renderer);
+            });
+            self.item_boxes.iter().for_each(|item_box| {
+                item_box.draw(renderer);
+            });
+            self.hazards.iter().for_each(|hazard| {
+                hazard.draw(renderer);
+            });
+            if let Some(ghost) = &self.ghost {
+                let _frame = self.lap_samples.len().saturating_sub(1) as u32;
+                ghost.draw(renderer, _frame, self.distance);
+            }
+            self.checkpoints.draw(renderer, self.scroll());
+            self.cart.draw(renderer);
+            if self.shield {
+                draw_shield(renderer, self.cart.get_position());
+            }
+            self.ornaments.iter().for_each(|ornament| {
+                ornament.draw(renderer);
+            });
+            self.walls.iter().for_each(|wall| {
+                wall.draw(renderer);
+            });
+            self.rival_carts.iter().for_each(|rival_cart| {
+                rival_cart.draw(renderer);
+            });
+        }
+    }
+
+    /// Converts a number of fixed steps into milliseconds
+    pub fn frames_to_millis(frames: u32) -> i32 {
+        (frames as f64 * FRAME_SIZE).round() as i32
+    }
+
+    /// Converts seconds into a number of fixed steps
+    pub fn seconds_to_frames(seconds: f32) -> u32 {
+        (seconds as f64 * 1000.0 / FRAME_SIZE).round() as u32
+    }
+
+    /// Builds the walls of the course at their original positions
+    fn new_walls(track: &Track) -> Vec<Wall> {
+        track
+            .wall_lines()
+            .map(|(p, q)| Wall::new(p, q, Velocity { x: 0.0, y: 0.0 }))
+            .collect()
+    }
+
+    /// Builds the pads of the course at their original positions
+    fn new_pads(track: &Track) -> Vec<Pad> {
+        track.pads.iter().map(Pad::from_spawn).collect()
+    }
+
+    /// Builds the item boxes of the course at their original positions
+    fn new_item_boxes(track: &Track) -> Vec<ItemBox> {
+        track.item_boxes.iter().copied().map(ItemBox::at).collect()
+    }
+
+    /// Builds the hazards of the course at their original positions
+    fn new_hazards(track: &Track) -> Vec<Hazard> {
+        track.hazards.iter().map(Hazard::from_spawn).collect()
+    }
+
+    /// Builds the ornaments of the course at their original positions
+    fn new_ornaments(track: &Track) -> Vec<Ornament> {
+        vec![Ornament::from_track(track)]
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::engine::FRAME_SIZE;
+        use crate::game::replay::replay::*;
+
+        /// Top speed of the cart per fixed step
+        fn top_speed() -> f32 {
+            TOP_SPEED * (FRAME_SIZE / 1000.0) as f32
+        }
+
+        fn default_track() -> Rc<Track> {
+            let _json = include_str!("../../../../cart/assets/tracks/default.json");
+            Rc::new(Track::from_json(_json).unwrap())
+        }
+
+        fn keys(codes: &[&str]) -> KeyState {
+            let mut _keystate = KeyState::new();
+            codes.iter().for_each(|code| _keystate.set_pressed(code));
+            _keystate
+        }
+
+        /// Throttle held, with a steer now and then
+        fn driver_keys(step: u32) -> KeyState {
+            match step % 90 {
+                0..=9 => keys(&["ArrowUp", "ArrowLeft"]),
+                40..=49 => keys(&["ArrowUp", "ArrowRight"]),
+                _ => keys(&["ArrowUp"]),
+            }
+        }
+
+        #[test]
+        fn throttle_moves_the_cart_on() {
+            let mut _race = Race::new(default_track(), Difficulty::default());
+            let _throttle = keys(&["ArrowUp"]);
+            for _ in 0..120 {
+                assert_eq!(_race.update(&_throttle), RaceStatus::Running);
+            }
+            assert!(_race.distance > 0.0);
+            assert!(_race.cart.get_velocity().y <= top_speed());
+            assert_eq!(_race.lap_count, 0);
+        }
+
+        #[test]
+        fn throttle_moves_every_car_on() {
+            let mut _race = Race::new(default_track(), Difficulty::default());
+            let _start = _race.standings();
+            let _throttle = keys(&["ArrowUp"]);
+            for _ in 0..120 {
+                assert_eq!(_race.update(&_throttle), RaceStatus::Running);
+            }
+            assert!(_race.finishes.is_empty());
+            let _standings = _race.standings();
+            assert_eq!(_standings.len(), 1 + _race.rival_carts.len());
+            for _standing in &_standings {
+                let _before = _start.iter().find(|s| s.racer == _standing.racer).unwrap();
+                assert!(
+                    _standing.progress > _before.progress,
+                    "{:?} did not move",
+                    _standing
+                );
+            }
+            assert!((1..=_standings.len()).contains(&_race.position()));
+        }
+
+        #[test]
+        fn surface_under_the_cart_limits_its_speed() {
+            let mut _track = (*default_track()).clone();
+            _track.surfaces = vec![SurfaceZone {
+                surface: Surface::Sand,
+                points: vec![[0.0, 0.0], [800.0, 0.0], [800.0, 1000.0], [0.0, 1000.0]],
+            }];
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            _race.rival_carts.clear();
+            let _throttle = keys(&["ArrowUp"]);
+            for _ in 0..300 {
+                assert_eq!(_race.update(&_throttle), RaceStatus::Running);
+                assert!(_race.cart.get_velocity().y <= top_speed() * 0.35 + 1e-4);
+            }
+            // Driving on past the sand, the cart speeds up again
+            while _race.scroll() < 1000.0 {
+                _race.update(&_throttle);
+            }
+            for _ in 0..120 {
+                assert_eq!(_race.update(&_throttle), RaceStatus::Running);
+            }
+            assert!(_race.cart.get_velocity().y > top_speed() * 0.5);
+        }
+
+        #[test]
+        fn steering_into_a_wall_crashes() {
+            let mut _race = Race::new(default_track(), Difficulty::default());
+            let _steer = keys(&["ArrowUp", "ArrowLeft"]);
+            let _status = (0..2000)
+                .map(|_| _race.update(&_steer))
+                .find(|_status| *_status != RaceStatus::Running);
+            assert_eq!(_status, Some(RaceStatus::Crashed));
+        }
+
+        #[test]
+        fn short_laps_finish_the_race() {
+            let mut _track = (*default_track()).clone();
+            _track.lap_length = 100.0;
+            _track.sectors.clear();
+            _track.checkpoints.clear();
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            let _throttle = keys(&["ArrowUp"]);
+            let _status = (0..2000)
+                .map(|_| _race.update(&_throttle))
+                .find(|_status| *_status != RaceStatus::Running);
+            assert_eq!(_status, Some(RaceStatus::Finished));
+            assert_eq!(_race.lap_count, RACE_LAPS);
+            assert_eq!(_race.lap_timer.laps.len(), RACE_LAPS as usize);
+            assert!(_race.finishes.contains(&(Racer::Player, _race.frames)));
+            let _standings = _race.standings();
+            let _player = _standings
+                .iter()
+                .find(|s| s.racer == Racer::Player)
+                .unwrap();
+            assert_eq!(_player.finish, Some(_race.frames));
+            assert_eq!(
+                _race.position(),
+                _race
+                    .finishes
+                    .iter()
+                    .position(|(r, _)| *r == Racer::Player)
+                    .unwrap()
+                    + 1
+            );
+        }
+
+        #[test]
+        fn rivals_racing_on_get_a_finish_time_from_their_pace() {
+            let mut _track = (*default_track()).clone();
+            _track.checkpoints.clear();
+            _track.lap_length = 400.0;
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            let _throttle = keys(&["ArrowUp"]);
+            // A boost pad under the cart on every step leaves the rivals behind
+            while _race.update(&_throttle) == RaceStatus::Running {
+                _race.pad_effect = Some((PadKind::Boost, 2));
+                assert!(_race.frames < 5000);
+            }
+            let _standings = _race.standings();
+            assert!(
+                _standings.iter().all(|s| s.finish.is_some()),
+                "{:?}",
+                _standings
+            );
+            assert!(
+                _standings
+                    .windows(2)
+                    .all(|pair| pair[0].finish <= pair[1].finish)
+            );
+            let _player = _race.frames;
+            let _rivals_behind: Vec<&Standing> = _standings
+                .iter()
+                .skip_while(|s| s.racer != Racer::Player)
+                .skip(1)
+                .collect();
+            assert!(!_rivals_behind.is_empty());
+            for _standing in &_rivals_behind {
+                assert!(_standing.finish.unwrap() > _player);
+                assert!(_standing.progress < RACE_LAPS as f32 * 400.0);
+            }
+        }
+
+        #[test]
+        fn lap_past_the_checkpoints_is_not_counted() {
+            let mut _track = (*default_track()).clone();
+            _track.lap_length = 100.0;
+            _track.sectors.clear();
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            let _throttle = keys(&["ArrowUp"]);
+            while _race.warning.is_none() {
+                assert_eq!(_race.update(&_throttle), RaceStatus::Running);
+            }
+            assert_eq!(
+                _race.warning,
+                Some((LapWarning::LapNotCounted, _race.frames))
+            );
+            assert_eq!(_race.lap_count, 0);
+            assert!(_race.lap_timer.laps.is_empty());
+            assert!(_race.ghost.is_none());
+        }
+
+        #[test]
+        fn pad_works_once_in_each_lap() {
+            let mut _track = (*default_track()).clone();
+            _track.lap_length = 100.0;
+            _track.sectors.clear();
+            _track.checkpoints.clear();
+            _track.pads = vec![PadSpawn {
+                kind: PadKind::Slow,
+                x: _track.start.x,
+                y: _track.start.y + 100.0,
+            }];
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            _race.rival_carts.clear();
+            let _throttle = keys(&["ArrowUp"]);
+            let mut _hits = vec![];
+            loop {
+                let _status = _race.update(&_throttle);
+                if _race.pad_hit.is_some() {
+                    _hits.push(_race.lap_count);
+                }
+                if _status != RaceStatus::Running {
+                    assert_eq!(_status, RaceStatus::Finished);
+                    break;
+                }
+            }
+            assert_eq!(_hits, [0, 1, 2]);
+        }
+
+        #[test]
+        fn item_box_gives_one_item_in_each_lap() {
+            let mut _track = (*default_track()).clone();
+            _track.lap_length = 100.0;
+            _track.sectors.clear();
+            _track.checkpoints.clear();
+            _track.item_boxes = vec![Point::new(_track.start.x, _track.start.y + 100.0)];
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            _race.rival_carts.clear();
+            let _throttle = keys(&["ArrowUp"]);
+            let mut _pick_ups = vec![];
+            loop {
+                let _status = _race.update(&_throttle);
+                if _race.item.take().is_some() {
+                    _pick_ups.push(_race.lap_count);
+                }
+                if _status != RaceStatus::Running {
+                    assert_eq!(_status, RaceStatus::Finished);
+                    break;
+                }
+            }
+            assert_eq!(_pick_ups, [0, 1, 2]);
+        }
+
+        #[test]
+        fn item_box_is_left_alone_while_the_slot_is_full() {
+            let mut _race = race_at_speed(0.0);
+            _race.item_boxes = vec![ItemBox::at(_race.cart.get_position())];
+            _race.item = Some(Item::Shield);
+            _race.pick_up_item();
+            assert_eq!(_race.item, Some(Item::Shield));
+            assert!(!_race.item_boxes[0].used);
+
+            _race.item = None;
+            _race.pick_up_item();
+            assert!(_race.item.is_some());
+            assert!(_race.item_boxes[0].used);
+        }
+
+        #[test]
+        fn shield_absorbs_one_wall_hit() {
+            let mut _race = race_at_speed(2.0);
+            _race.shield = true;
+            let (_contact, _motion) = wall_hit(2.0, Point::new(0.0, -1.0));
+            _race.knock_off_wall(&_contact, _motion);
+            assert_eq!(_race.damage, 0.0);
+            assert!(!_race.shield);
+            // The cart is still knocked back
+            assert!(_race.cart.is_knocked());
+
+            for _ in 0..STUN_FRAMES {
+                _race.cart.update();
+            }
+            _race.knock_off_wall(&_contact, _motion);
+            assert!(_race.damage > 0.0);
+        }
+
+        #[test]
+        fn shield_absorbs_an_oil_slick() {
+            let mut _race = race_at_speed(2.0);
+            _race.shield = true;
+            _race.oil_slicks = vec![OilSlick::at(_race.cart.get_position())];
+            _race.hit_oil_slick();
+            assert!(_race.oil_slicks.is_empty());
+            assert!(!_race.shield);
+            assert!(!_race.cart.is_knocked());
+
+            _race.oil_slicks = vec![OilSlick::at(_race.cart.get_position())];
+            _race.hit_oil_slick();
+            assert!(_race.cart.is_knocked());
+        }
+
+        #[test]
+        fn barrel_knocks_the_cart_back_and_does_damage() {
+            let mut _race = race_at_speed(0.0);
+            let _position = _race.cart.get_position();
+            _race.hazards = vec![Hazard::from_spawn(&HazardSpawn {
+                kind: HazardKind::Barrel,
+                x: _position.x - 10.0,
+                y: _position.y,
+            })];
+            assert_eq!(_race.hit_hazard(), None);
+            assert!(_race.cart.is_knocked());
+            assert_eq!(_race.damage, BARREL_DAMAGE);
+            assert_eq!(
+                _race.cart.get_position().x,
+                _position.x + KNOCKBACK_DISTANCE
+            );
+        }
+
+        #[test]
+        fn cone_slows_the_cart_once_and_is_knocked_aside() {
+            let mut _race = race_at_speed(0.0);
+            let _position = _race.cart.get_position();
+            _race.hazards = vec![Hazard::from_spawn(&HazardSpawn {
+                kind: HazardKind::Cone,
+                x: _position.x,
+                y: _position.y,
+            })];
+            assert_eq!(_race.hit_hazard(), Some(CONE_IMPULSE));
+            assert!(!_race.cart.is_knocked());
+            assert_eq!(_race.damage, 0.0);
+            assert_eq!(_race.hit_hazard(), None);
+        }
+
+        #[test]
+        fn same_inputs_give_the_same_race() {
+            let mut _first = Race::new(default_track(), Difficulty::default());
+            let mut _second = Race::new(default_track(), Difficulty::default());
+            for _step in 0..600 {
+                let _keystate = driver_keys(_step);
+                let _status = _first.update(&_keystate);
+                assert_eq!(_second.update(&_keystate), _status);
+                if _status != RaceStatus::Running {
+                    break;
+                }
+            }
+            assert_eq!(_second.distance, _first.distance);
+            assert_eq!(_second.lap_count, _first.lap_count);
+            assert_eq!(_second.cart.get_position(), _first.cart.get_position());
+            for (a, b) in _second.rival_carts.iter().zip(&_first.rival_carts) {
+                assert_eq!(a.get_position(), b.get_position());
+            }
+        }
+
+        #[test]
+        fn top_speed_cart_does_not_pass_a_thin_wall() {
+            let mut _track = (*default_track()).clone();
+            let _wall_y = _track.start.y + 500.0;
+            _track.walls.push([100.0, _wall_y, 700.0, _wall_y]);
+            let _track = Rc::new(_track);
+            let mut _race = Race::new(_track.clone(), Difficulty::default());
+            _race.rival_carts.clear();
+            _race.cart = Cart::new(
+                _track.start,
+                Velocity {
+                    x: 0.0,
+                    y: top_speed(),
+                },
+            );
+            let _first_wall_y = _race.walls[0].p().y;
+            let _throttle = keys(&["ArrowUp"]);
+            let mut _status = RaceStatus::Running;
+            for _ in 0..300 {
+                _status = _race.update(&_throttle);
+                // The walls scroll past the cart
+                let _front_y = _race.cart.get_position().y + CART_HEIGHT + _first_wall_y
+                    - _race.walls[0].p().y;
+                assert!(_front_y <= _wall_y, "cart passed the wall at {}", _front_y);
+                if _status != RaceStatus::Running {
+                    break;
+                }
+            }
+            assert_eq!(_status, RaceStatus::Crashed);
+            let _contact = _race.contact.expect("no contact");
+            assert_eq!(_contact.normal, Point::new(0.0, -1.0));
+        }
+
+        /// A race with no rivals and the cart at its start driving at `speed`
+        fn race_at_speed(speed: f32) -> Race {
+            let mut _race = Race::new(default_track(), Difficulty::default());
+            _race.rival_carts.clear();
+            _race.cart.run(Velocity { x: 0.0, y: speed }, 0.0);
+            _race
+        }
+
+        /// A head-on hit at the end of a step of `speed`, against a wall
+        /// with `normal`
+        fn wall_hit(speed: f32, normal: Point) -> (Contact, Velocity) {
+            let _contact = Contact {
+                time: 1.0,
+                point: Point::new(400.0, 0.0),
+                normal,
+            };
+            let _motion = Velocity {
+                x: -normal.x * distance_to_course(speed),
+                y: -normal.y * distance_to_course(speed),
+            };
+            (_contact, _motion)
+        }
+
+        #[test]
+        fn wall_damage_rises_with_the_speed_into_the_wall() {
+            let mut _slow = race_at_speed(0.0);
+            let (_contact, _motion) = wall_hit(0.0, Point::new(0.0, -1.0));
+            _slow.knock_off_wall(&_contact, _motion);
+            assert_eq!(_slow.damage, WALL_DAMAGE);
+
+            let mut _fast = race_at_speed(top_speed());
+            let (_contact, _motion) = wall_hit(top_speed(), Point::new(0.0, -1.0));
+            _fast.knock_off_wall(&_contact, _motion);
+            assert_eq!(
+                _fast.damage,
+                WALL_DAMAGE + WALL_DAMAGE_PER_SPEED * top_speed()
+            );
+        }
+
+        #[test]
+        fn only_the_first_hit_of_a_stun_does_damage() {
+            let mut _race = race_at_speed(2.0);
+            let (_contact, _motion) = wall_hit(2.0, Point::new(0.0, -1.0));
+            _race.knock_off_wall(&_contact, _motion);
+            let _damage = _race.damage;
+            _race.knock_off_wall(&_contact, _motion);
+            assert_eq!(_race.damage, _damage);
+
+            for _ in 0..STUN_FRAMES {
+                _race.cart.update();
+            }
+            _race.knock_off_wall(&_contact, _motion);
+            assert_eq!(_race.damage, 2.0 * _damage);
+        }
+
+        #[test]
+        fn wall_knockback_pushes_along_the_wall_normal() {
+            let mut _race = race_at_speed(0.0);
+            let _x = _race.cart.get_position().x;
+            let (_contact, _motion) = wall_hit(0.0, Point::new(1.0, 0.0));
+            _race.knock_off_wall(&_contact, _motion);
+            assert!(_race.cart.is_knocked());
+            assert_eq!(_race.cart.get_position().x, _x + KNOCKBACK_DISTANCE);
+            assert_eq!(_race.distance, 0.0);
+
+            let mut _race = race_at_speed(0.0);
+            let _wall_y = _race.walls[0].p().y;
+            let (_contact, _motion) = wall_hit(0.0, Point::new(0.0, -1.0));
+            _race.knock_off_wall(&_contact, _motion);
+            assert_eq!(_race.cart.get_position().x, _x);
+            assert_eq!(_race.distance, -course_to_distance(KNOCKBACK_DISTANCE));
+            // The course scrolls back towards the cart
+            assert_eq!(_race.walls[0].p().y, _wall_y + KNOCKBACK_DISTANCE);
+        }
+
+        #[test]
+        fn rival_hit_does_damage_and_pushes_away_from_the_rival() {
+            let mut _race = race_at_speed(0.0);
+            let _position = _race.cart.get_position();
+            _race.rival_carts = vec![RivalCart::new(
+                Point::new(_position.x - 10.0, _position.y),
+                0.0,
+                0.0,
+                1,
+                Level::Normal.reaction(),
+            )];
+            assert!(_race.hit_rival_cart());
+            assert_eq!(_race.damage, RIVAL_DAMAGE);
+            assert_eq!(
+                _race.cart.get_position().x,
+                _position.x + KNOCKBACK_DISTANCE
+            );
+            // Still stunned, so the rival does no more damage
+            assert!(!_race.hit_rival_cart());
+            assert_eq!(_race.damage, RIVAL_DAMAGE);
+        }
+
+        #[test]
+        fn damage_limit_ends_the_race() {
+            let mut _race = Race::new(default_track(), Difficulty::default());
+            _race.damage = DAMAGE_LIMIT;
+            assert_eq!(_race.update(&KeyState::new()), RaceStatus::Crashed);
+        }
+
+        #[test]
+        fn respawn_restarts_the_cart_at_the_last_checkpoint_passed() {
+            let mut _race = race_at_speed(top_speed());
+            // Cross the first checkpoint, from 296 to 467 at `y` 1800
+            _race
+                .checkpoints
+                .update(Point::new(300.0, 1790.0), Point::new(300.0, 1810.0));
+            _race.damage = DAMAGE_LIMIT;
+            let _frames = _race.frames;
+            assert!(_race.respawn());
+            let _start = _race.track.start;
+            assert_eq!(_race.cart.get_position(), Point::new(296.0, _start.y));
+            assert_eq!(_race.cart.get_velocity().y, 0.0);
+            assert_eq!(_race.distance, course_to_distance(1800.0 - _start.y));
+            assert_eq!(_race.scroll(), 1800.0 - _start.y);
+            assert_eq!(_race.damage, 0.0);
+            assert_eq!(
+                _race.frames,
+                _frames + seconds_to_frames(_race.track.respawn_penalty)
+            );
+            assert_eq!(_race.last_respawn, Some(_race.frames));
+        }
+
+        #[test]
+        fn respawn_works_whether_or_not_the_cart_is_knocked() {
+            let mut _driving = race_at_speed(top_speed());
+            assert!(_driving.respawn());
+            assert!(!_driving.cart.is_knocked());
+            assert_eq!(_driving.cart.get_velocity().y, 0.0);
+
+            let mut _knocked = race_at_speed(top_speed());
+            _knocked.cart.knocked(0.0);
+            assert!(_knocked.respawn());
+            assert!(!_knocked.cart.is_knocked());
+            assert_eq!(_knocked.cart.get_velocity().y, 0.0);
+            // The first step after a respawn drives on from the start line
+            assert_eq!(_knocked.update(&keys(&["ArrowUp"])), RaceStatus::Running);
+        }
+
+        #[test]
+        fn track_without_checkpoints_cannot_respawn() {
+            let mut _track = (*default_track()).clone();
+            _track.checkpoints.clear();
+            let mut _race = Race::new(Rc::new(_track), Difficulty::default());
+            _race.damage = DAMAGE_LIMIT;
+            assert!(!_race.respawn());
+            assert_eq!(_race.damage, DAMAGE_LIMIT);
+            assert_eq!(_race.frames, 0);
+        }
+
+        #[test]
+        fn replayed_inputs_give_the_same_race() {
+            let mut _recorded = Race::new(default_track(), Difficulty::default());
+            let mut _replay = Replay::new("default", Difficulty::default());
+            for _step in 0..600 {
+                let _keystate = driver_keys(_step);
+                _replay.record(&_keystate);
+                if _recorded.update(&_keystate) != RaceStatus::Running {
+                    break;
+                }
+            }
+
+            let _loaded = Replay::from_json(&_replay.to_json().unwrap()).unwrap();
+            let mut _replayed = Race::new(default_track(), Difficulty::default());
+            let mut _player = ReplayPlayer::new(_loaded);
+            for _ in 0.._replay.len() {
+                _replayed.update(&_player.next_keystate());
+            }
+
+            assert_eq!(_replayed.elapsed(), _recorded.elapsed());
+            assert_eq!(_replayed.distance, _recorded.distance);
+            assert_eq!(_replayed.cart.get_position(), _recorded.cart.get_position());
+            for (a, b) in _replayed.rival_carts.iter().zip(&_recorded.rival_carts) {
+                assert_eq!(a.get_position(), b.get_position());
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+#[allow(clippy::module_inception)]
+pub mod racing_line {
+    //! The line rival carts race along.
+    //!
+    //! A track lists waypoints in course space, in driving order. Between
+    //! waypoints the line is straight. Each waypoint also gets a speed factor
+    //! from how sharply the line turns there, lowered ahead of tight turns so
+    //! that a rival following the factors brakes in time.
+    use crate::engine::Point;
+    use crate::game::track::track::Track;
+
+    /* <-- CONSTANT VALUE */
+    /// Speed factor lost per radian the line turns at a waypoint
+    const CORNER_SLOWDOWN: f32 = 1.0;
+    /// Lowest speed factor, through the tightest turns
+    const CORNER_MIN: f32 = 0.5;
+    /// Speed factor a rival can shed per unit of course while braking
+    const BRAKING_PER_LENGTH: f32 = 0.001;
+    /* CONSTANT VALUE --> */
+
+    /// Waypoints of the racing line with their speed factors.
+    pub struct RacingLine {
+        points: Vec<Point>,
+        /// Share of its cruising speed a rival drives at each waypoint
+        speed_factors: Vec<f32>,
+    }
+    impl RacingLine {
+        ///
+        /// Builds the racing line of a track
+        ///
+        /// # Arguments
+        /// * `track` - Track whose `racing_line` waypoints to follow
+        ///
+        /// # Returns
+        /// `None` if the track has fewer than two waypoints
+        pub fn new(track: &Track) -> Option<Self> {
+            let points = track.racing_line.clone();
+            if points.len() < 2 {
+                return None;
+            }
+            let heading = |a: Point, b: Point| (b.x - a.x).atan2(b.y - a.y);
+            let mut speed_factors: Vec<f32> = (0..points.len())
+                .map(|i| {
+                    if i == 0 || i == points.len() - 1 {
+                        return 1.0;
+                    }
+                    let turn =
+                        heading(points[i], points[i + 1]) - heading(points[i - 1], points[i]);
+                    (1.0 - turn.abs() * CORNER_SLOWDOWN).max(CORNER_MIN)
+                })
+                .collect();
+            // Slow down early enough to make each turn
+            for i in (0..points.len() - 1).rev() {
+                let length = points[i + 1].y - points[i].y;
+                speed_factors[i] =
+                    speed_factors[i].min(speed_factors[i + 1] + BRAKING_PER_LENGTH * length);
+            }
+            Some(RacingLine {
+                points,
+                speed_factors,
+            })
+        }
+
+        /// Where the line crosses the course at course `y`
+        pub fn x_at(&self, y: f32) -> f32 {
+            self.interpolate(y, |i| self.points[i].x)
+        }
+
+        /// Share of its cruising speed a rival should drive at course `y`
+        pub fn speed_factor(&self, y: f32) -> f32 {
+            self.interpolate(y, |i| self.speed_factors[i])
+        }
+
+        /// Interpolates a value given at each waypoint, holding it beyond the ends
+        fn interpolate(&self, y: f32, value: impl Fn(usize) -> f32) -> f32 {
+            let last = self.points.len() - 1;
+            if y <= self.points[0].y {
+                return value(0);
+            }
+            if y >= self.points[last].y {
+                return value(last);
+            }
+            let i = self.points.partition_point(|point| point.y <= y) - 1;
+            let (a, b) = (self.points[i], self.points[i + 1]);
+            let t = (y - a.y) / (b.y - a.y);
+            value(i) + (value(i + 1) - value(i)) * t
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::game::cart::cart::CART_WIDTH;
+
+        fn shipped_tracks() -> [Track; 2] {
+            [
+                include_str!("../../../../cart/assets/tracks/default.json"),
+                include_str!("../../../../cart/assets/tracks/mirror.json"),
+            ]
+            .map(|json| Track::from_json(json).unwrap())
+        }
+
+        /// A track racing along `points` with no other content
+        fn track_along(points: &[(f32, f32)]) -> Track {
+            let mut track = shipped_tracks()[0].clone();
+            track.racing_line = points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+            track
+        }
+
+        #[test]
+        fn line_stays_inside_the_corridor() {
+            for track in shipped_tracks() {
+                let line = RacingLine::new(&track).expect("no racing line");
+                let (first, last) = (line.points[0].y, line.points[line.points.len() - 1].y);
+                // Halfway between rows of wall ends, which lie on whole tens
+                let mut y = first + 5.0;
+                while y <= last {
+                    let x = line.x_at(y);
+                    // Walls crossing this row; the open course lies between
+                    // an odd number of them on the left and the next one
+                    let xs: Vec<f32> = track
+                        .wall_lines()
+                        .filter(|(p, q)| p.y.min(q.y) < y && y < p.y.max(q.y))
+                        .map(|(p, q)| p.x + (q.x - p.x) * (y - p.y) / (q.y - p.y))
+                        .collect();
+                    let left = xs.iter().filter(|wall_x| **wall_x < x).count();
+                    assert_eq!(left % 2, 1, "{} y {}: x {} in {:?}", track.name, y, x, xs);
+                    let nearest = xs
+                        .iter()
+                        .map(|wall_x| (wall_x - x).abs())
+                        .fold(f32::MAX, f32::min);
+                    assert!(
+                        nearest >= CART_WIDTH / 2.0,
+                        "{} y {}: x {} is {} from a wall",
+                        track.name,
+                        y,
+                        x,
+                        nearest
+                    );
+                    y += 10.0;
+                }
+            }
+        }
+
+        #[test]
+        fn same_track_gives_the_same_line() {
+            for track in shipped_tracks() {
+                let first = RacingLine::new(&track).unwrap();
+                let second = RacingLine::new(&track.clone()).unwrap();
+                assert_eq!(first.points, second.points);
+                assert_eq!(first.speed_factors, second.speed_factors);
+            }
+        }
+
+        #[test]
+        fn line_is_interpolated_between_waypoints_and_held_beyond_them() {
+            let line = RacingLine::new(&track_along(&[(300.0, 100.0), (500.0, 300.0)])).unwrap();
+            assert_eq!(line.x_at(200.0), 400.0);
+            assert_eq!(line.x_at(0.0), 300.0);
+            assert_eq!(line.x_at(1000.0), 500.0);
+        }
+
+        #[test]
+        fn rivals_brake_ahead_of_a_turn() {
+            let line = RacingLine::new(&track_along(&[
+                (400.0, 0.0),
+                (400.0, 1800.0),
+                (400.0, 2000.0),
+                (600.0, 2200.0),
+            ]))
+            .unwrap();
+            // Straight on at the second waypoint, but the third turns
+            assert!(line.speed_factors[2] < 1.0);
+            assert!(line.speed_factors[1] < 1.0);
+            assert!(line.speed_factors[1] > line.speed_factors[2]);
+            assert_eq!(line.speed_factors[0], 1.0);
+            assert!(line.speed_factor(1900.0) < line.speed_factor(1800.0));
+        }
+
+        #[test]
+        fn line_needs_two_waypoints() {
+            assert!(RacingLine::new(&track_along(&[])).is_none());
+            assert!(RacingLine::new(&track_along(&[(400.0, 0.0)])).is_none());
+        }
+    }
+}
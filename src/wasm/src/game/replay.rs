@@ -0,0 +1,105 @@
+#[allow(clippy::module_inception)]
+pub mod replay {
+    //! Input recording and frame-exact playback.
+    //!
+    //! A race is fully determined by its difficulty and the `KeyState` of each
+    //! fixed step, so a replay only stores those, the snapshots run-length
+    //! encoded.
+    use crate::engine::KeyState;
+    use crate::game::difficulty::difficulty::Difficulty;
+    use anyhow::{Context, Result};
+    use serde::{Deserialize, Serialize};
+
+    /// Version written into every replay file
+    pub const REPLAY_VERSION: u32 = 1;
+
+    /// `frames` consecutive fixed steps with the same keys held
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct ReplayRun {
+        pub frames: u32,
+        pub keys: KeyState,
+    }
+
+    /// The `KeyState` of every fixed step of one race.
+    #[derive(Clone, Default, Serialize, Deserialize)]
+    pub struct Replay {
+        pub version: u32,
+        /// Name of the track raced; empty in replays of the single-track game
+        #[serde(default)]
+        pub track: String,
+        /// Difficulty the rivals raced at; older replays raced at the default
+        #[serde(default)]
+        pub difficulty: Difficulty,
+        pub runs: Vec<ReplayRun>,
+    }
+    impl Replay {
+        /// Creates an empty replay of a race on `track` at `difficulty`
+        pub fn new(track: &str, difficulty: Difficulty) -> Self {
+            Replay {
+                version: REPLAY_VERSION,
+                track: track.into(),
+                difficulty,
+                runs: vec![],
+            }
+        }
+        /// Appends the keys held during one fixed step
+        pub fn record(&mut self, keystate: &KeyState) {
+            match self.runs.last_mut() {
+                Some(run) if run.keys == *keystate => run.frames += 1,
+                _ => self.runs.push(ReplayRun {
+                    frames: 1,
+                    keys: keystate.clone(),
+                }),
+            }
+        }
+        /// Number of recorded fixed steps
+        pub fn len(&self) -> usize {
+            self.runs.iter().map(|run| run.frames as usize).sum()
+        }
+        /// Serializes the replay to JSON
+        pub fn to_json(&self) -> Result<String> {
+            serde_json::to_string(self).context("Error serializing replay")
+        }
+        /// Parses a replay written by `to_json`
+        pub fn from_json(json: &str) -> Result<Self> {
+            let replay: Replay = serde_json::from_str(json).context("Error parsing replay")?;
+            if replay.version != REPLAY_VERSION {
+                return Err(anyhow::anyhow!(
+                    "Unsupported replay version {} (expected {})",
+                    replay.version,
+                    REPLAY_VERSION
+                ));
+            }
+            Ok(replay)
+        }
+    }
+
+    /// Feeds the frames of a `Replay` back one fixed step at a time.
+    pub struct ReplayPlayer {
+        replay: Replay,
+        run: usize,
+        frame: u32,
+    }
+    impl ReplayPlayer {
+        pub fn new(replay: Replay) -> Self {
+            ReplayPlayer {
+                replay,
+                run: 0,
+                frame: 0,
+            }
+        }
+        /// Keys of the next fixed step; no keys once the replay is over
+        pub fn next_keystate(&mut self) -> KeyState {
+            let Some(run) = self.replay.runs.get(self.run) else {
+                return KeyState::new();
+            };
+            let keystate = run.keys.clone();
+            self.frame += 1;
+            if self.frame >= run.frames {
+                self.run += 1;
+                self.frame = 0;
+            }
+            keystate
+        }
+    }
+}
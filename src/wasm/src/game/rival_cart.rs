@@ -2,14 +2,61 @@
 pub mod rival_cart {
     use crate::engine::{Point, Renderer, Velocity};
     use crate::game::cart::cart::CarDirection;
-    use crate::game::wall::wall::Wall;
-    use crate::game::{Piece, STAGE_GOAL};
+    use crate::game::hazard::hazard::Hazard;
+    use crate::game::item::item::{Item, draw_shield};
+    use crate::game::racing_line::racing_line::RacingLine;
+    use crate::game::surface::surface::{SurfaceZone, surface_at};
+    use crate::game::wall::wall::{Wall, WallIndex};
+    use crate::game::{Piece, course_to_distance};
 
     const RIVAL_CART_WIDTH: f32 = 20.0;
-    const COLLISION_CHECK_DISTANCE: f32 = 50.0;
     const LEFT_EDGE: f32 = 100.0;
     const RIGHT_EDGE: f32 = 700.0;
     const EVASION_SPEED: f32 = 4.0;
+    /// Speed factor of a rival using a speed burst
+    const BURST_SPEED: f32 = 1.5;
+    /// Steps a rival's speed burst lasts
+    const BURST_FRAMES: u32 = 60;
+    /// Steps a rival swerves after running into an oil slick
+    const SWERVE_FRAMES: u32 = 60;
+    /// Steps between changes of side while swerving
+    const SWERVE_FLIP: u32 = 15;
+    /// How far up the course a rival aims at the racing line
+    const STEER_LOOKAHEAD: f32 = 120.0;
+    /// Fastest sideways speed per step while following the racing line
+    const STEER_MAX: f32 = 3.0;
+    /// Most the sideways speed changes in one step
+    const STEER_RATE: f32 = 0.3;
+    /// Sideways speed per step from which a rival is drawn turned
+    const TURNED_SPEED: f32 = 1.0;
+    /// Speed per step a rival gains in one step
+    const ACCELERATION: f32 = 0.02;
+    /// Speed per step a rival sheds in one step
+    const BRAKING: f32 = 0.05;
+    /// Steps a rival's mistake lasts
+    const MISTAKE_FRAMES: u32 = 40;
+    /// Share of its speed a rival keeps while making a mistake
+    const MISTAKE_SPEED: f32 = 0.7;
+
+    /// The race around the rivals for one step, the same for every rival
+    pub struct Surroundings<'a> {
+        /// Walls of the course
+        pub walls: &'a [Wall],
+        /// Broad phase over `walls`
+        pub wall_index: &'a WallIndex,
+        /// Velocity of the player's cart, which the screen follows
+        pub cart_velocity: Velocity,
+        /// Distance of one lap
+        pub lap_length: f32,
+        /// Surface zones of the course
+        pub surfaces: &'a [SurfaceZone],
+        /// How far the course has scrolled down the screen
+        pub scroll: f32,
+        /// Hazards on the course, which rivals steer around
+        pub hazards: &'a [Hazard],
+        /// Racing line of the course, if it has one
+        pub racing_line: Option<&'a RacingLine>,
+    }
 
     #[derive(Copy, Clone)]
     pub struct RivalCart {
@@ -17,25 +64,90 @@ pub mod rival_cart {
         velocity: Velocity,
         direction: CarDirection,
         distance: f32, // Rival cart's own distance counter
+        /// Laps completed, counted like the player's
+        laps: i32,
         no: usize,
+        /// Speed per step on the straights; the rival's speed profile
+        /// scales it by the racing line's speed factors
+        cruise: f32,
+        /// Item held, with the steps left before it is used
+        item: Option<(Item, u32)>,
+        shield: bool,
+        /// Steps left of a speed burst
+        burst: u32,
+        /// Steps left of swerving off an oil slick
+        swerve: u32,
+        /// How far ahead the rival looks for walls and hazards to dodge
+        reaction: f32,
+        /// Steps left of a mistake, lifting off and missing the racing line
+        mistake: u32,
     }
 
     impl RivalCart {
-        pub fn new(_position: Point, speed: f32, _distance: f32, _no: usize) -> Self {
+        pub fn new(
+            _position: Point,
+            speed: f32,
+            _distance: f32,
+            _no: usize,
+            _reaction: f32,
+        ) -> Self {
             RivalCart {
                 position: _position,
                 velocity: Velocity { x: 0.0, y: speed },
                 direction: CarDirection::Normal,
                 distance: _distance,
+                laps: 0,
                 no: _no,
+                cruise: speed,
+                item: None,
+                shield: false,
+                burst: 0,
+                swerve: 0,
+                reaction: _reaction,
+                mistake: 0,
             }
         }
-        pub fn update(&mut self, _walls: &[Wall], _velocity: Velocity) {
-            // Update rival's own distance independently
-            self.distance += self.velocity.y;
+        ///
+        /// Moves the rival on by one step
+        ///
+        /// The rival steers for the racing line ahead and drives at its speed
+        /// profile, braking before tight sections. Only when a wall or hazard
+        /// is right in front does it dodge it instead.
+        ///
+        /// # Arguments
+        /// * `_around` - The race around the rival
+        /// * `_pace` - Factor on the rival's speed from catch-up mode
+        pub fn update(&mut self, _around: &Surroundings, _pace: f32) {
+            // Follow the speed profile, braking harder than accelerating
+            let _course_y = self.position.y + _around.scroll;
+            let mut _target = self.cruise
+                * _around
+                    .racing_line
+                    .map_or(1.0, |line| line.speed_factor(_course_y));
+            if self.mistake > 0 {
+                self.mistake -= 1;
+                _target *= MISTAKE_SPEED;
+            }
+            self.velocity.y += (_target - self.velocity.y).clamp(-BRAKING, ACCELERATION);
+
+            // The surface under the rival scales its speed
+            let _surface = surface_at(
+                _around.surfaces,
+                Point::new(self.position.x, self.position.y + _around.scroll),
+            );
+            let mut _speed = self.velocity.y * _surface.handling().top_speed * _pace;
+            if self.burst > 0 {
+                self.burst -= 1;
+                _speed *= BURST_SPEED;
+            }
+
+            // Count distance the way the player's is counted: the screen
+            // follows the player, so the rival covers its own motion on
+            // screen plus the course scrolled past
+            self.distance += course_to_distance(_speed + _around.cart_velocity.y);
 
             // Update Y position relative to player
-            self.position.y += self.velocity.y - _velocity.y;
+            self.position.y += _speed - _around.cart_velocity.y;
 
             // Update X position with calculated horizontal velocity
             let _x: f32 = self.position.x + self.velocity.x;
@@ -45,15 +157,140 @@ pub mod rival_cart {
                 self.position.x += self.velocity.x;
             }
 
-            if self.distance > STAGE_GOAL {
+            if self.distance > _around.lap_length {
                 self.distance = 0.0;
+                self.laps += 1;
+            }
+
+            // Steer for the racing line, unless an upcoming wall or hazard
+            // has to be dodged first; a rival making a mistake holds its line
+            let _target_x = match _around.racing_line {
+                Some(line) if self.mistake == 0 => line.x_at(_course_y + STEER_LOOKAHEAD),
+                _ => self.position.x,
+            };
+            let _near = _around.wall_index.near(
+                _around.walls,
+                self.position.y,
+                self.position.y + self.reaction,
+            );
+            if !self.check_collision_and_adjust(&_near, _around.hazards, _target_x) {
+                self.steer(_target_x, _speed);
+            }
+
+            // Swerving from side to side while no wall is in the way
+            if self.swerve > 0 {
+                self.swerve -= 1;
+                if (self.swerve / SWERVE_FLIP) % 2 == 0 {
+                    self.velocity.x = EVASION_SPEED;
+                    self.direction = CarDirection::Right;
+                } else {
+                    self.velocity.x = -EVASION_SPEED;
+                    self.direction = CarDirection::Left;
+                }
             }
+        }
+
+        ///
+        /// Eases the sideways speed towards the racing line
+        ///
+        /// The rival heads straight for the line's point `STEER_LOOKAHEAD`
+        /// ahead, so it keeps to the line along a straight and cuts the
+        /// corners of a bend.
+        ///
+        /// # Arguments
+        /// * `_target_x` - Where the racing line is `STEER_LOOKAHEAD` ahead
+        /// * `_speed` - Speed per step of the rival along the course
+        fn steer(&mut self, _target_x: f32, _speed: f32) {
+            let _wanted = ((_target_x - self.position.x) * _speed / STEER_LOOKAHEAD)
+                .clamp(-STEER_MAX, STEER_MAX);
+            self.velocity.x += (_wanted - self.velocity.x).clamp(-STEER_RATE, STEER_RATE);
+            self.direction = if self.velocity.x < -TURNED_SPEED {
+                CarDirection::Left
+            } else if self.velocity.x > TURNED_SPEED {
+                CarDirection::Right
+            } else {
+                CarDirection::Normal
+            };
+        }
+
+        /// Starts a mistake: the rival lifts off and stops following the
+        /// racing line for a while
+        pub fn make_mistake(&mut self) {
+            self.mistake = MISTAKE_FRAMES;
+        }
 
-            // Check for upcoming walls and adjust path using rival's own distance
-            self.check_collision_and_adjust(_walls);
+        /// Whether the rival is in the middle of a mistake
+        pub fn is_making_mistake(&self) -> bool {
+            self.mistake > 0
         }
 
-        fn check_collision_and_adjust(&mut self, _walls: &[Wall]) {
+        /// Whether the rival has an item waiting to be used
+        pub fn holds_item(&self) -> bool {
+            self.item.is_some()
+        }
+
+        ///
+        /// Gives the rival an item from an item box
+        ///
+        /// # Arguments
+        /// * `_item` - Item taken
+        /// * `_hold` - Steps the rival keeps the item before using it
+        pub fn give_item(&mut self, _item: Item, _hold: u32) {
+            self.item = Some((_item, _hold));
+        }
+
+        ///
+        /// Counts down to using the held item, and uses it when the time comes
+        ///
+        /// A shield or speed burst takes effect on the rival at once; an oil
+        /// slick is left to the race to drop behind the rival.
+        ///
+        /// # Returns
+        /// The item used in this step, if any
+        pub fn use_item(&mut self) -> Option<Item> {
+            let (_item, _hold) = self.item?;
+            if _hold > 0 {
+                self.item = Some((_item, _hold - 1));
+                return None;
+            }
+            self.item = None;
+            match _item {
+                Item::Shield => self.shield = true,
+                Item::SpeedBurst => self.burst = BURST_FRAMES,
+                Item::OilSlick => {}
+            }
+            Some(_item)
+        }
+
+        /// Makes the rival swerve off an oil slick, unless a shield absorbs it
+        pub fn swerve(&mut self) {
+            if self.shield {
+                self.shield = false;
+            } else {
+                self.swerve = SWERVE_FRAMES;
+            }
+        }
+
+        ///
+        /// Dodges a wall or hazard right in front of the rival
+        ///
+        /// Probes run `reaction` ahead from both sides of the
+        /// rival. With one side blocked the rival dodges to the other; with
+        /// both blocked it dodges towards the racing line.
+        ///
+        /// # Arguments
+        /// * `_walls` - Walls near the probes
+        /// * `_hazards` - Hazards on the course
+        /// * `_target_x` - Where the rival is steering for the racing line
+        ///
+        /// # Returns
+        /// Whether the rival had to dodge
+        fn check_collision_and_adjust(
+            &mut self,
+            _walls: &[&Wall],
+            _hazards: &[Hazard],
+            _target_x: f32,
+        ) -> bool {
             let _center_x = self.position.x;
             let _y = self.position.y;
             let _left_x = _center_x - RIVAL_CART_WIDTH / 2.0 - 10.0;
@@ -61,46 +298,44 @@ pub mod rival_cart {
 
             // Check the intersection of the line connecting the center of the rival car
             let _left_point = Point::new(_left_x, _y);
-            let _left_ahead_point = Point::new(_left_x, _y + COLLISION_CHECK_DISTANCE);
+            let _left_ahead_point = Point::new(_left_x, _y + self.reaction);
             let _right_point = Point::new(_right_x, _y);
-            let _right_ahead_point = Point::new(_right_x, _y + COLLISION_CHECK_DISTANCE);
+            let _right_ahead_point = Point::new(_right_x, _y + self.reaction);
 
-            if !self.line_segments_intersect(_walls, _right_point, _right_ahead_point)
-                && !self.line_segments_intersect(_walls, _left_point, _left_ahead_point)
-            {
-                //1. If they don't intersect, go straight
-                self.velocity.x = 0.0;
-                self.direction = CarDirection::Normal;
-                return;
-            }
-            match self.no {
-                1 => {
-                    // 2. Checks whether the line connecting the left edge of the rival car
-                    if !self.line_segments_intersect(_walls, _left_point, _left_ahead_point) {
-                        // If they do not intersect, move left
-                        self.velocity.x = -EVASION_SPEED;
-                        self.direction = CarDirection::Left;
-                        return;
-                    }
-                    // 3.If the left edge also intersects, move to the right
-                    self.velocity.x = EVASION_SPEED;
-                    self.direction = CarDirection::Right;
-                }
-                _ => {
-                    if !self.line_segments_intersect(_walls, _right_point, _right_ahead_point) {
-                        // If they do not intersect, move right
-                        self.velocity.x = EVASION_SPEED;
-                        self.direction = CarDirection::Right;
-                        return;
-                    }
-                    // If they do not intersect, move left
-                    self.velocity.x = -EVASION_SPEED;
-                    self.direction = CarDirection::Left;
-                }
+            let _left_blocked = self.is_blocked(_walls, _hazards, _left_point, _left_ahead_point);
+            let _right_blocked =
+                self.is_blocked(_walls, _hazards, _right_point, _right_ahead_point);
+            let _go_left = match (_left_blocked, _right_blocked) {
+                // The way ahead is clear
+                (false, false) => return false,
+                (true, false) => false,
+                (false, true) => true,
+                // Towards the racing line, or by number when it is straight ahead
+                (true, true) if _target_x != _center_x => _target_x < _center_x,
+                (true, true) => self.no == 1,
+            };
+            if _go_left {
+                self.velocity.x = -EVASION_SPEED;
+                self.direction = CarDirection::Left;
+            } else {
+                self.velocity.x = EVASION_SPEED;
+                self.direction = CarDirection::Right;
             }
+            true
         }
 
-        fn line_segments_intersect(&self, _walls: &[Wall], _p: Point, _q: Point) -> bool {
+        /// Whether a wall crosses the probe from `_p` to `_q`, or a standing
+        /// hazard lies between the probe and the middle of the rival
+        fn is_blocked(&self, _walls: &[&Wall], _hazards: &[Hazard], _p: Point, _q: Point) -> bool {
+            let _left = _p.x.min(self.position.x);
+            let _right = _p.x.max(self.position.x);
+            self.line_segments_intersect(_walls, _p, _q)
+                || _hazards
+                    .iter()
+                    .any(|_hazard| _hazard.blocks(_left, _right, _p.y.min(_q.y), _p.y.max(_q.y)))
+        }
+
+        fn line_segments_intersect(&self, _walls: &[&Wall], _p: Point, _q: Point) -> bool {
             for _w in _walls {
                 if _w.p().y.min(_w.q().y) < _p.y && _w.p().y.min(_w.q().y) > _p.y {
                     return false;
@@ -128,16 +363,12 @@ pub mod rival_cart {
             self.position
         }
 
-        pub fn get_velocity(&self) -> Velocity {
-            self.velocity
-        }
-
         pub fn get_distance(&self) -> f32 {
             self.distance
         }
 
-        pub fn get_no(&self) -> usize {
-            self.no
+        pub fn get_laps(&self) -> i32 {
+            self.laps
         }
 
         pub fn check_collision_with_cart(&self, cart_position: Point) -> bool {
@@ -148,9 +379,12 @@ pub mod rival_cart {
             distance < collision_radius
         }
 
-        pub fn draw(&self, renderer: &Renderer) {
+        pub fn draw(&self, renderer: &dyn Renderer) {
             // Calculate draw position based on rival's position relative to player
             let _draw_position: Point = Point::new(self.position.x, self.position.y);
+            if self.shield {
+                draw_shield(renderer, _draw_position);
+            }
             // Only draw if rival is visible on screen
             match self.direction {
                 CarDirection::Left => {
@@ -165,4 +399,50 @@ pub mod rival_cart {
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::game::difficulty::difficulty::Level;
+        use crate::game::surface::surface::Surface;
+
+        /// Distance a rival at `speed` covers in 60 steps on a course
+        /// without walls, covered by `surfaces`
+        fn distance_covered(surfaces: &[SurfaceZone], speed: f32) -> f32 {
+            let _index = WallIndex::new(&[]);
+            let _around = Surroundings {
+                walls: &[],
+                wall_index: &_index,
+                cart_velocity: Velocity::default(),
+                lap_length: 10_000.0,
+                surfaces,
+                scroll: 0.0,
+                hazards: &[],
+                racing_line: None,
+            };
+            let mut _rival = RivalCart::new(
+                Point::new(400.0, 500.0),
+                speed,
+                0.0,
+                1,
+                Level::Normal.reaction(),
+            );
+            for _ in 0..60 {
+                _rival.update(&_around, 1.0);
+            }
+            _rival.distance
+        }
+
+        #[test]
+        fn surface_under_a_rival_scales_its_speed() {
+            let mud = SurfaceZone {
+                surface: Surface::Mud,
+                points: vec![[0.0, 0.0], [800.0, 0.0], [800.0, 5000.0], [0.0, 5000.0]],
+            };
+            assert_eq!(distance_covered(&[], 3.0), course_to_distance(180.0));
+            let _on_mud = distance_covered(&[mud], 3.0);
+            let _expected = course_to_distance(180.0) * Surface::Mud.handling().top_speed;
+            assert!((_on_mud - _expected).abs() < 1e-3, "{}", _on_mud);
+        }
+    }
 }
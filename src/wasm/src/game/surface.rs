@@ -0,0 +1,200 @@
+#[allow(clippy::module_inception)]
+pub mod surface {
+    //! Surfaces of the course.
+    //!
+    //! A track lists surface zones as polygons in course space. Anywhere no
+    //! zone covers is asphalt; where zones overlap the one listed last wins,
+    //! as it is also drawn on top.
+    use crate::engine::{Point, Renderer};
+    use crate::game::CANVAS_HEIGHT;
+    use crate::game::physics::physics::Handling;
+    use serde::{Deserialize, Serialize};
+
+    /// Opacity of the surface fills
+    const SURFACE_ALPHA: f64 = 0.4;
+
+    /// What the course is made of under a zone
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Surface {
+        Asphalt,
+        Grass,
+        Mud,
+        Ice,
+        Sand,
+    }
+    impl Surface {
+        /// How driving on the surface differs from asphalt
+        pub fn handling(&self) -> Handling {
+            match self {
+                Surface::Asphalt => Handling::NORMAL,
+                Surface::Grass => Handling {
+                    acceleration: 0.6,
+                    top_speed: 0.6,
+                    grip: 0.7,
+                    drag: 3.0,
+                },
+                Surface::Mud => Handling {
+                    acceleration: 0.4,
+                    top_speed: 0.4,
+                    grip: 0.8,
+                    drag: 6.0,
+                },
+                Surface::Ice => Handling {
+                    acceleration: 0.5,
+                    top_speed: 1.0,
+                    grip: 0.1,
+                    drag: 0.5,
+                },
+                Surface::Sand => Handling {
+                    acceleration: 0.3,
+                    top_speed: 0.35,
+                    grip: 0.6,
+                    drag: 8.0,
+                },
+            }
+        }
+        /// Fill colour of a zone of this surface
+        pub fn color(&self) -> &'static str {
+            match self {
+                Surface::Asphalt => "#666666",
+                Surface::Grass => "#4a9f4a",
+                Surface::Mud => "#6b4a2b",
+                Surface::Ice => "#9fd6ff",
+                Surface::Sand => "#e6cf8a",
+            }
+        }
+    }
+
+    /// A polygon of one surface
+    #[derive(Clone, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct SurfaceZone {
+        pub surface: Surface,
+        /// Corners as `[x, y]`, in order around the polygon
+        pub points: Vec<[f32; 2]>,
+    }
+    impl SurfaceZone {
+        /// Whether `point` lies inside the polygon
+        pub fn contains(&self, point: Point) -> bool {
+            let mut inside = false;
+            let mut j = self.points.len().wrapping_sub(1);
+            for (i, p) in self.points.iter().enumerate() {
+                let q = self.points[j];
+                // Even-odd rule: count edges crossed by a ray to the right
+                if (p[1] > point.y) != (q[1] > point.y)
+                    && point.x < p[0] + (point.y - p[1]) / (q[1] - p[1]) * (q[0] - p[0])
+                {
+                    inside = !inside;
+                }
+                j = i;
+            }
+            inside
+        }
+    }
+
+    ///
+    /// Finds the surface under a point
+    ///
+    /// # Arguments
+    /// * `zones` - Surface zones of the track
+    /// * `point` - A point in course space
+    ///
+    /// # Returns
+    /// The surface of the last zone containing `point`, or asphalt
+    pub fn surface_at(zones: &[SurfaceZone], point: Point) -> Surface {
+        zones
+            .iter()
+            .rev()
+            .find(|zone| zone.contains(point))
+            .map_or(Surface::Asphalt, |zone| zone.surface)
+    }
+
+    ///
+    /// Fills the surface zones on screen
+    ///
+    /// # Arguments
+    /// * `renderer` - Renderer to draw with
+    /// * `zones` - Surface zones of the track
+    /// * `scroll` - How far the course has scrolled down the screen
+    pub fn draw_surfaces(renderer: &dyn Renderer, zones: &[SurfaceZone], scroll: f32) {
+        renderer.set_alpha(SURFACE_ALPHA);
+        for zone in zones {
+            let points: Vec<Point> = zone
+                .points
+                .iter()
+                .map(|p| Point::new(p[0], p[1] - scroll))
+                .collect();
+            if points.iter().all(|p| p.y < 0.0) || points.iter().all(|p| p.y > CANVAS_HEIGHT) {
+                continue;
+            }
+            renderer.polygon(&points, zone.surface.color());
+        }
+        renderer.set_alpha(1.0);
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn zone(surface: Surface, points: &[[f32; 2]]) -> SurfaceZone {
+            SurfaceZone {
+                surface,
+                points: points.to_vec(),
+            }
+        }
+
+        /// A square of grass from 100 to 300, and a triangle of ice over
+        /// its right half
+        fn zones() -> Vec<SurfaceZone> {
+            vec![
+                zone(
+                    Surface::Grass,
+                    &[
+                        [100.0, 100.0],
+                        [300.0, 100.0],
+                        [300.0, 300.0],
+                        [100.0, 300.0],
+                    ],
+                ),
+                zone(
+                    Surface::Ice,
+                    &[[200.0, 100.0], [400.0, 200.0], [200.0, 300.0]],
+                ),
+            ]
+        }
+
+        #[test]
+        fn zone_contains_points_inside_its_polygon() {
+            let square = &zones()[0];
+            assert!(square.contains(Point::new(150.0, 150.0)));
+            assert!(!square.contains(Point::new(50.0, 150.0)));
+            assert!(!square.contains(Point::new(150.0, 350.0)));
+            let triangle = &zones()[1];
+            assert!(triangle.contains(Point::new(300.0, 200.0)));
+            // Inside the bounding box but outside the slanted edge
+            assert!(!triangle.contains(Point::new(350.0, 120.0)));
+        }
+
+        #[test]
+        fn zone_with_its_points_the_other_way_round_is_the_same() {
+            let mut square = zones().remove(0);
+            square.points.reverse();
+            assert!(square.contains(Point::new(150.0, 150.0)));
+            assert!(!square.contains(Point::new(50.0, 150.0)));
+        }
+
+        #[test]
+        fn surface_at_takes_the_last_zone_and_defaults_to_asphalt() {
+            let zones = zones();
+            assert_eq!(surface_at(&zones, Point::new(150.0, 200.0)), Surface::Grass);
+            assert_eq!(surface_at(&zones, Point::new(250.0, 200.0)), Surface::Ice);
+            assert_eq!(surface_at(&zones, Point::new(350.0, 200.0)), Surface::Ice);
+            assert_eq!(
+                surface_at(&zones, Point::new(500.0, 200.0)),
+                Surface::Asphalt
+            );
+            assert_eq!(surface_at(&[], Point::new(150.0, 200.0)), Surface::Asphalt);
+        }
+    }
+}
@@ -0,0 +1,519 @@
+#[allow(clippy::module_inception)]
+pub mod track {
+    //! Track file format.
+    //!
+    //! A track is a JSON document describing the course geometry and the
+    //! starting positions. All coordinates are in course space: `x` across the
+    //! canvas, `y` along the course, as they are when the race starts.
+    use crate::engine::Point;
+    use crate::game::hazard::hazard::HazardSpawn;
+    use crate::game::pad::pad::PadSpawn;
+    use crate::game::surface::surface::SurfaceZone;
+    use anyhow::{Context, Result, anyhow};
+    use serde::{Deserialize, Serialize};
+    use std::rc::Rc;
+
+    /* <-- CONSTANT VALUE */
+    /// Largest distance from the origin of any track coordinate; the course
+    /// is indexed and checked in cells along its whole extent
+    pub const MAX_COORDINATE: f32 = 50_000.0;
+    /// Longest lap distance a track may have
+    pub const MAX_LAP_LENGTH: f32 = 20_000.0;
+    /* CONSTANT VALUE --> */
+
+    /// Fruit hanging on a decoration tree
+    #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Fruit {
+        Apple,
+        Orange,
+        Cherry,
+        Lemon,
+        Plum,
+    }
+    impl Fruit {
+        /// Name understood by `Renderer::draw_fruit_tree`
+        pub fn name(&self) -> &'static str {
+            match self {
+                Fruit::Apple => "apple",
+                Fruit::Orange => "orange",
+                Fruit::Cherry => "cherry",
+                Fruit::Lemon => "lemon",
+                Fruit::Plum => "plum",
+            }
+        }
+    }
+
+    /// A fruit tree beside the course
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Decoration {
+        pub x: f32,
+        pub y: f32,
+        pub fruit: Fruit,
+    }
+
+    /// Starting position and behaviour of a rival cart
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct RivalSpawn {
+        pub x: f32,
+        pub y: f32,
+        pub speed: f32,
+        /// Rival number; when the racing line gives no side to dodge an
+        /// obstacle, rival 1 dodges left and the others right
+        pub no: usize,
+    }
+
+    /// A complete course.
+    #[derive(Clone, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Track {
+        pub name: String,
+        /// Distance driven to complete one lap
+        pub lap_length: f32,
+        /// Lap distances where sector split times are taken, ascending
+        #[serde(default)]
+        pub sectors: Vec<f32>,
+        /// Start position of the player's cart
+        pub start: Point,
+        /// Centre of the chequered finish banner
+        pub finish_line: Point,
+        /// Wall segments as `[x1, y1, x2, y2]`
+        pub walls: Vec<[f32; 4]>,
+        /// Checkpoint trigger lines as `[x1, y1, x2, y2]`, in driving order
+        #[serde(default)]
+        pub checkpoints: Vec<[f32; 4]>,
+        /// Seconds added to the race time for each respawn at a checkpoint
+        #[serde(default = "default_respawn_penalty")]
+        pub respawn_penalty: f32,
+        /// Zones of grass, mud, ice and sand; everywhere else is asphalt
+        #[serde(default)]
+        pub surfaces: Vec<SurfaceZone>,
+        /// Boost pads and slow strips
+        #[serde(default)]
+        pub pads: Vec<PadSpawn>,
+        /// Centres of the item boxes
+        #[serde(default)]
+        pub item_boxes: Vec<Point>,
+        /// Oil slicks, cones and barrels
+        #[serde(default)]
+        pub hazards: Vec<HazardSpawn>,
+        #[serde(default)]
+        pub decorations: Vec<Decoration>,
+        #[serde(default)]
+        pub rivals: Vec<RivalSpawn>,
+        /// Waypoints of the rivals' racing line, in driving order
+        #[serde(default)]
+        pub racing_line: Vec<Point>,
+    }
+    fn default_respawn_penalty() -> f32 {
+        3.0
+    }
+
+    impl Track {
+        ///
+        /// Parses and validates a track file
+        ///
+        /// # Arguments
+        /// * `json` - Contents of the track file
+        ///
+        /// # Returns
+        /// The track, or an error naming the line or entry at fault
+        pub fn from_json(json: &str) -> Result<Self> {
+            let track: Track = serde_json::from_str(json).context("Error parsing track")?;
+            track.validate()?;
+            Ok(track)
+        }
+
+        /// Serializes the track as a pretty-printed track file
+        pub fn to_json(&self) -> Result<String> {
+            serde_json::to_string_pretty(self).context("Error serializing track")
+        }
+
+        /// Checks the values serde cannot, naming every bad entry
+        // The negated comparisons also reject NaN
+        #[allow(clippy::neg_cmp_op_on_partial_ord)]
+        pub fn validate(&self) -> Result<()> {
+            let mut errors: Vec<String> = vec![];
+            if self.name.trim().is_empty() {
+                errors.push("name: must not be empty".into());
+            }
+            if !(self.lap_length > 0.0 && self.lap_length <= MAX_LAP_LENGTH) {
+                errors.push(format!(
+                    "lap_length: must be positive and at most {}, got {}",
+                    MAX_LAP_LENGTH, self.lap_length
+                ));
+            }
+            let mut previous = 0.0;
+            for (i, sector) in self.sectors.iter().enumerate() {
+                if !(*sector > previous && *sector < self.lap_length) {
+                    errors.push(format!(
+                        "sectors[{}]: {} must be above {} and below lap_length {}",
+                        i, sector, previous, self.lap_length
+                    ));
+                }
+                previous = *sector;
+            }
+            check_coordinates(&mut errors, "start", &[self.start.x, self.start.y]);
+            check_coordinates(
+                &mut errors,
+                "finish_line",
+                &[self.finish_line.x, self.finish_line.y],
+            );
+            if self.walls.is_empty() {
+                errors.push("walls: a track needs at least one wall".into());
+            }
+            for (i, wall) in self.walls.iter().enumerate() {
+                check_coordinates(&mut errors, &format!("walls[{}]", i), wall);
+            }
+            for (i, decoration) in self.decorations.iter().enumerate() {
+                check_coordinates(
+                    &mut errors,
+                    &format!("decorations[{}]", i),
+                    &[decoration.x, decoration.y],
+                );
+            }
+            for (i, c) in self.checkpoints.iter().enumerate() {
+                check_coordinates(&mut errors, &format!("checkpoints[{}]", i), c);
+                if c[0] == c[2] && c[1] == c[3] {
+                    errors.push(format!("checkpoints[{}]: must not have zero length", i));
+                }
+            }
+            for (i, zone) in self.surfaces.iter().enumerate() {
+                for (j, point) in zone.points.iter().enumerate() {
+                    check_coordinates(
+                        &mut errors,
+                        &format!("surfaces[{}].points[{}]", i, j),
+                        point,
+                    );
+                }
+                if zone.points.len() < 3 {
+                    errors.push(format!(
+                        "surfaces[{}]: a zone needs at least 3 points, got {}",
+                        i,
+                        zone.points.len()
+                    ));
+                }
+            }
+            for (i, pad) in self.pads.iter().enumerate() {
+                check_coordinates(&mut errors, &format!("pads[{}]", i), &[pad.x, pad.y]);
+            }
+            for (i, item_box) in self.item_boxes.iter().enumerate() {
+                check_coordinates(
+                    &mut errors,
+                    &format!("item_boxes[{}]", i),
+                    &[item_box.x, item_box.y],
+                );
+            }
+            for (i, hazard) in self.hazards.iter().enumerate() {
+                check_coordinates(
+                    &mut errors,
+                    &format!("hazards[{}]", i),
+                    &[hazard.x, hazard.y],
+                );
+            }
+            if !(self.respawn_penalty >= 0.0) {
+                errors.push(format!(
+                    "respawn_penalty: must not be negative, got {}",
+                    self.respawn_penalty
+                ));
+            }
+            for (i, rival) in self.rivals.iter().enumerate() {
+                check_coordinates(&mut errors, &format!("rivals[{}]", i), &[rival.x, rival.y]);
+                if !(rival.speed >= 0.0 && rival.speed.is_finite()) {
+                    errors.push(format!(
+                        "rivals[{}]: speed must be finite and not negative, got {}",
+                        i, rival.speed
+                    ));
+                }
+            }
+            for (i, point) in self.racing_line.iter().enumerate() {
+                check_coordinates(
+                    &mut errors,
+                    &format!("racing_line[{}]", i),
+                    &[point.x, point.y],
+                );
+            }
+            for (i, pair) in self.racing_line.windows(2).enumerate() {
+                if !(pair[1].y > pair[0].y) {
+                    errors.push(format!(
+                        "racing_line[{}]: y {} must be above the previous waypoint's {}",
+                        i + 1,
+                        pair[1].y,
+                        pair[0].y
+                    ));
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Invalid track '{}':\n  {}",
+                    self.name,
+                    errors.join("\n  ")
+                ))
+            }
+        }
+
+        /// Wall segments as pairs of end points
+        pub fn wall_lines(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+            self.walls
+                .iter()
+                .map(|w| (Point::new(w[0], w[1]), Point::new(w[2], w[3])))
+        }
+
+        /// Checkpoint lines as pairs of end points, in driving order
+        pub fn checkpoint_lines(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+            self.checkpoints
+                .iter()
+                .map(|c| (Point::new(c[0], c[1]), Point::new(c[2], c[3])))
+        }
+    }
+
+    /// Adds an error for `field` unless every value is a finite coordinate
+    /// within `MAX_COORDINATE` of the origin
+    // The negated comparison also rejects NaN
+    #[allow(clippy::neg_cmp_op_on_partial_ord)]
+    fn check_coordinates(errors: &mut Vec<String>, field: &str, values: &[f32]) {
+        if values.iter().any(|value| !(value.abs() <= MAX_COORDINATE)) {
+            errors.push(format!(
+                "{}: {:?} must be finite and within {} of the origin",
+                field, values, MAX_COORDINATE
+            ));
+        }
+    }
+
+    /// Every course the player can choose from, and the one chosen.
+    pub struct TrackRegistry {
+        tracks: Vec<Rc<Track>>,
+        /// Whether each track came from the editor rather than a track file
+        edited: Vec<bool>,
+        selected: usize,
+    }
+    impl TrackRegistry {
+        ///
+        /// Creates a registry with the first track selected
+        ///
+        /// # Arguments
+        /// * `tracks` - Courses in the order they are offered
+        pub fn new(tracks: Vec<Track>) -> Result<Self> {
+            if tracks.is_empty() {
+                return Err(anyhow!("No tracks to choose from"));
+            }
+            Ok(TrackRegistry {
+                edited: vec![false; tracks.len()],
+                tracks: tracks.into_iter().map(Rc::new).collect(),
+                selected: 0,
+            })
+        }
+        /// The chosen track
+        pub fn selected(&self) -> Rc<Track> {
+            self.tracks[self.selected].clone()
+        }
+        /// Whether the chosen track was put in by `insert` from the editor
+        pub fn is_selected_edited(&self) -> bool {
+            self.edited[self.selected]
+        }
+        /// Position of the chosen track, counted from 0
+        pub fn selected_index(&self) -> usize {
+            self.selected
+        }
+        /// Number of tracks
+        pub fn len(&self) -> usize {
+            self.tracks.len()
+        }
+        /// Chooses the following track, wrapping around to the first
+        pub fn select_next(&mut self) {
+            self.selected = (self.selected + 1) % self.tracks.len();
+        }
+        /// Chooses the preceding track, wrapping around to the last
+        pub fn select_previous(&mut self) {
+            self.selected = (self.selected + self.tracks.len() - 1) % self.tracks.len();
+        }
+        /// Replaces the track of the same name, or adds `track`, and chooses it;
+        /// the track counts as edited from then on
+        pub fn insert(&mut self, track: Track) {
+            let track = Rc::new(track);
+            match self.tracks.iter().position(|t| t.name == track.name) {
+                Some(index) => {
+                    self.tracks[index] = track;
+                    self.edited[index] = true;
+                    self.selected = index;
+                }
+                None => {
+                    self.tracks.push(track);
+                    self.edited.push(true);
+                    self.selected = self.tracks.len() - 1;
+                }
+            }
+        }
+        /// Chooses the track called `name`; returns false when there is none
+        pub fn select(&mut self, name: &str) -> bool {
+            match self.tracks.iter().position(|track| track.name == name) {
+                Some(index) => {
+                    self.selected = index;
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const DEFAULT_TRACK: &str = include_str!("../../../../cart/assets/tracks/default.json");
+
+        /// The default track file with `from` replaced by `to`
+        fn edited(from: &str, to: &str) -> String {
+            assert!(
+                DEFAULT_TRACK.contains(from),
+                "{} not in the track file",
+                from
+            );
+            DEFAULT_TRACK.replacen(from, to, 1)
+        }
+
+        fn error_of(json: &str) -> String {
+            format!(
+                "{:#}",
+                Track::from_json(json).err().expect("track was accepted")
+            )
+        }
+
+        #[test]
+        fn default_track_is_valid() {
+            let track = Track::from_json(DEFAULT_TRACK).unwrap();
+            assert_eq!(track.name, "default");
+            assert_eq!(track.start, Point::new(400.0, 100.0));
+        }
+
+        #[test]
+        fn missing_field_is_named() {
+            let json = edited("\"lap_length\"", "\"lap_distance\"");
+            let error = error_of(&json);
+            assert!(error.starts_with("Error parsing track: "), "{}", error);
+            assert!(error.contains("lap_distance"), "{}", error);
+        }
+
+        #[test]
+        fn bad_start_is_named() {
+            let json = edited("\"start\": { \"x\": 400.0", "\"start\": { \"x\": 1e39");
+            assert_eq!(
+                error_of(&json),
+                format!(
+                    "Invalid track 'default':\n  start: [inf, 100.0] must be finite and within {} of the origin",
+                    MAX_COORDINATE
+                )
+            );
+        }
+
+        #[test]
+        fn empty_walls_are_rejected() {
+            let mut track = Track::from_json(DEFAULT_TRACK).unwrap();
+            track.walls.clear();
+            assert_eq!(
+                format!("{:#}", track.validate().unwrap_err()),
+                "Invalid track 'default':\n  walls: a track needs at least one wall"
+            );
+        }
+
+        #[test]
+        fn every_bad_value_is_listed() {
+            let mut track = Track::from_json(DEFAULT_TRACK).unwrap();
+            track.sectors.clear();
+            track.lap_length = f32::NAN;
+            track.walls[1][3] = 1_000_000.0;
+            track.rivals[0].speed = f32::INFINITY;
+            let error = format!("{:#}", track.validate().unwrap_err());
+            let lines: Vec<&str> = error.lines().skip(1).map(str::trim).collect();
+            assert_eq!(lines.len(), 3, "{}", error);
+            assert!(lines[0].starts_with("lap_length: "), "{}", error);
+            assert!(lines[1].starts_with("walls[1]: "), "{}", error);
+            assert!(lines[2].starts_with("rivals[0]: speed "), "{}", error);
+        }
+
+        #[test]
+        fn huge_lap_is_rejected() {
+            let mut track = Track::from_json(DEFAULT_TRACK).unwrap();
+            track.lap_length = MAX_LAP_LENGTH;
+            assert!(track.validate().is_ok());
+            track.lap_length = MAX_LAP_LENGTH * 1000.0;
+            assert!(track.validate().is_err());
+        }
+
+        /// A registry of copies of the default track named `names`
+        fn registry(names: &[&str]) -> TrackRegistry {
+            let track = Track::from_json(DEFAULT_TRACK).unwrap();
+            TrackRegistry::new(
+                names
+                    .iter()
+                    .map(|name| Track {
+                        name: name.to_string(),
+                        ..track.clone()
+                    })
+                    .collect(),
+            )
+            .unwrap()
+        }
+
+        #[test]
+        fn empty_registry_is_rejected() {
+            assert!(TrackRegistry::new(vec![]).is_err());
+        }
+
+        #[test]
+        fn next_and_previous_wrap_around() {
+            let mut tracks = registry(&["a", "b", "c"]);
+            assert_eq!(tracks.selected().name, "a");
+            tracks.select_previous();
+            assert_eq!(tracks.selected_index(), 2);
+            assert_eq!(tracks.selected().name, "c");
+            tracks.select_next();
+            assert_eq!(tracks.selected().name, "a");
+            tracks.select_next();
+            tracks.select_next();
+            tracks.select_next();
+            assert_eq!(tracks.selected().name, "a");
+        }
+
+        #[test]
+        fn select_finds_a_track_by_name() {
+            let mut tracks = registry(&["a", "b", "c"]);
+            assert!(tracks.select("b"));
+            assert_eq!(tracks.selected_index(), 1);
+            assert!(!tracks.select("missing"));
+            assert_eq!(tracks.selected().name, "b");
+        }
+
+        #[test]
+        fn insert_replaces_a_track_of_the_same_name() {
+            let mut tracks = registry(&["a", "b"]);
+            let mut edited = (*tracks.selected()).clone();
+            edited.lap_length = 1000.0;
+            edited.name = "b".to_string();
+            tracks.insert(edited.clone());
+            assert_eq!(tracks.len(), 2);
+            assert_eq!(tracks.selected_index(), 1);
+            assert_eq!(tracks.selected().lap_length, 1000.0);
+            assert!(tracks.is_selected_edited());
+            tracks.select_next();
+            assert!(!tracks.is_selected_edited());
+
+            edited.name = "c".to_string();
+            tracks.insert(edited);
+            assert_eq!(tracks.len(), 3);
+            assert_eq!(tracks.selected().name, "c");
+            assert!(tracks.is_selected_edited());
+        }
+
+        #[test]
+        fn selected_track_is_shared() {
+            let tracks = registry(&["a", "b"]);
+            assert!(Rc::ptr_eq(&tracks.selected(), &tracks.selected()));
+            assert_eq!(tracks.len(), 2);
+        }
+    }
+}
@@ -0,0 +1,725 @@
+#[allow(clippy::module_inception)]
+pub mod track_check {
+    //! Offline checks of a track file.
+    //!
+    //! Beyond what `Track::validate` enforces, the course is rasterised into
+    //! cells of `CELL` units and flood-filled from the cart's start twice: once
+    //! for a point, to find the open course, and once for a cart as wide as
+    //! `CART_WIDTH` allows, to find where the cart can really go.
+    use crate::engine::Point;
+    use crate::game::CANVAS_WIDTH;
+    use crate::game::cart::cart::CART_WIDTH;
+    use crate::game::track::track::Track;
+    use std::collections::VecDeque;
+    use std::fmt;
+
+    /* <-- CONSTANT VALUE */
+    /// Grid resolution of the reachability checks
+    const CELL: f32 = 10.0;
+    /// Open wall ends closer than this are reported as a gap
+    const GAP_DISTANCE: f32 = 100.0;
+    /// A wall end this close to another wall is joined to it
+    const JOIN_DISTANCE: f32 = 0.01;
+    /* CONSTANT VALUE --> */
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Severity {
+        /// The track must not ship
+        Error,
+        /// Probably a mistake, but the track can be raced
+        Warning,
+    }
+
+    /// One problem found in a track.
+    #[derive(Clone, Debug)]
+    pub struct Issue {
+        pub severity: Severity,
+        pub message: String,
+    }
+    impl Issue {
+        fn error(message: String) -> Self {
+            Issue {
+                severity: Severity::Error,
+                message,
+            }
+        }
+        fn warning(message: String) -> Self {
+            Issue {
+                severity: Severity::Warning,
+                message,
+            }
+        }
+    }
+    impl fmt::Display for Issue {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.severity {
+                Severity::Error => write!(f, "error: {}", self.message),
+                Severity::Warning => write!(f, "warning: {}", self.message),
+            }
+        }
+    }
+
+    ///
+    /// Checks the contents of a track file
+    ///
+    /// # Arguments
+    /// * `json` - Contents of the track file
+    ///
+    /// # Returns
+    /// Every problem found; a file that cannot be loaded gives a single error
+    pub fn check_track_file(json: &str) -> Vec<Issue> {
+        match Track::from_json(json) {
+            Ok(track) => check_track(&track),
+            Err(err) => vec![Issue::error(format!("{:#}", err))],
+        }
+    }
+
+    /// Checks the wall geometry of a loaded track
+    pub fn check_track(track: &Track) -> Vec<Issue> {
+        let walls: Vec<(Point, Point)> = track.wall_lines().collect();
+        let mut issues = vec![];
+        check_segments(&walls, &mut issues);
+        check_open_ends(&walls, &mut issues);
+
+        let course = Grid::new(&walls, CELL / 2.0);
+        let cart = Grid::new(&walls, CART_WIDTH);
+        let Some(start) = course.cell(track.start) else {
+            issues.push(Issue::error(format!(
+                "start {} lies outside the walls",
+                describe(track.start)
+            )));
+            return issues;
+        };
+        let open = course.flood(start);
+        check_corridors(&walls, &course, &open, &mut issues);
+        check_placements(track, &course, &open, &mut issues);
+
+        if !cart.free[start] {
+            issues.push(Issue::error(format!(
+                "the cart does not fit at the start {}",
+                describe(track.start)
+            )));
+            return issues;
+        }
+        let drivable = cart.flood(start);
+        let Some(goal) = cart.row(track.finish_line.y) else {
+            issues.push(Issue::error(format!(
+                "goal {} lies outside the walls",
+                describe(track.finish_line)
+            )));
+            return issues;
+        };
+        if !(0..cart.columns).any(|column| drivable[goal * cart.columns + column]) {
+            issues.push(Issue::error(format!(
+                "no route for the cart from start {} to goal {}",
+                describe(track.start),
+                describe(track.finish_line)
+            )));
+            // Everything past the blockage would be reported as unreachable
+            return issues;
+        }
+        check_islands(&walls, &course, &open, &mut issues);
+        issues
+    }
+
+    fn check_segments(walls: &[(Point, Point)], issues: &mut Vec<Issue>) {
+        for (i, (p, q)) in walls.iter().enumerate() {
+            if p == q {
+                issues.push(Issue::error(format!(
+                    "walls[{}]: zero-length segment at {}",
+                    i,
+                    describe(*p)
+                )));
+            }
+        }
+    }
+
+    /// Reports wall ends that join no other wall, other than at the ends of the course
+    fn check_open_ends(walls: &[(Point, Point)], issues: &mut Vec<Issue>) {
+        let (bottom, top) = y_range(walls);
+        let open: Vec<(usize, Point)> = walls
+            .iter()
+            .enumerate()
+            .filter(|(_, (p, q))| p != q)
+            .flat_map(|(i, (p, q))| [(i, *p), (i, *q)])
+            .filter(|(i, end)| {
+                !walls
+                    .iter()
+                    .enumerate()
+                    .any(|(j, wall)| *i != j && segment_distance(*end, *wall) < JOIN_DISTANCE)
+            })
+            .filter(|(_, end)| end.y != bottom && end.y != top)
+            .collect();
+        let mut reported = vec![false; open.len()];
+        for a in 0..open.len() {
+            if reported[a] {
+                continue;
+            }
+            let nearest = (0..open.len())
+                // Both ends of one short wall are not a gap
+                .filter(|&b| b != a && open[b].0 != open[a].0 && !reported[b])
+                .map(|b| (b, distance(open[a].1, open[b].1)))
+                .filter(|(_, gap)| *gap < GAP_DISTANCE)
+                .min_by(|x, y| x.1.total_cmp(&y.1));
+            reported[a] = true;
+            match nearest {
+                Some((b, gap)) => {
+                    reported[b] = true;
+                    issues.push(Issue::error(format!(
+                        "gap of {:.1} between walls[{}] at {} and walls[{}] at {}",
+                        gap,
+                        open[a].0,
+                        describe(open[a].1),
+                        open[b].0,
+                        describe(open[b].1)
+                    )));
+                }
+                None => issues.push(Issue::warning(format!(
+                    "walls[{}] ends in the open at {}",
+                    open[a].0,
+                    describe(open[a].1)
+                ))),
+            }
+        }
+    }
+
+    /// Reports stretches of the open course narrower than the cart
+    fn check_corridors(
+        walls: &[(Point, Point)],
+        grid: &Grid,
+        open: &[bool],
+        issues: &mut Vec<Issue>,
+    ) {
+        let needed = CART_WIDTH * 2.0;
+        // (first y, last y, narrowest width)
+        let mut narrow: Option<(f32, f32, f32)> = None;
+        for row in 0..grid.rows {
+            let y = grid.y(row);
+            let mut crossings: Vec<f32> = walls
+                .iter()
+                .filter(|(p, q)| p.y.min(q.y) <= y && p.y.max(q.y) >= y && p.y != q.y)
+                .map(|(p, q)| p.x + (q.x - p.x) * (y - p.y) / (q.y - p.y))
+                .collect();
+            crossings.sort_by(|a, b| a.total_cmp(b));
+            let mut width: Option<f32> = None;
+            let mut column = 0;
+            while column < grid.columns {
+                if !open[row * grid.columns + column] {
+                    column += 1;
+                    continue;
+                }
+                let first = column;
+                while column < grid.columns && open[row * grid.columns + column] {
+                    column += 1;
+                }
+                let left = crossings
+                    .iter()
+                    .rev()
+                    .find(|x| **x <= grid.x(first))
+                    .copied()
+                    .unwrap_or(0.0);
+                let right = crossings
+                    .iter()
+                    .find(|x| **x >= grid.x(column - 1))
+                    .copied()
+                    .unwrap_or(CANVAS_WIDTH);
+                let run = right - left;
+                if run < needed {
+                    width = Some(width.map_or(run, |w: f32| w.min(run)));
+                }
+            }
+            narrow = match (narrow, width) {
+                (Some((from, _, least)), Some(w)) => Some((from, y, least.min(w))),
+                (None, Some(w)) => Some((y, y, w)),
+                (Some(stretch), None) => {
+                    report_narrow(stretch, needed, issues);
+                    None
+                }
+                (None, None) => None,
+            };
+        }
+        if let Some(stretch) = narrow {
+            report_narrow(stretch, needed, issues);
+        }
+    }
+
+    fn report_narrow((from, to, least): (f32, f32, f32), needed: f32, issues: &mut Vec<Issue>) {
+        issues.push(Issue::error(format!(
+            "corridor only {:.1} wide between y {:.0} and {:.0} (the cart needs {:.0})",
+            least, from, to, needed
+        )));
+    }
+
+    /// Reports pads, item boxes and hazards whose centre is off the open
+    /// course, and racing line waypoints off it
+    fn check_placements(track: &Track, grid: &Grid, open: &[bool], issues: &mut Vec<Issue>) {
+        let pads = track
+            .pads
+            .iter()
+            .enumerate()
+            .map(|(i, pad)| ("pads", i, Point::new(pad.x, pad.y)));
+        let item_boxes = track
+            .item_boxes
+            .iter()
+            .enumerate()
+            .map(|(i, centre)| ("item_boxes", i, *centre));
+        let hazards = track
+            .hazards
+            .iter()
+            .enumerate()
+            .map(|(i, hazard)| ("hazards", i, Point::new(hazard.x, hazard.y)));
+        let racing_line = track
+            .racing_line
+            .iter()
+            .enumerate()
+            .map(|(i, point)| ("racing_line", i, *point));
+        for (list, i, centre) in pads.chain(item_boxes).chain(hazards).chain(racing_line) {
+            if !grid.cell(centre).is_some_and(|cell| open[cell]) {
+                issues.push(Issue::warning(format!(
+                    "{}[{}]: {} lies off the course",
+                    list,
+                    i,
+                    describe(centre)
+                )));
+            }
+        }
+    }
+
+    /// Reports groups of joined walls that never border the open course
+    fn check_islands(
+        walls: &[(Point, Point)],
+        grid: &Grid,
+        open: &[bool],
+        issues: &mut Vec<Issue>,
+    ) {
+        let mut group: Vec<usize> = (0..walls.len()).collect();
+        fn root(group: &mut [usize], i: usize) -> usize {
+            let mut i = i;
+            while group[i] != i {
+                group[i] = group[group[i]];
+                i = group[i];
+            }
+            i
+        }
+        for i in 0..walls.len() {
+            for j in i + 1..walls.len() {
+                let (a, b) = (walls[i], walls[j]);
+                let joined = [a.0, a.1]
+                    .iter()
+                    .any(|end| segment_distance(*end, b) < JOIN_DISTANCE)
+                    || [b.0, b.1]
+                        .iter()
+                        .any(|end| segment_distance(*end, a) < JOIN_DISTANCE);
+                if joined {
+                    let (ri, rj) = (root(&mut group, i), root(&mut group, j));
+                    group[ri] = rj;
+                }
+            }
+        }
+        let touches_course = |(p, q): (Point, Point)| {
+            let steps = (distance(p, q) / (CELL / 2.0)).ceil().max(1.0) as usize;
+            (0..=steps).any(|s| {
+                let t = s as f32 / steps as f32;
+                let point = Point::new(p.x + (q.x - p.x) * t, p.y + (q.y - p.y) * t);
+                grid.neighbourhood(point).any(|cell| open[cell])
+            })
+        };
+        let mut reachable = vec![false; walls.len()];
+        for (i, wall) in walls.iter().enumerate() {
+            if touches_course(*wall) {
+                let r = root(&mut group, i);
+                reachable[r] = true;
+            }
+        }
+        let mut reported = vec![false; walls.len()];
+        for i in 0..walls.len() {
+            let r = root(&mut group, i);
+            if reachable[r] || reported[r] {
+                continue;
+            }
+            reported[r] = true;
+            let members: Vec<String> = (0..walls.len())
+                .filter(|&j| root(&mut group, j) == r)
+                .map(|j| j.to_string())
+                .collect();
+            issues.push(Issue::warning(format!(
+                "walls[{}] form an island the cart can never reach",
+                members.join(", ")
+            )));
+        }
+    }
+
+    /// Cells of the course a shape of the given half width fits in.
+    struct Grid {
+        columns: usize,
+        rows: usize,
+        bottom: f32,
+        free: Vec<bool>,
+    }
+    impl Grid {
+        ///
+        /// Rasterises the course between the lowest and highest wall ends
+        ///
+        /// # Arguments
+        /// * `walls` - Wall segments
+        /// * `half_width` - Half the width of what moves through the course
+        fn new(walls: &[(Point, Point)], half_width: f32) -> Self {
+            let (bottom, top) = y_range(walls);
+            let columns = (CANVAS_WIDTH / CELL).ceil() as usize;
+            let rows = ((top - bottom) / CELL).ceil().max(1.0) as usize;
+            let mut free = vec![false; columns * rows];
+            for row in 0..rows {
+                let y = bottom + (row as f32 + 0.5) * CELL;
+                let (low, high) = (y - CELL / 2.0, y + CELL / 2.0);
+                let near: Vec<&(Point, Point)> = walls
+                    .iter()
+                    .filter(|(p, q)| p.y.min(q.y) <= high && p.y.max(q.y) >= low)
+                    .collect();
+                for column in 0..columns {
+                    let x = (column as f32 + 0.5) * CELL;
+                    let (left, right) = (x - half_width, x + half_width);
+                    free[row * columns + column] = left >= 0.0
+                        && right <= CANVAS_WIDTH
+                        && !near.iter().any(|(p, q)| {
+                            segment_hits_rect(
+                                *p,
+                                *q,
+                                Point::new(left, low),
+                                Point::new(right, high),
+                            )
+                        });
+                }
+            }
+            Grid {
+                columns,
+                rows,
+                bottom,
+                free,
+            }
+        }
+
+        fn x(&self, column: usize) -> f32 {
+            (column as f32 + 0.5) * CELL
+        }
+
+        fn y(&self, row: usize) -> f32 {
+            self.bottom + (row as f32 + 0.5) * CELL
+        }
+
+        fn row(&self, y: f32) -> Option<usize> {
+            let row = ((y - self.bottom) / CELL).floor();
+            (row >= 0.0 && (row as usize) < self.rows).then_some(row as usize)
+        }
+
+        /// Index of the cell containing `point`
+        fn cell(&self, point: Point) -> Option<usize> {
+            let column = (point.x / CELL).floor();
+            if column < 0.0 || column as usize >= self.columns {
+                return None;
+            }
+            self.row(point.y)
+                .map(|row| row * self.columns + column as usize)
+        }
+
+        /// The cells up to two away from the one containing `point`; a wall
+        /// on a cell border closes the cells on both sides of it
+        fn neighbourhood(&self, point: Point) -> impl Iterator<Item = usize> + '_ {
+            const STEPS: [f32; 5] = [-2.0, -1.0, 0.0, 1.0, 2.0];
+            STEPS.into_iter().flat_map(move |dy| {
+                STEPS.into_iter().filter_map(move |dx| {
+                    self.cell(Point::new(point.x + dx * CELL, point.y + dy * CELL))
+                })
+            })
+        }
+
+        /// Free cells connected to `start` through free cells
+        fn flood(&self, start: usize) -> Vec<bool> {
+            let mut reached = vec![false; self.free.len()];
+            if !self.free[start] {
+                return reached;
+            }
+            let mut queue = VecDeque::from([start]);
+            reached[start] = true;
+            while let Some(cell) = queue.pop_front() {
+                let (row, column) = (cell / self.columns, cell % self.columns);
+                let mut next = vec![];
+                if column > 0 {
+                    next.push(cell - 1);
+                }
+                if column + 1 < self.columns {
+                    next.push(cell + 1);
+                }
+                if row > 0 {
+                    next.push(cell - self.columns);
+                }
+                if row + 1 < self.rows {
+                    next.push(cell + self.columns);
+                }
+                for n in next {
+                    if self.free[n] && !reached[n] {
+                        reached[n] = true;
+                        queue.push_back(n);
+                    }
+                }
+            }
+            reached
+        }
+    }
+
+    fn y_range(walls: &[(Point, Point)]) -> (f32, f32) {
+        walls
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(low, high), (p, q)| {
+                (low.min(p.y).min(q.y), high.max(p.y).max(q.y))
+            })
+    }
+
+    /// Whether any part of segment `p`-`q` lies in the rectangle from `min` to `max`
+    fn segment_hits_rect(p: Point, q: Point, min: Point, max: Point) -> bool {
+        // Liang-Barsky clipping
+        let (dx, dy) = (q.x - p.x, q.y - p.y);
+        let mut t0: f32 = 0.0;
+        let mut t1: f32 = 1.0;
+        for (step, room) in [
+            (-dx, p.x - min.x),
+            (dx, max.x - p.x),
+            (-dy, p.y - min.y),
+            (dy, max.y - p.y),
+        ] {
+            if step == 0.0 {
+                if room < 0.0 {
+                    return false;
+                }
+            } else {
+                let t = room / step;
+                if step < 0.0 {
+                    t0 = t0.max(t);
+                } else {
+                    t1 = t1.min(t);
+                }
+            }
+        }
+        t0 <= t1
+    }
+
+    fn distance(a: Point, b: Point) -> f32 {
+        ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+    }
+
+    /// Distance from `point` to the segment `wall`
+    fn segment_distance(point: Point, (a, b): (Point, Point)) -> f32 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let length = dx * dx + dy * dy;
+        if length == 0.0 {
+            return distance(point, a);
+        }
+        let t = (((point.x - a.x) * dx + (point.y - a.y) * dy) / length).clamp(0.0, 1.0);
+        distance(point, Point::new(a.x + t * dx, a.y + t * dy))
+    }
+
+    fn describe(point: Point) -> String {
+        format!("({}, {})", point.x, point.y)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::game::pad::pad::{PadKind, PadSpawn};
+
+        /// A track from start (400, 100) to the goal at (400, 900) between
+        /// `walls`, given as the JSON array of the track file
+        fn track(walls: &str) -> Track {
+            Track::from_json(&format!(
+                r#"{{
+                    "name": "test",
+                    "lap_length": 400.0,
+                    "start": {{ "x": 400.0, "y": 100.0 }},
+                    "finish_line": {{ "x": 400.0, "y": 900.0 }},
+                    "walls": {}
+                }}"#,
+                walls
+            ))
+            .unwrap()
+        }
+
+        /// The messages of the issues of `severity`
+        fn messages(issues: &[Issue], severity: Severity) -> Vec<String> {
+            issues
+                .iter()
+                .filter(|issue| issue.severity == severity)
+                .map(|issue| issue.message.clone())
+                .collect()
+        }
+
+        #[test]
+        fn shipped_tracks_have_no_errors() {
+            for json in [
+                include_str!("../../../../cart/assets/tracks/default.json"),
+                include_str!("../../../../cart/assets/tracks/mirror.json"),
+            ] {
+                let issues = check_track_file(json);
+                assert_eq!(messages(&issues, Severity::Error), Vec::<String>::new());
+            }
+        }
+
+        #[test]
+        fn straight_corridor_is_clean() {
+            let issues = check_track(&track("[[200, 0, 200, 1000], [600, 0, 600, 1000]]"));
+            assert!(issues.is_empty(), "{:?}", issues);
+        }
+
+        #[test]
+        fn pad_off_the_course_is_a_warning() {
+            let mut track = track("[[200, 0, 200, 1000], [600, 0, 600, 1000]]");
+            track.pads = vec![
+                PadSpawn {
+                    kind: PadKind::Boost,
+                    x: 400.0,
+                    y: 500.0,
+                },
+                PadSpawn {
+                    kind: PadKind::Slow,
+                    x: 100.0,
+                    y: 500.0,
+                },
+            ];
+            let issues = check_track(&track);
+            assert_eq!(
+                messages(&issues, Severity::Warning),
+                ["pads[1]: (100, 500) lies off the course"]
+            );
+            assert!(messages(&issues, Severity::Error).is_empty());
+        }
+
+        #[test]
+        fn item_box_off_the_course_is_a_warning() {
+            let mut track = track("[[200, 0, 200, 1000], [600, 0, 600, 1000]]");
+            track.item_boxes = vec![Point::new(700.0, 300.0), Point::new(400.0, 300.0)];
+            let issues = check_track(&track);
+            assert_eq!(
+                messages(&issues, Severity::Warning),
+                ["item_boxes[0]: (700, 300) lies off the course"]
+            );
+            assert!(messages(&issues, Severity::Error).is_empty());
+        }
+
+        #[test]
+        fn unloadable_file_is_one_error() {
+            let issues = check_track_file("{}");
+            assert_eq!(issues.len(), 1);
+            assert_eq!(issues[0].severity, Severity::Error);
+        }
+
+        #[test]
+        fn zero_length_segment_is_an_error() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 500], [200, 500, 200, 500], [200, 500, 200, 1000], [600, 0, 600, 1000]]",
+            ));
+            assert_eq!(
+                messages(&issues, Severity::Error),
+                ["walls[1]: zero-length segment at (200, 500)"]
+            );
+        }
+
+        #[test]
+        fn gap_between_wall_ends_is_reported_once() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 500], [200, 520, 200, 1000], [600, 0, 600, 1000]]",
+            ));
+            assert_eq!(
+                messages(&issues, Severity::Error),
+                ["gap of 20.0 between walls[0] at (200, 500) and walls[1] at (200, 520)"]
+            );
+        }
+
+        #[test]
+        fn short_freestanding_wall_is_not_a_gap_with_itself() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 1000], [600, 0, 600, 1000], [380, 500, 420, 500]]",
+            ));
+            assert_eq!(messages(&issues, Severity::Error), Vec::<String>::new());
+            assert_eq!(
+                messages(&issues, Severity::Warning),
+                [
+                    "walls[2] ends in the open at (380, 500)",
+                    "walls[2] ends in the open at (420, 500)"
+                ]
+            );
+        }
+
+        #[test]
+        fn far_open_end_is_a_warning() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 1000], [600, 0, 600, 1000], [200, 400, 300, 400]]",
+            ));
+            assert_eq!(
+                messages(&issues, Severity::Warning),
+                ["walls[2] ends in the open at (300, 400)"]
+            );
+        }
+
+        #[test]
+        fn narrow_corridor_is_reported_with_its_extent() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 1000], [600, 0, 600, 400], [600, 400, 230, 400], \
+                 [230, 400, 230, 500], [230, 500, 600, 500], [600, 500, 600, 1000]]",
+            ));
+            let errors = messages(&issues, Severity::Error);
+            assert!(
+                errors[0].starts_with("corridor only 30.0 wide between y 4"),
+                "{:?}",
+                errors
+            );
+            assert!(errors[0].ends_with("(the cart needs 40)"), "{:?}", errors);
+            assert!(
+                errors[1].starts_with("no route for the cart"),
+                "{:?}",
+                errors
+            );
+            assert_eq!(errors.len(), 2);
+        }
+
+        #[test]
+        fn wide_corridor_narrowing_is_not_reported() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 1000], [600, 0, 600, 400], [600, 400, 300, 400], \
+                 [300, 400, 300, 500], [300, 500, 600, 500], [600, 500, 600, 1000]]",
+            ));
+            assert!(issues.is_empty(), "{:?}", issues);
+        }
+
+        #[test]
+        fn blocked_course_has_no_route() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 1000], [600, 0, 600, 1000], [200, 500, 600, 500]]",
+            ));
+            assert_eq!(
+                messages(&issues, Severity::Error),
+                ["no route for the cart from start (400, 100) to goal (400, 900)"]
+            );
+        }
+
+        #[test]
+        fn walls_beyond_the_course_are_an_island() {
+            let issues = check_track(&track(
+                "[[200, 0, 200, 1000], [600, 0, 600, 1000], \
+                 [650, 400, 750, 400], [750, 400, 750, 500], [750, 500, 650, 500], [650, 500, 650, 400]]",
+            ));
+            assert_eq!(
+                messages(&issues, Severity::Warning),
+                ["walls[2, 3, 4, 5] form an island the cart can never reach"]
+            );
+        }
+
+        #[test]
+        fn start_outside_the_walls_is_an_error() {
+            let mut track = track("[[200, 0, 200, 1000], [600, 0, 600, 1000]]");
+            track.start = Point::new(400.0, -100.0);
+            let issues = check_track(&track);
+            assert_eq!(
+                messages(&issues, Severity::Error),
+                ["start (400, -100) lies outside the walls"]
+            );
+        }
+    }
+}
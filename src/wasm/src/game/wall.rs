@@ -55,7 +55,7 @@ pub mod wall {
         ///
         /// # Returns
         /// Nothing (unit type)
-        fn draw(&self, renderer: &Renderer) {
+        fn draw(&self, renderer: &dyn Renderer) {
             renderer.line(
                 &Point {
                     x: self.state_machine.context().p.x,
@@ -69,49 +69,154 @@ pub mod wall {
         }
     }
 
-    /// Static wall data defining the game level geometry.
-    /// Each tuple represents (x1, y1, x2, y2) coordinates for wall line segments.
-    /// The walls form the boundaries and obstacles of the game world.
-    pub const WALLS_DATA: [(f32, f32, f32, f32); 32] = [
-        // left wall
-        (100.0, 0.0, 100.0, 1000.0),
-        (100.0, 1000.0, 200.0, 1400.0),
-        (200.0, 1400.0, 50.0, 2200.0),
-        (50.0, 2200.0, 400.0, 2900.0),
-        (400.0, 2900.0, 400.0, 3700.0),
-        (400.0, 3700.0, 100.0, 3700.0),
-        (100.0, 3700.0, 100.0, 6900.0),
-        (100.0, 6900.0, 200.0, 7200.0),
-        (200.0, 7200.0, 100.0, 7600.0),
-        (100.0, 7600.0, 100.0, 9900.0),
-        // right wall
-        (700.0, 0.0, 700.0, 1000.0),
-        (700.0, 1000.0, 350.0, 2200.0),
-        (350.0, 2200.0, 700.0, 2900.0),
-        (700.0, 2900.0, 700.0, 3700.0),
-        (700.0, 3700.0, 700.0, 3700.0),
-        (700.0, 3700.0, 700.0, 6900.0),
-        (700.0, 6900.0, 600.0, 7200.0),
-        (600.0, 7200.0, 700.0, 7600.0),
-        (700.0, 7600.0, 700.0, 9900.0),
-        // left wall in island1
-        (450.0, 4600.0, 250.0, 5200.0),
-        (250.0, 5200.0, 550.0, 5900.0),
-        // right wall in island1
-        (450.0, 4600.0, 600.0, 5200.0),
-        (600.0, 5200.0, 550.0, 5900.0),
-        // left wall in island2
-        (200.0, 5400.0, 200.0, 6550.0),
-        (200.0, 6550.0, 550.0, 6550.0),
-        // right wall in island2
-        (200.0, 5400.0, 550.0, 6550.0),
-        // left wall in island3
-        (400.0, 7400.0, 250.0, 7800.0),
-        (250.0, 7800.0, 250.0, 8200.0),
-        (250.0, 8200.0, 400.0, 8600.0),
-        // right wall in island3
-        (400.0, 7400.0, 550.0, 7800.0),
-        (550.0, 7800.0, 550.0, 8200.0),
-        (550.0, 8200.0, 400.0, 8600.0),
-    ];
+    /// Length of course covered by one bucket of a `WallIndex`
+    const BUCKET_LENGTH: f32 = 100.0;
+
+    /// Broad phase for wall collision: the walls of a course bucketed by
+    /// distance along the course.
+    ///
+    /// Walls all scroll together, so the index is built once from their start
+    /// positions and every query is shifted by how far the first wall moved.
+    pub struct WallIndex {
+        /// `y` of the first wall's `p` when the index was built
+        origin: f32,
+        /// Course `y` where the first bucket starts
+        bottom: f32,
+        buckets: Vec<Vec<usize>>,
+    }
+    impl WallIndex {
+        /// Indexes `walls` at their current positions
+        pub fn new(walls: &[Wall]) -> Self {
+            let bottom = walls
+                .iter()
+                .map(|wall| wall.p().y.min(wall.q().y))
+                .fold(f32::MAX, f32::min);
+            let top = walls
+                .iter()
+                .map(|wall| wall.p().y.max(wall.q().y))
+                .fold(f32::MIN, f32::max);
+            let bottom = if walls.is_empty() { 0.0 } else { bottom };
+            let count = if walls.is_empty() {
+                0
+            } else {
+                ((top - bottom) / BUCKET_LENGTH).floor() as usize + 1
+            };
+            let mut buckets = vec![vec![]; count];
+            for (i, wall) in walls.iter().enumerate() {
+                let first =
+                    ((wall.p().y.min(wall.q().y) - bottom) / BUCKET_LENGTH).floor() as usize;
+                let last = ((wall.p().y.max(wall.q().y) - bottom) / BUCKET_LENGTH).floor() as usize;
+                for bucket in &mut buckets[first..=last] {
+                    bucket.push(i);
+                }
+            }
+            WallIndex {
+                origin: walls.first().map_or(0.0, |wall| wall.p().y),
+                bottom,
+                buckets,
+            }
+        }
+
+        ///
+        /// Finds the walls that may reach into a band of the screen
+        ///
+        /// # Arguments
+        /// * `walls` - The walls this index was built from, at their current positions
+        /// * `low` - Bottom of the band
+        /// * `high` - Top of the band
+        ///
+        /// # Returns
+        /// Each wall whose `y` extent overlaps the band, once, in the order of `walls`
+        pub fn near<'a>(&self, walls: &'a [Wall], low: f32, high: f32) -> Vec<&'a Wall> {
+            let Some(first_wall) = walls.first() else {
+                return vec![];
+            };
+            let offset = first_wall.p().y - self.origin;
+            let first = ((low - offset - self.bottom) / BUCKET_LENGTH)
+                .floor()
+                .max(0.0) as usize;
+            let last = ((high - offset - self.bottom) / BUCKET_LENGTH).floor();
+            if last < 0.0 || first >= self.buckets.len() {
+                return vec![];
+            }
+            let last = (last as usize).min(self.buckets.len() - 1);
+            let mut found: Vec<usize> = self.buckets[first..=last].concat();
+            found.sort_unstable();
+            found.dedup();
+            found
+                .into_iter()
+                .map(|i| &walls[i])
+                .filter(|wall| {
+                    wall.p().y.min(wall.q().y) <= high && wall.p().y.max(wall.q().y) >= low
+                })
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// A wall in bucket 0, one over buckets 1 to 3 and one in bucket 4,
+        /// all scrolling down by `speed` each step
+        fn walls(speed: f32) -> Vec<Wall> {
+            let velocity = Velocity { x: 0.0, y: speed };
+            [
+                (0.0, 0.0, 0.0, 50.0),
+                (100.0, 120.0, 100.0, 380.0),
+                (200.0, 450.0, 200.0, 400.0),
+            ]
+            .iter()
+            .map(|&(x1, y1, x2, y2)| Wall::new(Point::new(x1, y1), Point::new(x2, y2), velocity))
+            .collect()
+        }
+
+        /// The `x` of each wall `near` finds, which tells the walls apart
+        fn near_x(index: &WallIndex, walls: &[Wall], low: f32, high: f32) -> Vec<f32> {
+            index
+                .near(walls, low, high)
+                .iter()
+                .map(|wall| wall.p().x)
+                .collect()
+        }
+
+        #[test]
+        fn wall_over_several_buckets_is_found_once() {
+            let walls = walls(0.0);
+            let index = WallIndex::new(&walls);
+            assert_eq!(near_x(&index, &walls, 110.0, 390.0), [100.0]);
+            assert_eq!(near_x(&index, &walls, 0.0, 500.0), [0.0, 100.0, 200.0]);
+        }
+
+        #[test]
+        fn band_edges_touching_a_wall_find_it() {
+            let walls = walls(0.0);
+            let index = WallIndex::new(&walls);
+            assert_eq!(near_x(&index, &walls, 50.0, 50.0), [0.0]);
+            assert_eq!(near_x(&index, &walls, 51.0, 119.0), Vec::<f32>::new());
+            assert_eq!(near_x(&index, &walls, 380.0, 400.0), [100.0, 200.0]);
+            assert_eq!(near_x(&index, &walls, 450.0, 1000.0), [200.0]);
+        }
+
+        #[test]
+        fn band_beyond_the_course_finds_nothing() {
+            let walls = walls(0.0);
+            let index = WallIndex::new(&walls);
+            assert!(index.near(&walls, -100.0, -1.0).is_empty());
+            assert!(index.near(&walls, 451.0, 1000.0).is_empty());
+            assert!(WallIndex::new(&[]).near(&[], 0.0, 1000.0).is_empty());
+        }
+
+        #[test]
+        fn queries_follow_the_scrolled_walls() {
+            let mut walls = walls(150.0);
+            let index = WallIndex::new(&walls);
+            walls.iter_mut().for_each(|wall| wall.update());
+            assert_eq!(walls[1].p().y, -30.0);
+            assert_eq!(near_x(&index, &walls, -40.0, -35.0), Vec::<f32>::new());
+            assert_eq!(near_x(&index, &walls, -40.0, -30.0), [100.0]);
+            assert_eq!(near_x(&index, &walls, 240.0, 260.0), [200.0]);
+            assert_eq!(near_x(&index, &walls, -150.0, -150.0), [0.0]);
+        }
+    }
 }
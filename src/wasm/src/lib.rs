@@ -1,9 +1,11 @@
 #[macro_use]
 mod browser;
-mod engine;
+pub mod engine;
 mod game;
 mod sound;
 
+pub use game::track_check::track_check::{Issue, Severity, check_track_file};
+
 use engine::GameLoop;
 use game::GameStage;
 use wasm_bindgen::prelude::*;
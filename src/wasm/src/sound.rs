@@ -46,6 +46,15 @@ pub fn play_sound(ctx: &AudioContext, buffer: &AudioBuffer, looping: Looping) ->
         .map_err(|err| anyhow!("Could not start sound! {:#?}", err))
 }
 
+pub fn play_sound_at_rate(ctx: &AudioContext, buffer: &AudioBuffer, rate: f32) -> Result<()> {
+    let track_source = create_track_source(ctx, buffer)?;
+    track_source.playback_rate().set_value(rate);
+
+    track_source
+        .start()
+        .map_err(|err| anyhow!("Could not start sound! {:#?}", err))
+}
+
 pub async fn decode_audio_data(
     ctx: &AudioContext,
     array_buffer: &ArrayBuffer,